@@ -0,0 +1,41 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define COUNTER_KEY 0x60
+#define TOTAL_KEY 0x61
+#define LAST_KEY 0x62
+#define HITS_MAP 5
+
+// Several instances of this program can run concurrently and they all update
+// the same counters without losing any increments.
+BPF_MAP_DEF(hits, HITS_MAP, BPF_MAP_TYPE_HASH, sizeof(uint32_t),
+            sizeof(uint32_t), 4);
+
+int test_atomics(void *ctx)
+{
+    uint32_t previous = 0;
+    bpf_store_global_fetch_add(COUNTER_KEY, 1, &previous);
+
+    // Classic compare-and-swap loop updating the maximum.
+    uint32_t now = bpf_ztimer_now();
+    uint32_t last;
+    do {
+        bpf_fetch_global(LAST_KEY, &last);
+    } while (last < now && !bpf_store_global_cas(LAST_KEY, last, now));
+
+    uint32_t key = 1;
+    bpf_map_fetch_add_elem(HITS_MAP, &key, 1, NULL);
+
+    // Updates spanning several keys need to be protected by a mutex.
+    if (bpf_mutex_lock("totals", 100) < 0) {
+        bpf_printf("Failed to lock the mutex\n");
+        return -1;
+    }
+    uint32_t total;
+    bpf_fetch_global(TOTAL_KEY, &total);
+    bpf_store_global(TOTAL_KEY, total + previous);
+    bpf_mutex_unlock("totals");
+
+    bpf_printf("Counter was %d\n", previous);
+    return previous;
+}
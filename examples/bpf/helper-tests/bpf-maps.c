@@ -0,0 +1,46 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define READINGS_MAP 1
+#define COUNTERS_MAP 2
+#define RUNS_MAP 3
+
+// Keeps the last 8 readings in an array, counts the occurrences of each
+// reading in a hash map and the number of runs of the program in a per-slot
+// array. The maps can be inspected from the host using the /maps endpoints.
+BPF_MAP_DEF(readings, READINGS_MAP, BPF_MAP_TYPE_ARRAY, sizeof(uint32_t),
+            sizeof(uint32_t), 8);
+BPF_MAP_DEF(counters, COUNTERS_MAP, BPF_MAP_TYPE_HASH, sizeof(uint32_t),
+            sizeof(uint32_t), 16);
+BPF_MAP_DEF(runs, RUNS_MAP, BPF_MAP_TYPE_PER_SLOT_ARRAY, sizeof(uint32_t),
+            sizeof(uint32_t), 1);
+
+int test_bpf_maps(void *ctx)
+{
+    uint32_t zero = 0;
+    uint32_t run_count = 0;
+    bpf_map_lookup_elem(RUNS_MAP, &zero, &run_count);
+    run_count++;
+    bpf_map_update_elem(RUNS_MAP, &zero, &run_count, BPF_ANY);
+
+    for (uint32_t i = 0; i < 8; i++) {
+        uint32_t reading = (bpf_ztimer_now() + i) % 4;
+        bpf_map_update_elem(READINGS_MAP, &i, &reading, BPF_ANY);
+
+        uint32_t count = 0;
+        if (bpf_map_lookup_elem(COUNTERS_MAP, &reading, &count) < 0) {
+            count = 0;
+        }
+        count++;
+        bpf_map_update_elem(COUNTERS_MAP, &reading, &count, BPF_ANY);
+    }
+
+    uint32_t missing = 42;
+    if (bpf_map_delete_elem(COUNTERS_MAP, &missing) == 0) {
+        bpf_printf("Deleted a key that was never inserted\n");
+        return -1;
+    }
+
+    bpf_printf("Program executed %d times\n", run_count);
+    return run_count;
+}
@@ -0,0 +1,20 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define WINDOW_SIZE 4
+
+// A small library program that is intended to be loaded into its own SUIT
+// slot and called by other programs using bpf_call_slot. It clamps all
+// readings in the argument buffer into the [0, 100] range in place and
+// returns their average.
+int filter(uint32_t *readings)
+{
+    uint32_t sum = 0;
+    for (int i = 0; i < WINDOW_SIZE; i++) {
+        if (readings[i] > 100) {
+            readings[i] = 100;
+        }
+        sum += readings[i];
+    }
+    return sum / WINDOW_SIZE;
+}
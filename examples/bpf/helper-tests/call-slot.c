@@ -0,0 +1,23 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define WINDOW_SIZE 4
+// The slot into which call-slot-filter.c needs to be loaded before this
+// program is executed.
+#define FILTER_SLOT 1
+
+int test_call_slot(void *ctx)
+{
+    uint32_t readings[WINDOW_SIZE] = {12, 250, 40, 80};
+
+    uint64_t average;
+    if (bpf_call_slot(FILTER_SLOT, readings, sizeof(readings), &average) < 0) {
+        bpf_printf("Call into slot %d failed\n", FILTER_SLOT);
+        return -1;
+    }
+
+    bpf_printf("Filtered readings: [%d, %d, %d, %d]\n", readings[0],
+               readings[1], readings[2], readings[3]);
+    bpf_printf("Average: %d\n", (uint32_t)average);
+    return average;
+}
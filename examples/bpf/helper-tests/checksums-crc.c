@@ -0,0 +1,65 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define DATA_SIZE 320
+
+// CRC-16/CCITT-FALSE implemented in the VM, the same variant as the native
+// bpf_crc16 helper (RIOT crc16_ccitt_false_calc).
+static uint32_t crc16_in_vm(const uint8_t *data, uint32_t len)
+{
+    uint16_t crc = 0xFFFF;
+    for (uint32_t i = 0; i < len; i++) {
+        crc ^= (uint16_t)data[i] << 8;
+        for (int bit = 0; bit < 8; bit++) {
+            crc = (crc & 0x8000) ? (uint16_t)((crc << 1) ^ 0x1021)
+                                 : (uint16_t)(crc << 1);
+        }
+    }
+    return crc;
+}
+
+// The standard CRC-32 implemented in the VM, the same algorithm as the native
+// bpf_crc32 helper (see micro-bpf-server/src/infra/checksums.rs).
+static uint32_t crc32_in_vm(const uint8_t *data, uint32_t len)
+{
+    uint32_t crc = 0xFFFFFFFF;
+    for (uint32_t i = 0; i < len; i++) {
+        crc ^= data[i];
+        for (int bit = 0; bit < 8; bit++) {
+            uint32_t mask = -(crc & 1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    return ~crc;
+}
+
+// Compares the in-VM CRCs against the native helpers on the same random data
+// and reports the time that each of them took. The native implementations can
+// be benchmarked on their own using the /native/checksum endpoint.
+int test_checksums_crc(void *ctx)
+{
+    uint8_t data[DATA_SIZE];
+    if (bpf_random_bytes(data, sizeof(data)) < 0) {
+        return -1;
+    }
+
+    uint32_t start = bpf_ztimer_now();
+    uint32_t crc16 = crc16_in_vm(data, sizeof(data));
+    uint32_t crc16_time = bpf_ztimer_now() - start;
+    start = bpf_ztimer_now();
+    uint32_t crc16_native = bpf_crc16(data, sizeof(data));
+    uint32_t crc16_native_time = bpf_ztimer_now() - start;
+
+    start = bpf_ztimer_now();
+    uint32_t crc32 = crc32_in_vm(data, sizeof(data));
+    uint32_t crc32_time = bpf_ztimer_now() - start;
+    start = bpf_ztimer_now();
+    uint32_t crc32_native = bpf_crc32(data, sizeof(data));
+    uint32_t crc32_native_time = bpf_ztimer_now() - start;
+
+    bpf_printf("crc16 in VM: %x in %d us\n", crc16, crc16_time);
+    bpf_printf("crc16 helper: %x in %d us\n", crc16_native, crc16_native_time);
+    bpf_printf("crc32 in VM: %x in %d us\n", crc32, crc32_time);
+    bpf_printf("crc32 helper: %x in %d us\n", crc32_native, crc32_native_time);
+    return (crc16 == crc16_native && crc32 == crc32_native) ? 0 : -1;
+}
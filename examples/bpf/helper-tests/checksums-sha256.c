@@ -0,0 +1,37 @@
+#include <stdint.h>
+#include "../helpers.h"
+#include "sha256.h"
+
+// Smaller than in checksums.c so that the data, the hash context and the
+// digests fit into the VM stack together.
+#define DATA_SIZE 160
+
+// Compares SHA-256 computed in the VM against the native bpf_sha256 helper on
+// the same random data and reports the time that each of them took. The
+// native implementation can be benchmarked on its own using the
+// /native/checksum endpoint.
+int test_checksums_sha256(void *ctx)
+{
+    uint8_t data[DATA_SIZE];
+    if (bpf_random_bytes(data, sizeof(data)) < 0) {
+        return -1;
+    }
+
+    uint8_t in_vm[SHA256_DIGEST_SIZE];
+    uint32_t start = bpf_ztimer_now();
+    sha256_ctx_t sha;
+    sha256_init(&sha);
+    sha256_update(&sha, data, sizeof(data));
+    sha256_final(&sha, in_vm);
+    uint32_t in_vm_time = bpf_ztimer_now() - start;
+
+    uint8_t native[BPF_SHA256_DIGEST_LENGTH];
+    start = bpf_ztimer_now();
+    bpf_sha256(data, sizeof(data), native);
+    uint32_t native_time = bpf_ztimer_now() - start;
+
+    bpf_printf("sha256 in VM: %x%x... in %d us\n", in_vm[0], in_vm[1], in_vm_time);
+    bpf_printf("sha256 helper: %x%x... in %d us\n", native[0], native[1],
+               native_time);
+    return digests_equal(in_vm, native) ? 0 : -1;
+}
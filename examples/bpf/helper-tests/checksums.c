@@ -0,0 +1,53 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define DATA_SIZE 320
+
+// Fletcher16 implemented in the VM, the same algorithm as the native
+// benchmark in micro-bpf-server/src/ffi/fletcher16_benchmarks.c.
+static uint32_t fletcher16_in_vm(const uint8_t *data, uint32_t len)
+{
+    uint16_t sum1 = 0;
+    uint16_t sum2 = 0;
+    for (uint32_t i = 0; i < len; i++) {
+        sum1 = (sum1 + data[i]) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    return (sum2 << 8) | sum1;
+}
+
+// Compares the in-VM Fletcher16 against the native helper on the same random
+// data and exercises the remaining checksum and hashing helpers. The native
+// implementations can be benchmarked on their own using the /native/checksum
+// endpoint of the testing server.
+int test_checksums(void *ctx)
+{
+    uint8_t data[DATA_SIZE];
+    if (bpf_random_bytes(data, sizeof(data)) < 0) {
+        return -1;
+    }
+
+    uint32_t start = bpf_ztimer_now();
+    uint32_t in_vm = fletcher16_in_vm(data, sizeof(data));
+    uint32_t in_vm_time = bpf_ztimer_now() - start;
+
+    start = bpf_ztimer_now();
+    uint32_t native = bpf_fletcher16(data, sizeof(data));
+    uint32_t native_time = bpf_ztimer_now() - start;
+
+    bpf_printf("fletcher16 in VM: %x in %d us\n", in_vm, in_vm_time);
+    bpf_printf("fletcher16 helper: %x in %d us\n", native, native_time);
+
+    bpf_printf("crc16: %x\n", (uint32_t)bpf_crc16(data, sizeof(data)));
+    bpf_printf("crc32: %x\n", (uint32_t)bpf_crc32(data, sizeof(data)));
+    bpf_printf("fletcher32: %x\n", (uint32_t)bpf_fletcher32(data, sizeof(data)));
+
+    uint8_t digest[BPF_SHA256_DIGEST_LENGTH];
+    bpf_sha256(data, sizeof(data), digest);
+    bpf_printf("sha256: %x%x...\n", digest[0], digest[1]);
+
+    char key[] = "device-secret";
+    bpf_hmac_sha256(key, sizeof(key) - 1, data, sizeof(data), digest);
+    bpf_printf("hmac-sha256: %x%x...\n", digest[0], digest[1]);
+    return 0;
+}
@@ -0,0 +1,28 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define GATEWAY 0
+#define COAP_CODE_CHANGED 0x44
+
+// Reports a reading to the gateway declared as the first CoAP destination of
+// the execution request (with the /readings URI) and prints its reply. On
+// native it can be tested against a local CoAP server, e.g. aiocoap-fileserver.
+int test_coap_client(void *ctx)
+{
+    uint32_t reading = bpf_ztimer_now() % 100;
+    int code = bpf_coap_client_send(GATEWAY, BPF_COAP_POST, "/readings",
+                                    &reading, sizeof(reading));
+    if (code < 0) {
+        bpf_printf("Failed to send the reading\n");
+        return -1;
+    }
+
+    char reply[32];
+    int len = bpf_coap_client_read_response(reply, sizeof(reply) - 1);
+    if (len > sizeof(reply) - 1) {
+        len = sizeof(reply) - 1;
+    }
+    reply[len] = '\0';
+    bpf_printf("Gateway replied with code %d: %s\n", code, reply);
+    return code == COAP_CODE_CHANGED ? 0 : -1;
+}
@@ -0,0 +1,48 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define COAP_OPT_FINISH_PAYLOAD (0x0001)
+#define COAP_METHOD_GET (1)
+#define COAP_FORMAT_TEXT (0)
+
+/* Logs the contents of the request and responds with the length of its
+ * payload. All inspection helpers need to be called before the response is
+ * initialised as bpf_gcoap_resp_init overwrites the request options. */
+int coap_test(bpf_coap_ctx_t *gcoap)
+{
+    unsigned method = bpf_coap_get_method(gcoap);
+    bpf_printf("Request method: %d\n", method);
+
+    char path[64];
+    if (bpf_coap_get_uri_path(gcoap, path, sizeof(path)) > 0) {
+        bpf_printf("URI path: %s\n", path);
+    }
+
+    char query[32];
+    unsigned i = 0;
+    ssize_t len;
+    while ((len = bpf_coap_get_uri_query(gcoap, i, query, sizeof(query) - 1)) >= 0) {
+        query[len < sizeof(query) - 1 ? len : sizeof(query) - 1] = '\0';
+        bpf_printf("URI query %d: %s\n", i, query);
+        i++;
+    }
+
+    bpf_printf("Content format: %d\n", bpf_coap_get_content_format(gcoap));
+
+    uint8_t payload[16];
+    ssize_t payload_len = bpf_coap_get_payload(gcoap, payload, sizeof(payload));
+    bpf_printf("Payload length: %d\n", payload_len);
+
+    unsigned code = method == COAP_METHOD_GET ? (2 << 5) | 5 : (4 << 5) | 5;
+    bpf_gcoap_resp_init(gcoap, code);
+    bpf_coap_add_format(gcoap, COAP_FORMAT_TEXT);
+    ssize_t pdu_len = bpf_coap_opt_finish(gcoap, COAP_OPT_FINISH_PAYLOAD);
+
+    char response[12];
+    size_t str_len = bpf_fmt_u32_dec(response, payload_len < 0 ? 0 : payload_len);
+    ssize_t written = bpf_coap_add_payload(gcoap, response, str_len);
+    if (written < 0) {
+        return -1;
+    }
+    return pdu_len + written;
+}
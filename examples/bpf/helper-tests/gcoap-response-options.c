@@ -0,0 +1,31 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define COAP_OPT_FINISH_PAYLOAD (0x0001)
+#define COAP_OPT_ETAG (4)
+#define COAP_OPT_CONTENT_FORMAT (12)
+#define COAP_OPT_MAX_AGE (14)
+#define COAP_FORMAT_TEXT (0)
+
+/* Builds a response with an ETag, Content-Format and Max-Age options. The
+ * options need to be added in the order of their option numbers. */
+int coap_test(bpf_coap_ctx_t *gcoap)
+{
+    bpf_gcoap_resp_init(gcoap, (2 << 5) | 5);
+
+    uint8_t etag[4] = {0xde, 0xad, 0xbe, 0xef};
+    if (bpf_coap_add_option(gcoap, COAP_OPT_ETAG, etag, sizeof(etag)) < 0) {
+        return -1;
+    }
+    bpf_coap_add_uint_option(gcoap, COAP_OPT_CONTENT_FORMAT, COAP_FORMAT_TEXT);
+    bpf_coap_add_uint_option(gcoap, COAP_OPT_MAX_AGE, 30);
+
+    ssize_t pdu_len = bpf_coap_opt_finish(gcoap, COAP_OPT_FINISH_PAYLOAD);
+
+    const char response[] = "hello";
+    ssize_t written = bpf_coap_add_payload(gcoap, response, sizeof(response) - 1);
+    if (written < 0) {
+        return -1;
+    }
+    return pdu_len + written;
+}
@@ -0,0 +1,39 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+char STATUS[] = "Status: OK";
+char LONG_LINE[] = "This line is too long for the display";
+
+// Opens the second row of the LCD display, so that another program (e.g. the
+// display update program of the weather station restricted to the first row)
+// can print at the same time. Returns 0 if all checks pass, otherwise the
+// number of the first failing check.
+int lcd_rows_test(void *ctx)
+{
+    uint64_t dev = bpf_hd44780_open_rows(1, 1);
+    if (dev == 0) {
+        return 1;
+    }
+    // Opening the same rows again gives back the same handle.
+    if (bpf_hd44780_open_rows(1, 1) != dev) {
+        return 2;
+    }
+
+    bpf_hd44780_clear(dev);
+    if (bpf_hd44780_print(dev, STATUS) != sizeof(STATUS) - 1) {
+        return 3;
+    }
+
+    // The first row isn't writable through the handle.
+    if (bpf_hd44780_set_cursor(dev, 0, 0) >= 0) {
+        return 4;
+    }
+
+    // The text is cut off at the end of the row instead of wrapping around.
+    bpf_hd44780_set_cursor(dev, 0, 1);
+    int printed = bpf_hd44780_print(dev, LONG_LINE);
+    if (printed <= 0 || printed >= sizeof(LONG_LINE) - 1) {
+        return 5;
+    }
+    return 0;
+}
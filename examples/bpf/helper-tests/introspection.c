@@ -0,0 +1,51 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define LCD_ROWS 2
+
+// Logs the execution context of the program and prints it on the row of the
+// LCD given by the slot the program is loaded into, so that the same program
+// deployed into several slots doesn't overwrite its own output.
+// Returns 0 if all checks pass, otherwise the number of the first failing
+// check.
+int test_introspection(void *ctx)
+{
+    int32_t slot = bpf_get_slot();
+    if (slot < 0) {
+        return 1;
+    }
+    int64_t job_id = bpf_get_job_id();
+    if (job_id < 0) {
+        return 2;
+    }
+    int32_t model = bpf_get_execution_model();
+    int32_t worker = bpf_get_worker();
+    // Only the long-running programs execute on the VM workers.
+    if ((model == BPF_EXECUTION_LONG_RUNNING) != (worker >= 0)) {
+        return 3;
+    }
+
+    int64_t budget = bpf_get_remaining_budget_ms();
+    if (budget < 0) {
+        bpf_log(BPF_LOG_INFO, "Model %d on worker %d, no time budget", model,
+                worker);
+    } else {
+        bpf_log(BPF_LOG_INFO, "Model %d on worker %d, %u ms left", model,
+                worker, budget);
+    }
+
+    char line[17];
+    uint64_t args[] = {slot, job_id};
+    if (bpf_snprintf(line, sizeof(line), "slot %d job %u", args, 2) < 0) {
+        return 4;
+    }
+    // Only the row of the program is opened, so the copies of the program
+    // loaded into other slots can use the remaining ones at the same time.
+    uint64_t dev = bpf_hd44780_open_rows(slot % LCD_ROWS, 1);
+    if (dev == 0) {
+        return 5;
+    }
+    bpf_hd44780_clear(dev);
+    bpf_hd44780_print(dev, line);
+    return 0;
+}
@@ -0,0 +1,21 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+// Logs a few records at different levels. Depending on the log level of the
+// server, some of them are discarded. Each record printed on the console is
+// tagged with the slot of the program and the ID of the job, e.g.
+// INFO:bpf -- [slot 0 job 3] Measured 21 C
+int test_log(void *ctx)
+{
+    char unit[] = "C";
+    bpf_log(BPF_LOG_INFO, "Measured %d %s", 21, unit);
+    bpf_log(BPF_LOG_DEBUG, "Raw reading: %#06x", 0x2a);
+    bpf_log(BPF_LOG_WARNING, "Battery at %u%%", 15);
+
+    // Rejected: %n would write into memory.
+    int written = 0;
+    if (bpf_log(BPF_LOG_ERROR, "Oops%n", &written) < 0) {
+        bpf_log(BPF_LOG_ERROR, "Format string with %%n rejected");
+    }
+    return 0;
+}
@@ -0,0 +1,42 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define EMA_MAP 6
+#define FRAC_BITS 8
+
+// Moving averages of the readings of several sensors, the key is the sensor
+// index. The averages are kept in Q8 so that they don't lose the fractions.
+BPF_MAP_DEF(averages, EMA_MAP, BPF_MAP_TYPE_ARRAY, sizeof(uint32_t),
+            sizeof(int32_t), 4);
+
+// Feeds a few readings into the exponential moving average with alpha = 1/4.
+// Each update rounds to nearest (error at most 0.5 in Q8, i.e. 1/512).
+// Returns 0 if all checks pass, otherwise the number of the first failing
+// check.
+int test_math_ema(void *ctx)
+{
+    uint32_t sensor = 0;
+    int32_t zero = 0;
+    bpf_map_update_elem(EMA_MAP, &sensor, &zero, 0);
+
+    uint32_t alpha = BPF_EMA_ALPHA(1, 4);
+    int32_t average = 0;
+    int32_t readings[] = {20, 20, 20, 20};
+    for (int i = 0; i < 4; i++) {
+        if (bpf_ema_update(EMA_MAP, &sensor, Q_FROM_INT(readings[i], FRAC_BITS),
+                           alpha, &average) < 0) {
+            return 1;
+        }
+    }
+    // 20 * (1 - 0.75^4) = 13.67 = 3500 in Q8.
+    bpf_printf("Average: %d/256\n", average);
+    if (average < 3499 || average > 3501) {
+        return 2;
+    }
+
+    // An alpha larger than 1.0 is rejected.
+    if (bpf_ema_update(EMA_MAP, &sensor, 0, BPF_EMA_ALPHA(2, 1), NULL) >= 0) {
+        return 3;
+    }
+    return 0;
+}
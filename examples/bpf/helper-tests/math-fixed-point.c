@@ -0,0 +1,53 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define FRAC_BITS 16
+
+// Tests the Q-format multiplication and division, which round to nearest
+// (error at most 0.5 LSB). Returns 0 if all checks pass, otherwise the number
+// of the first failing check.
+int test_math_fixed_point(void *ctx)
+{
+    int32_t one_and_half = Q_FROM_INT(3, FRAC_BITS) / 2;
+    int32_t two = Q_FROM_INT(2, FRAC_BITS);
+    int32_t result;
+
+    if (bpf_q_mul(one_and_half, two, FRAC_BITS, &result) != 0 ||
+        result != Q_FROM_INT(3, FRAC_BITS)) {
+        return 1;
+    }
+    if (bpf_q_div(Q_FROM_INT(3, FRAC_BITS), two, FRAC_BITS, &result) != 0 ||
+        result != one_and_half) {
+        return 2;
+    }
+    // 1/3 = 21845.33 in Q16, rounded to nearest.
+    if (bpf_q_div(Q_FROM_INT(1, FRAC_BITS), Q_FROM_INT(3, FRAC_BITS),
+                  FRAC_BITS, &result) != 0 ||
+        result != 21845) {
+        return 3;
+    }
+    // 2/3 = 43690.67 in Q16, rounded to nearest.
+    if (bpf_q_div(Q_FROM_INT(2, FRAC_BITS), Q_FROM_INT(3, FRAC_BITS),
+                  FRAC_BITS, &result) != 0 ||
+        result != 43691) {
+        return 4;
+    }
+    // 30000 * 30000 doesn't fit into Q16 and saturates.
+    if (bpf_q_mul(Q_FROM_INT(30000, FRAC_BITS), Q_FROM_INT(30000, FRAC_BITS),
+                  FRAC_BITS, &result) != 0 ||
+        result != INT32_MAX) {
+        return 5;
+    }
+    if (bpf_q_div(-one_and_half, 0, FRAC_BITS, &result) != 0 ||
+        result != INT32_MIN) {
+        return 6;
+    }
+    // More than 31 fractional bits are rejected, 0 * x = 0 is a valid result.
+    if (bpf_q_mul(one_and_half, two, 32, &result) != -1) {
+        return 7;
+    }
+    if (bpf_q_mul(0, two, FRAC_BITS, &result) != 0 || result != 0) {
+        return 8;
+    }
+    return 0;
+}
@@ -0,0 +1,43 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define FRAC_BITS 8
+
+// Tests the logarithm approximations: log2 rounds down (error < 1 LSB) and
+// log10 is within 1 LSB. Also shows the typical conversion of a power ratio
+// into decibels. Returns 0 if all checks pass, otherwise the number of the
+// first failing check.
+int test_math_log(void *ctx)
+{
+    if (bpf_log2_q(1, FRAC_BITS) != 0) {
+        return 1;
+    }
+    if (bpf_log2_q(1024, FRAC_BITS) != Q_FROM_INT(10, FRAC_BITS)) {
+        return 2;
+    }
+    // log2(3) = 1.58496 = 405.75 in Q8, rounded down.
+    if (bpf_log2_q(3, FRAC_BITS) != 405) {
+        return 3;
+    }
+    if (bpf_log10_q(1000, FRAC_BITS) != Q_FROM_INT(3, FRAC_BITS)) {
+        return 4;
+    }
+    // log10(2) = 0.30103 = 77.06 in Q8.
+    int32_t log10_2 = bpf_log10_q(2, FRAC_BITS);
+    if (log10_2 < 76 || log10_2 > 78) {
+        return 5;
+    }
+    if (bpf_log2_q(0, FRAC_BITS) != -1 || bpf_log10_q(8, 17) != -1) {
+        return 6;
+    }
+
+    // 10 * log10(power / reference) in Q8 decibels.
+    uint32_t power = 5000;
+    uint32_t reference = 50;
+    int32_t db = 10 * bpf_log10_q(power / reference, FRAC_BITS);
+    bpf_printf("Signal level: %d/256 dB\n", db);
+    if (db != Q_FROM_INT(20, FRAC_BITS)) {
+        return 7;
+    }
+    return 0;
+}
@@ -0,0 +1,34 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+// Tests the saturating arithmetic and the integer square root, which are all
+// exact. Returns 0 if all checks pass, otherwise the number of the first
+// failing check.
+int test_math_saturating(void *ctx)
+{
+    if (bpf_sat_mul(1000, 1000) != 1000000) {
+        return 1;
+    }
+    if (bpf_sat_mul(100000, 100000) != INT32_MAX) {
+        return 2;
+    }
+    if (bpf_sat_mul(-100000, 100000) != INT32_MIN) {
+        return 3;
+    }
+    if (bpf_sat_div(-7, 2) != -3) {
+        return 4;
+    }
+    if (bpf_sat_div(INT32_MIN, -1) != INT32_MAX) {
+        return 5;
+    }
+    if (bpf_sat_div(5, 0) != INT32_MAX || bpf_sat_div(-5, 0) != INT32_MIN) {
+        return 6;
+    }
+    if (bpf_isqrt(0) != 0 || bpf_isqrt(15) != 3 || bpf_isqrt(16) != 4) {
+        return 7;
+    }
+    if (bpf_isqrt(0xFFFFFFFFFFFFFFFFULL) != 0xFFFFFFFF) {
+        return 8;
+    }
+    return 0;
+}
@@ -0,0 +1,41 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+// Exercises the bounds-checked memory and string helpers. Returns 0 if all
+// checks pass, otherwise the number of the first failing check.
+int test_memory_string(void *ctx)
+{
+    char buffer[16];
+    bpf_memset(buffer, 'a', sizeof(buffer));
+    if (buffer[0] != 'a' || buffer[15] != 'a') {
+        return 1;
+    }
+
+    char expected[] = "aaaa";
+    if (bpf_memcmp(buffer, expected, 4) != 0) {
+        return 2;
+    }
+
+    char command[] = "temperature=21";
+    char key[8];
+    // Truncated to 7 bytes and null-terminated.
+    if (bpf_strncpy(key, command, sizeof(key)) != 7) {
+        return 3;
+    }
+    bpf_printf("Truncated key: %s\n", key);
+
+    char prefix[] = "temp";
+    if (bpf_strncmp(command, prefix, 4) != 0) {
+        return 4;
+    }
+    char other[] = "humidity";
+    if (bpf_strncmp(command, other, sizeof(other)) != 1) {
+        return 5;
+    }
+
+    // The helpers refuse to access memory outside of the program's regions.
+    if (bpf_memcmp(buffer, (void *)0x1000, 4) != BPF_CMP_INVALID) {
+        return 6;
+    }
+    return 0;
+}
@@ -0,0 +1,20 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define READINGS_QUEUE 1
+#define TIMEOUT_MS 5000
+
+// Long-running program that receives the readings sent by
+// message-queue-producer.c until no message arrives within the timeout. It
+// needs to be granted receive access to the queue READINGS_QUEUE in the
+// execution request.
+int test_queue_consumer(void *ctx)
+{
+    uint32_t reading;
+    uint32_t received = 0;
+    while (bpf_queue_receive(READINGS_QUEUE, &reading, TIMEOUT_MS) == 0) {
+        bpf_printf("Received reading: %d\n", reading);
+        received++;
+    }
+    return received;
+}
@@ -0,0 +1,21 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define READINGS_QUEUE 1
+#define PERIOD_MS 1000
+
+// Long-running program that periodically sends readings into a message queue.
+// It needs to be granted send access to the queue READINGS_QUEUE in the
+// execution request. See message-queue-consumer.c for the receiving end.
+int test_queue_producer(void *ctx)
+{
+    uint32_t start = bpf_ztimer_now();
+    for (uint32_t reading = 0; reading < 10; reading++) {
+        if (bpf_queue_send(READINGS_QUEUE, reading) < 0) {
+            bpf_printf("Queue %d is full, dropping reading %d\n",
+                       READINGS_QUEUE, reading);
+        }
+        bpf_ztimer_periodic_wakeup(&start, PERIOD_MS);
+    }
+    return 0;
+}
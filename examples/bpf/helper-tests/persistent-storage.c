@@ -0,0 +1,35 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define BOOT_COUNT_KEY 0
+#define CALIBRATION_KEY 1
+
+typedef struct {
+    int32_t offset;
+    uint32_t scale;
+} calibration_t;
+
+// Counts the executions of the program across reboots and initialises the
+// calibration values the first time it runs. The stored values can be
+// exported using the /storage/persistent endpoint.
+int test_persistent_storage(void *ctx)
+{
+    uint32_t runs = 0;
+    bpf_persist_fetch(BOOT_COUNT_KEY, &runs, sizeof(runs));
+    runs++;
+    if (bpf_persist_store(BOOT_COUNT_KEY, &runs, sizeof(runs)) < 0) {
+        bpf_printf("Failed to store the run count\n");
+        return -1;
+    }
+
+    calibration_t calibration;
+    if (bpf_persist_fetch(CALIBRATION_KEY, &calibration, sizeof(calibration)) !=
+        sizeof(calibration)) {
+        calibration.offset = -40;
+        calibration.scale = 100;
+        bpf_persist_store(CALIBRATION_KEY, &calibration, sizeof(calibration));
+    }
+
+    bpf_printf("Run %d, calibration offset: %d\n", runs, calibration.offset);
+    return runs;
+}
@@ -0,0 +1,44 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define SAMPLES_RINGBUF 4
+
+// Ring buffer of 256 bytes, each sample takes up 8 bytes plus the 2 byte
+// record header. The host receives the samples by observing the
+// /maps/ringbuf resource and frees up their space by consuming them through
+// /maps/ringbuf/consume.
+BPF_MAP_DEF(samples, SAMPLES_RINGBUF, BPF_MAP_TYPE_RINGBUF, 0, 0, 256);
+
+typedef struct {
+    uint32_t timestamp;
+    uint32_t value;
+} sample_t;
+
+int test_ringbuf_producer(void *ctx)
+{
+    sample_t sample;
+    uint32_t handle =
+        bpf_ringbuf_reserve(SAMPLES_RINGBUF, &sample, sizeof(sample));
+    if (handle == 0) {
+        bpf_printf("Ring buffer is full\n");
+        return -1;
+    }
+    sample.timestamp = bpf_now_ms();
+    sample.value = bpf_ztimer_now() % 100;
+    bpf_ringbuf_submit(handle, 0);
+
+    // Reservations that are no longer needed can be given back.
+    handle = bpf_ringbuf_reserve(SAMPLES_RINGBUF, &sample, sizeof(sample));
+    if (handle != 0) {
+        bpf_ringbuf_discard(handle);
+    }
+
+    // Small records can be written in one step, this one is only delivered
+    // with the next notification.
+    uint32_t marker = 0xCAFE;
+    if (bpf_ringbuf_output(SAMPLES_RINGBUF, &marker, sizeof(marker),
+                           BPF_RB_NO_WAKEUP) < 0) {
+        return -1;
+    }
+    return 0;
+}
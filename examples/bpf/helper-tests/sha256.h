@@ -0,0 +1,143 @@
+#ifndef SHA256_H
+#define SHA256_H
+
+#include <stdint.h>
+
+/* SHA-256 implemented in the VM for comparing against the native bpf_sha256
+ * and bpf_hmac_sha256 helpers. The message schedule is kept in a rolling
+ * window of 16 words so that the implementation fits into the VM stack. */
+
+#define SHA256_BLOCK_SIZE 64
+#define SHA256_DIGEST_SIZE 32
+
+typedef struct {
+    uint32_t state[8];
+    uint8_t block[SHA256_BLOCK_SIZE];
+    uint32_t block_len;
+    uint32_t total_len;
+} sha256_ctx_t;
+
+static const uint32_t SHA256_K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+};
+
+#define SHA256_ROTR(x, n) (((x) >> (n)) | ((x) << (32 - (n))))
+
+static inline void sha256_init(sha256_ctx_t *ctx)
+{
+    ctx->state[0] = 0x6a09e667;
+    ctx->state[1] = 0xbb67ae85;
+    ctx->state[2] = 0x3c6ef372;
+    ctx->state[3] = 0xa54ff53a;
+    ctx->state[4] = 0x510e527f;
+    ctx->state[5] = 0x9b05688c;
+    ctx->state[6] = 0x1f83d9ab;
+    ctx->state[7] = 0x5be0cd19;
+    ctx->block_len = 0;
+    ctx->total_len = 0;
+}
+
+static inline void sha256_transform(sha256_ctx_t *ctx)
+{
+    uint32_t w[16];
+    for (int i = 0; i < 16; i++) {
+        w[i] = (uint32_t)ctx->block[4 * i] << 24 |
+               (uint32_t)ctx->block[4 * i + 1] << 16 |
+               (uint32_t)ctx->block[4 * i + 2] << 8 |
+               (uint32_t)ctx->block[4 * i + 3];
+    }
+
+    uint32_t a = ctx->state[0], b = ctx->state[1], c = ctx->state[2],
+             d = ctx->state[3], e = ctx->state[4], f = ctx->state[5],
+             g = ctx->state[6], h = ctx->state[7];
+    for (int i = 0; i < 64; i++) {
+        if (i >= 16) {
+            // w[i & 15] still holds the word from 16 rounds ago.
+            uint32_t w15 = w[(i - 15) & 15];
+            uint32_t w2 = w[(i - 2) & 15];
+            uint32_t s0 = SHA256_ROTR(w15, 7) ^ SHA256_ROTR(w15, 18) ^ (w15 >> 3);
+            uint32_t s1 = SHA256_ROTR(w2, 17) ^ SHA256_ROTR(w2, 19) ^ (w2 >> 10);
+            w[i & 15] += s0 + w[(i - 7) & 15] + s1;
+        }
+        uint32_t sum1 = SHA256_ROTR(e, 6) ^ SHA256_ROTR(e, 11) ^ SHA256_ROTR(e, 25);
+        uint32_t choice = (e & f) ^ (~e & g);
+        uint32_t t1 = h + sum1 + choice + SHA256_K[i] + w[i & 15];
+        uint32_t sum0 = SHA256_ROTR(a, 2) ^ SHA256_ROTR(a, 13) ^ SHA256_ROTR(a, 22);
+        uint32_t majority = (a & b) ^ (a & c) ^ (b & c);
+        uint32_t t2 = sum0 + majority;
+        h = g;
+        g = f;
+        f = e;
+        e = d + t1;
+        d = c;
+        c = b;
+        b = a;
+        a = t1 + t2;
+    }
+    ctx->state[0] += a;
+    ctx->state[1] += b;
+    ctx->state[2] += c;
+    ctx->state[3] += d;
+    ctx->state[4] += e;
+    ctx->state[5] += f;
+    ctx->state[6] += g;
+    ctx->state[7] += h;
+}
+
+static inline void sha256_update_byte(sha256_ctx_t *ctx, uint8_t byte)
+{
+    ctx->block[ctx->block_len++] = byte;
+    ctx->total_len++;
+    if (ctx->block_len == SHA256_BLOCK_SIZE) {
+        sha256_transform(ctx);
+        ctx->block_len = 0;
+    }
+}
+
+static inline void sha256_update(sha256_ctx_t *ctx, const uint8_t *data,
+                                 uint32_t len)
+{
+    for (uint32_t i = 0; i < len; i++) {
+        sha256_update_byte(ctx, data[i]);
+    }
+}
+
+static inline void sha256_final(sha256_ctx_t *ctx, uint8_t *digest)
+{
+    uint64_t bit_len = (uint64_t)ctx->total_len * 8;
+    sha256_update_byte(ctx, 0x80);
+    while (ctx->block_len != SHA256_BLOCK_SIZE - 8) {
+        sha256_update_byte(ctx, 0);
+    }
+    for (int i = 7; i >= 0; i--) {
+        sha256_update_byte(ctx, (uint8_t)(bit_len >> (8 * i)));
+    }
+    for (int i = 0; i < 8; i++) {
+        digest[4 * i] = (uint8_t)(ctx->state[i] >> 24);
+        digest[4 * i + 1] = (uint8_t)(ctx->state[i] >> 16);
+        digest[4 * i + 2] = (uint8_t)(ctx->state[i] >> 8);
+        digest[4 * i + 3] = (uint8_t)ctx->state[i];
+    }
+}
+
+static inline int digests_equal(const uint8_t *a, const uint8_t *b)
+{
+    for (int i = 0; i < SHA256_DIGEST_SIZE; i++) {
+        if (a[i] != b[i]) {
+            return 0;
+        }
+    }
+    return 1;
+}
+
+#endif /* SHA256_H */
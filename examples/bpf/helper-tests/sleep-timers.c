@@ -0,0 +1,32 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define BLINK_PERIOD_MS 500
+
+// Long-running program that prints a heartbeat twice per second until it is
+// stopped (POST `<admin token>;<slot>` to /long-running/stop) or its time
+// budget runs out, in which case bpf_timer_wait returns -1 and the program
+// exits.
+int test_sleep_timers(void *ctx)
+{
+    bpf_printf("Sleeping for 100 ms\n");
+    if (bpf_sleep_ms(100) < 0) {
+        return -1;
+    }
+
+    uint32_t timer = bpf_timer_create(BLINK_PERIOD_MS, 1);
+    if (timer == 0) {
+        bpf_printf("Failed to create the timer\n");
+        return -1;
+    }
+
+    uint32_t beats = 0;
+    while (bpf_timer_wait(timer) == 0) {
+        beats++;
+        bpf_printf("Heartbeat %d at %d ms\n", beats, bpf_now_ms());
+    }
+
+    bpf_printf("Stopped after %d heartbeats\n", beats);
+    bpf_timer_delete(timer);
+    return beats;
+}
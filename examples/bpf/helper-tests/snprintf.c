@@ -0,0 +1,44 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define TEMPERATURE_STORAGE_INDEX 0
+#define HUMIDITY_STORAGE_INDEX 1
+#define COAP_OPT_FINISH_PAYLOAD (0x0001)
+
+typedef struct {
+    uint32_t hdr_p;       /* ptr to raw packet */
+    uint32_t payload_p;   /* ptr to payload    */
+    uint32_t token_p;     /* ptr to token      */
+    uint16_t payload_len; /* length of payload */
+    uint16_t options_len; /* length of options */
+} bpf_coap_pkt_t;
+
+const unsigned SUCCESS_RESPONSE_CODE = (2 << 5) | 5;
+
+// Builds the JSON response of the weather station (see
+// weather-station/src/gcoap_temperature_humidity.c) using a single
+// bpf_snprintf call instead of chaining the fmt and memcpy helpers. The
+// readings are stored in tenths of a degree / percent.
+int test_snprintf(bpf_coap_ctx_t *gcoap)
+{
+    bpf_coap_pkt_t *pkt = gcoap->pkt;
+
+    uint32_t temperature = 0;
+    uint32_t humidity = 0;
+    bpf_fetch_global(TEMPERATURE_STORAGE_INDEX, &temperature);
+    bpf_fetch_global(HUMIDITY_STORAGE_INDEX, &humidity);
+
+    bpf_gcoap_resp_init(gcoap, SUCCESS_RESPONSE_CODE);
+    bpf_coap_add_format(gcoap, 0);
+    ssize_t pdu_len = bpf_coap_opt_finish(gcoap, COAP_OPT_FINISH_PAYLOAD);
+
+    char *payload = (char *)(intptr_t)(pkt->payload_p);
+    uint64_t args[] = {(int16_t)temperature, (int16_t)humidity};
+    int len = bpf_snprintf(payload, pkt->payload_len,
+                           "{\"temperature\": %.1q, \"humidity\": %.1q}", args,
+                           2);
+    if (len < 0) {
+        return -1;
+    }
+    return pdu_len + len;
+}
@@ -0,0 +1,30 @@
+#include <stdint.h>
+#include "../helpers.h"
+
+#define SYSLOG_LOCAL_PORT 5514
+#define SYSLOG_COLLECTOR "[fe80::1]:514"
+
+// Sends a syslog message (RFC 3164 format, facility local0, severity info)
+// and waits briefly for an acknowledgement datagram. The execution request
+// needs to grant the local port 5514 together with the remote host fe80::1
+// and the remote port 514.
+int test_udp_syslog(void *ctx)
+{
+    uint32_t endpoint = bpf_udp_open(SYSLOG_LOCAL_PORT);
+    if (endpoint == 0) {
+        bpf_printf("UDP port not granted\n");
+        return -1;
+    }
+
+    char message[] = "<134>micro-bpf: sensor compartment alive";
+    if (bpf_udp_send(endpoint, SYSLOG_COLLECTOR, message,
+                     sizeof(message) - 1) < 0) {
+        bpf_printf("Failed to send the syslog message\n");
+        return -1;
+    }
+
+    char ack[16];
+    int len = bpf_udp_recv(endpoint, ack, sizeof(ack), 500);
+    bpf_printf("Received %d bytes of acknowledgement\n", len);
+    return len;
+}
@@ -38,7 +38,9 @@ typedef struct {
 } phydat_t;
 
 /**
- * Opaque dummy type saul registration
+ * Opaque dummy type saul registration. When running on the rBPF VMs, the find
+ * helpers return a small integer handle instead of the registry pointer, it
+ * can only be passed into the other SAUL helpers and NULL means not found.
  */
 typedef void bpf_saul_reg_t;
 
@@ -55,6 +57,33 @@ static int (*bpf_fetch_global)(uint32_t key, uint32_t *value) = (void *)
     BPF_FUNC_BPF_FETCH_GLOBAL;
 static int (*bpf_fetch_local)(uint32_t key, uint32_t *value) = (void *)
     BPF_FUNC_BPF_FETCH_LOCAL;
+
+/* Atomic operations on the global store, cas returns 1 if the value was
+ * replaced. */
+static int (*bpf_store_global_cas)(uint32_t key, uint32_t expected,
+                                   uint32_t desired) = (void *)
+    BPF_FUNC_BPF_STORE_GLOBAL_CAS;
+static int (*bpf_store_global_fetch_add)(uint32_t key, uint32_t delta,
+                                         uint32_t *previous) = (void *)
+    BPF_FUNC_BPF_STORE_GLOBAL_FETCH_ADD;
+
+/* Persistent storage of the program's SUIT slot, survives reboots but is
+ * cleared when a new image is loaded into the slot. fetch returns the length
+ * of the stored value or -1 if the key isn't present. */
+static int (*bpf_persist_store)(uint32_t key, const void *value,
+                                uint32_t len) = (void *)
+    BPF_FUNC_BPF_PERSIST_STORE;
+static int (*bpf_persist_fetch)(uint32_t key, void *buf, uint32_t len) = (void *)
+    BPF_FUNC_BPF_PERSIST_FETCH;
+static int (*bpf_persist_delete)(uint32_t key) = (void *)
+    BPF_FUNC_BPF_PERSIST_DELETE;
+
+/* Named mutexes, released automatically when the program terminates. Only
+ * long-running programs can lock them with a non-zero timeout. */
+static int (*bpf_mutex_lock)(const char *name, uint32_t timeout_ms) = (void *)
+    BPF_FUNC_BPF_MUTEX_LOCK;
+static int (*bpf_mutex_unlock)(const char *name) = (void *)
+    BPF_FUNC_BPF_MUTEX_UNLOCK;
 static uint32_t (*bpf_now_ms)(void) = (void *)BPF_FUNC_BPF_NOW_MS;
 
 /* STDLIB */
@@ -81,6 +110,47 @@ static void (*bpf_coap_add_format)(bpf_coap_ctx_t *ctx, uint32_t format) =
 static uint8_t *(*bpf_coap_get_pdu)(bpf_coap_ctx_t *ctx) = (void *)
     BPF_FUNC_BPF_COAP_GET_PDU;
 
+/* CoAP request inspection, needs to happen before bpf_gcoap_resp_init as it
+ * overwrites the options of the request with the ones of the response. */
+static unsigned (*bpf_coap_get_method)(bpf_coap_ctx_t *ctx) = (void *)
+    BPF_FUNC_BPF_COAP_GET_METHOD;
+static ssize_t (*bpf_coap_get_option)(bpf_coap_ctx_t *ctx, unsigned optnum,
+                                      unsigned index, void *buf,
+                                      size_t buf_len) = (void *)
+    BPF_FUNC_BPF_COAP_GET_OPTION;
+static ssize_t (*bpf_coap_get_uri_path)(bpf_coap_ctx_t *ctx, char *buf,
+                                        size_t buf_len) = (void *)
+    BPF_FUNC_BPF_COAP_GET_URI_PATH;
+static ssize_t (*bpf_coap_get_uri_query)(bpf_coap_ctx_t *ctx, unsigned index,
+                                         char *buf, size_t buf_len) = (void *)
+    BPF_FUNC_BPF_COAP_GET_URI_QUERY;
+static unsigned (*bpf_coap_get_content_format)(bpf_coap_ctx_t *ctx) = (void *)
+    BPF_FUNC_BPF_COAP_GET_CONTENT_FORMAT;
+static ssize_t (*bpf_coap_get_payload)(bpf_coap_ctx_t *ctx, void *buf,
+                                       size_t buf_len) = (void *)
+    BPF_FUNC_BPF_COAP_GET_PAYLOAD;
+
+/* CoAP response construction */
+static ssize_t (*bpf_coap_add_option)(bpf_coap_ctx_t *ctx, unsigned optnum,
+                                      const void *value, size_t len) = (void *)
+    BPF_FUNC_BPF_COAP_ADD_OPTION;
+static ssize_t (*bpf_coap_add_uint_option)(bpf_coap_ctx_t *ctx, unsigned optnum,
+                                           uint32_t value) = (void *)
+    BPF_FUNC_BPF_COAP_ADD_UINT_OPTION;
+static ssize_t (*bpf_coap_add_payload)(bpf_coap_ctx_t *ctx, const void *data,
+                                       size_t len) = (void *)
+    BPF_FUNC_BPF_COAP_ADD_PAYLOAD;
+
+/* Outbound CoAP requests, the destination is the index of the peer in the
+ * list declared in the execution request. send returns the response code and
+ * is only available to long-running programs. */
+static int (*bpf_coap_client_send)(uint32_t destination, uint32_t method,
+                                   const char *uri, const void *payload,
+                                   size_t payload_len) = (void *)
+    BPF_FUNC_BPF_COAP_CLIENT_SEND;
+static int (*bpf_coap_client_read_response)(void *buf, size_t len) = (void *)
+    BPF_FUNC_BPF_COAP_CLIENT_READ_RESPONSE;
+
 /* FMT and String calls */
 static size_t (*bpf_strlen)(char *str) = (void *)BPF_FUNC_BPF_STRLEN;
 static size_t (*bpf_fmt_s16_dfp)(char *out, int16_t val, int fp_digits) =
@@ -95,21 +165,207 @@ static void (*bpf_ztimer_periodic_wakeup)(uint32_t *last_wakeup,
     BPF_FUNC_BPF_ZTIMER_PERIODIC_WAKEUP;
 
 /* GPIO calls */
-static uint64_t (*bpf_gpio_read_input)(uint32_t port, uint32_t pin) = (void *)
+static int64_t (*bpf_gpio_read_input)(uint32_t port, uint32_t pin) = (void *)
     BPF_FUNC_GPIO_READ_INPUT;
-static uint64_t (*bpf_gpio_read_raw)(uint32_t port, uint32_t pin) = (void *)
+static int64_t (*bpf_gpio_read_raw)(uint32_t port, uint32_t pin) = (void *)
     BPF_FUNC_GPIO_READ_RAW;
 static void (*bpf_gpio_write)(uint32_t port, uint32_t pin,
                               uint32_t val) = (void *)BPF_FUNC_GPIO_WRITE;
 
-/* HD44780 calls */
+/* HD44780 calls. The display is shared by all programs, a handle gives the
+ * program write access to a range of its rows: bpf_hd44780_init opens all of
+ * them exclusively, bpf_hd44780_open_rows only row_count rows starting at
+ * first_row. Both return 0 if any of the rows is used by another running
+ * program. Rows are numbered from the top of the display, the cursor can only
+ * be moved within the rows of the handle and printed text is cut off at the
+ * end of the row. The handles are released when the program finishes. */
 static uint64_t (*bpf_hd44780_init)() = (void *)BPF_FUNC_HD44780_INIT;
-static uint64_t (*bpf_hd44780_clear)(uint32_t dev) = (void *)
-    BPF_FUNC_HD44780_CLEAR;
-static uint64_t (*bpf_hd44780_print)(uint32_t dev, const char *data) = (void *)
+static uint64_t (*bpf_hd44780_open_rows)(uint32_t first_row,
+                                         uint32_t row_count) = (void *)
+    BPF_FUNC_HD44780_OPEN_ROWS;
+static int (*bpf_hd44780_clear)(uint32_t dev) = (void *)BPF_FUNC_HD44780_CLEAR;
+static int (*bpf_hd44780_print)(uint32_t dev, const char *data) = (void *)
     BPF_FUNC_HD44780_PRINT;
-static uint64_t (*bpf_hd44780_set_cursor)(uint32_t dev, uint32_t row,
-                                          uint32_t col) = (void *)
+static int (*bpf_hd44780_set_cursor)(uint32_t dev, uint32_t col,
+                                     uint32_t row) = (void *)
     BPF_FUNC_HD44780_SET_CURSOR;
+static uint64_t (*bpf_keypad_get_input)(uint32_t adc_index) = (void *)
+    BPF_FUNC_KEYPAD_GET_INPUT;
+
+/* Program chaining */
+static int (*bpf_call_slot)(uint32_t slot, void *arg, size_t arg_len,
+                            uint64_t *result) = (void *)BPF_FUNC_BPF_CALL_SLOT;
+
+/* Message queues, only long-running programs can receive with a non-zero
+ * timeout. */
+static int (*bpf_queue_send)(uint32_t queue, uint32_t value) = (void *)
+    BPF_FUNC_BPF_QUEUE_SEND;
+static int (*bpf_queue_receive)(uint32_t queue, uint32_t *value,
+                                uint32_t timeout_ms) = (void *)
+    BPF_FUNC_BPF_QUEUE_RECEIVE;
+
+/* BPF maps, the values are copied in and out of the maps as the program
+ * cannot access their memory directly. */
+static int (*bpf_map_lookup_elem)(uint32_t map_id, const void *key,
+                                  void *value) = (void *)
+    BPF_FUNC_BPF_MAP_LOOKUP_ELEM;
+static int (*bpf_map_update_elem)(uint32_t map_id, const void *key,
+                                  const void *value, uint64_t flags) = (void *)
+    BPF_FUNC_BPF_MAP_UPDATE_ELEM;
+static int (*bpf_map_delete_elem)(uint32_t map_id, const void *key) = (void *)
+    BPF_FUNC_BPF_MAP_DELETE_ELEM;
+
+/* Ring buffers, the record is written into a buffer owned by the program and
+ * copied into the ring buffer when it is submitted. The host reads the records
+ * through the /maps/ringbuf CoAP resource and removes the ones it has
+ * processed through /maps/ringbuf/consume. */
+static uint32_t (*bpf_ringbuf_reserve)(uint32_t map_id, void *buf,
+                                       uint32_t len) = (void *)
+    BPF_FUNC_BPF_RINGBUF_RESERVE;
+static int (*bpf_ringbuf_submit)(uint32_t handle, uint64_t flags) = (void *)
+    BPF_FUNC_BPF_RINGBUF_SUBMIT;
+static int (*bpf_ringbuf_discard)(uint32_t handle) = (void *)
+    BPF_FUNC_BPF_RINGBUF_DISCARD;
+static int (*bpf_ringbuf_output)(uint32_t map_id, const void *data,
+                                 uint32_t len, uint64_t flags) = (void *)
+    BPF_FUNC_BPF_RINGBUF_OUTPUT;
+
+/* Atomic operations on map values, the values need to be 4 or 8 bytes long */
+static int (*bpf_map_cas_elem)(uint32_t map_id, const void *key,
+                               uint64_t expected, uint64_t desired) = (void *)
+    BPF_FUNC_BPF_MAP_CAS_ELEM;
+static int (*bpf_map_fetch_add_elem)(uint32_t map_id, const void *key,
+                                     uint64_t delta, void *previous) = (void *)
+    BPF_FUNC_BPF_MAP_FETCH_ADD_ELEM;
+
+/* UDP sockets, only the local ports and the remote hosts and ports granted in
+ * the execution request can be used. bpf_udp_open binds the port for the rest
+ * of the job, datagrams arriving before bpf_udp_recv is called are queued.
+ * Only long-running programs can receive with a non-zero timeout. */
+static uint32_t (*bpf_udp_open)(uint16_t local_port) = (void *)
+    BPF_FUNC_BPF_UDP_OPEN;
+static int (*bpf_udp_send)(uint32_t endpoint, const char *remote,
+                           const void *data, size_t len) = (void *)
+    BPF_FUNC_BPF_UDP_SEND;
+static int (*bpf_udp_recv)(uint32_t endpoint, void *buf, size_t len,
+                           uint32_t timeout_ms) = (void *)
+    BPF_FUNC_BPF_UDP_RECV;
+
+/* Sleeping and timers. The waits are only available to long-running programs
+ * and return -1 early if the program has been asked to stop or has run out of
+ * its time budget, in which case it should exit. */
+static int (*bpf_sleep_ms)(uint32_t ms) = (void *)BPF_FUNC_BPF_SLEEP_MS;
+static int (*bpf_sleep_us)(uint32_t us) = (void *)BPF_FUNC_BPF_SLEEP_US;
+static uint32_t (*bpf_timer_create)(uint32_t timeout_ms, uint32_t periodic) =
+    (void *)BPF_FUNC_BPF_TIMER_CREATE;
+static int (*bpf_timer_wait)(uint32_t timer) = (void *)BPF_FUNC_BPF_TIMER_WAIT;
+static int (*bpf_timer_delete)(uint32_t timer) = (void *)
+    BPF_FUNC_BPF_TIMER_DELETE;
+
+/* Logging through the logger of the server, the levels follow the numbering
+ * of the RIOT LOG_* levels. Records are tagged with the SUIT slot and job ID
+ * and discarded if their level is above the log level of the server. */
+#define BPF_LOG_ERROR 1
+#define BPF_LOG_WARNING 2
+#define BPF_LOG_INFO 3
+#define BPF_LOG_DEBUG 4
+#define BPF_LOG_TRACE 5
+static int (*bpf_log)(uint32_t level, const char *fmt, ...) = (void *)
+    BPF_FUNC_BPF_LOG;
+
+/* Bounded formatting into program memory. The arguments are passed as an
+ * array of (at most 8) 64 bit values. Supports %d, %i, %u, %x, %X, %p, %c, %s
+ * and the fixed-point %q conversion, where the precision is the number of
+ * fractional digits (e.g. "%.1q" formats 215 as "21.5"). The output is always
+ * null-terminated, returns the number of bytes written or -1 on failure. */
+static int (*bpf_snprintf)(char *buf, uint32_t len, const char *fmt,
+                           const uint64_t *args, uint32_t args_count) = (void *)
+    BPF_FUNC_BPF_SNPRINTF;
+
+/* Bounds-checked memory and string functions. The comparisons return -1, 0
+ * or 1, or BPF_CMP_INVALID if any of the arguments can't be read. bpf_strncpy
+ * always null-terminates the destination and returns the number of bytes
+ * copied or -1 on failure. */
+#define BPF_CMP_INVALID (-2)
+static void *(*bpf_memset)(void *dest, int c, uint32_t size) = (void *)
+    BPF_FUNC_BPF_MEMSET;
+static int (*bpf_memcmp)(const void *a, const void *b, uint32_t size) = (void *)
+    BPF_FUNC_BPF_MEMCMP;
+static int (*bpf_strncpy)(char *dest, const char *src, uint32_t size) = (void *)
+    BPF_FUNC_BPF_STRNCPY;
+static int (*bpf_strncmp)(const char *a, const char *b, uint32_t size) = (void *)
+    BPF_FUNC_BPF_STRNCMP;
+
+/* Checksums, hashes and random numbers computed natively over program
+ * memory. The checksums return -1 if the data can't be read. The digests are
+ * 32 bytes long. The random bytes come from a non-cryptographic PRNG, they
+ * must not be used as key material or nonces. */
+#define BPF_SHA256_DIGEST_LENGTH 32
+static int64_t (*bpf_crc16)(const void *data, uint32_t len) = (void *)
+    BPF_FUNC_BPF_CRC16;
+static int64_t (*bpf_crc32)(const void *data, uint32_t len) = (void *)
+    BPF_FUNC_BPF_CRC32;
+static int64_t (*bpf_fletcher16)(const void *data, uint32_t len) = (void *)
+    BPF_FUNC_BPF_FLETCHER16;
+static int64_t (*bpf_fletcher32)(const void *data, uint32_t len) = (void *)
+    BPF_FUNC_BPF_FLETCHER32;
+static int (*bpf_sha256)(const void *data, uint32_t len, uint8_t *digest) =
+    (void *)BPF_FUNC_BPF_SHA256;
+static int (*bpf_hmac_sha256)(const void *key, uint32_t key_len,
+                              const void *data, uint32_t len,
+                              uint8_t *digest) = (void *)
+    BPF_FUNC_BPF_HMAC_SHA256;
+static int (*bpf_random_bytes)(void *buf, uint32_t len) = (void *)
+    BPF_FUNC_BPF_RANDOM_BYTES;
+
+/* Integer and fixed-point math. Fractional values use the Q format: a 32 bit
+ * signed integer with frac_bits fractional bits, e.g. 1.5 in Q16 is 98304.
+ * Results saturate instead of overflowing.
+ *
+ * Precision:
+ * - bpf_sat_mul, bpf_sat_div (truncating) and bpf_isqrt (floor) are exact.
+ *   Division by zero returns INT32_MAX/INT32_MIN by the sign of the dividend.
+ * - bpf_q_mul and bpf_q_div round to nearest, error <= 0.5 LSB. They write
+ *   the result into *result and return 0, or return -1 for more than 31
+ *   fractional bits.
+ * - bpf_log2_q rounds down, error < 1 LSB. bpf_log10_q has error <= 1 LSB.
+ *   Both accept at most 16 fractional bits and return -1 for 0.
+ * - bpf_ema_update moves the average stored in a map (4 or 8 byte signed
+ *   values) towards the sample by alpha in Q16 (65536 = 1.0), rounding to
+ *   nearest (error <= 0.5 per update). Scale the samples up to keep fractions.
+ */
+#define Q_FROM_INT(value, frac_bits) ((int32_t)(value) << (frac_bits))
+#define BPF_EMA_ALPHA(numerator, denominator)                                  \
+  ((uint32_t)(((uint64_t)(numerator) << 16) / (denominator)))
+static int32_t (*bpf_sat_mul)(int32_t a, int32_t b) = (void *)
+    BPF_FUNC_BPF_SAT_MUL;
+static int32_t (*bpf_sat_div)(int32_t a, int32_t b) = (void *)
+    BPF_FUNC_BPF_SAT_DIV;
+static uint32_t (*bpf_isqrt)(uint64_t value) = (void *)BPF_FUNC_BPF_ISQRT;
+static int (*bpf_q_mul)(int32_t a, int32_t b, uint32_t frac_bits,
+                        int32_t *result) = (void *)BPF_FUNC_BPF_Q_MUL;
+static int (*bpf_q_div)(int32_t a, int32_t b, uint32_t frac_bits,
+                        int32_t *result) = (void *)BPF_FUNC_BPF_Q_DIV;
+static int32_t (*bpf_log2_q)(uint32_t value, uint32_t frac_bits) = (void *)
+    BPF_FUNC_BPF_LOG2_Q;
+static int32_t (*bpf_log10_q)(uint32_t value, uint32_t frac_bits) = (void *)
+    BPF_FUNC_BPF_LOG10_Q;
+static int (*bpf_ema_update)(uint32_t map_id, const void *key, int32_t sample,
+                             uint32_t alpha, void *average) = (void *)
+    BPF_FUNC_BPF_EMA_UPDATE;
+
+/* Execution context introspection. All of them return -1 if the information
+ * isn't available, e.g. bpf_get_remaining_budget_ms when the job has no time
+ * budget or bpf_get_worker when the program isn't executed by a VM worker. */
+#define BPF_EXECUTION_SHORT_LIVED 0
+#define BPF_EXECUTION_LONG_RUNNING 1
+#define BPF_EXECUTION_COAP_PACKET 2
+static int32_t (*bpf_get_slot)(void) = (void *)BPF_FUNC_BPF_GET_SLOT;
+static int64_t (*bpf_get_job_id)(void) = (void *)BPF_FUNC_BPF_GET_JOB_ID;
+static int32_t (*bpf_get_execution_model)(void) = (void *)
+    BPF_FUNC_BPF_GET_EXECUTION_MODEL;
+static int64_t (*bpf_get_remaining_budget_ms)(void) = (void *)
+    BPF_FUNC_BPF_GET_REMAINING_BUDGET_MS;
+static int32_t (*bpf_get_worker)(void) = (void *)BPF_FUNC_BPF_GET_WORKER;
 
 #endif /* BPF_APPLICATION_CALL_H */
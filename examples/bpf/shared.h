@@ -28,12 +28,22 @@ enum {
   BPF_FUNC_BPF_PRINTF = 0x01,
   BPF_FUNC_BPF_MEMCPY = 0x02,
   BPF_FUNC_BPF_PRINT_DEBUG = 0x03,
+  BPF_FUNC_BPF_LOG = 0x04,
+  BPF_FUNC_BPF_MEMSET = 0x05,
+  BPF_FUNC_BPF_MEMCMP = 0x06,
 
   /* Key/value store functions */
   BPF_FUNC_BPF_STORE_LOCAL = 0x10,
   BPF_FUNC_BPF_STORE_GLOBAL = 0x11,
   BPF_FUNC_BPF_FETCH_LOCAL = 0x12,
   BPF_FUNC_BPF_FETCH_GLOBAL = 0x13,
+  BPF_FUNC_BPF_STORE_GLOBAL_CAS = 0x14,
+  BPF_FUNC_BPF_STORE_GLOBAL_FETCH_ADD = 0x15,
+  BPF_FUNC_BPF_MUTEX_LOCK = 0x16,
+  BPF_FUNC_BPF_MUTEX_UNLOCK = 0x17,
+  BPF_FUNC_BPF_PERSIST_STORE = 0x18,
+  BPF_FUNC_BPF_PERSIST_FETCH = 0x19,
+  BPF_FUNC_BPF_PERSIST_DELETE = 0x1A,
 
   /* Time(r) functions */
   BPF_FUNC_BPF_NOW_MS = 0x20,
@@ -49,14 +59,33 @@ enum {
   BPF_FUNC_BPF_COAP_OPT_FINISH = 0x41,
   BPF_FUNC_BPF_COAP_ADD_FORMAT = 0x42,
   BPF_FUNC_BPF_COAP_GET_PDU = 0x43,
+  BPF_FUNC_BPF_COAP_GET_METHOD = 0x44,
+  BPF_FUNC_BPF_COAP_GET_OPTION = 0x45,
+  BPF_FUNC_BPF_COAP_GET_URI_PATH = 0x46,
+  BPF_FUNC_BPF_COAP_GET_URI_QUERY = 0x47,
+  BPF_FUNC_BPF_COAP_GET_CONTENT_FORMAT = 0x48,
+  BPF_FUNC_BPF_COAP_GET_PAYLOAD = 0x49,
+  BPF_FUNC_BPF_COAP_ADD_OPTION = 0x4A,
+  BPF_FUNC_BPF_COAP_ADD_UINT_OPTION = 0x4B,
+  BPF_FUNC_BPF_COAP_ADD_PAYLOAD = 0x4C,
+  BPF_FUNC_BPF_COAP_CLIENT_SEND = 0x4D,
+  BPF_FUNC_BPF_COAP_CLIENT_READ_RESPONSE = 0x4E,
 
   BPF_FUNC_BPF_FMT_S16_DFP = 0x50,
   BPF_FUNC_BPF_FMT_U32_DEC = 0x51,
   BPF_FUNC_BPF_STRLEN = 0x52,
+  BPF_FUNC_BPF_SNPRINTF = 0x53,
+  BPF_FUNC_BPF_STRNCPY = 0x54,
+  BPF_FUNC_BPF_STRNCMP = 0x55,
 
   /* ZTIMER */
   BPF_FUNC_BPF_ZTIMER_NOW = 0x60,
   BPF_FUNC_BPF_ZTIMER_PERIODIC_WAKEUP = 0x61,
+  BPF_FUNC_BPF_SLEEP_MS = 0x62,
+  BPF_FUNC_BPF_SLEEP_US = 0x63,
+  BPF_FUNC_BPF_TIMER_CREATE = 0x64,
+  BPF_FUNC_BPF_TIMER_WAIT = 0x65,
+  BPF_FUNC_BPF_TIMER_DELETE = 0x66,
 
   /* GPIO */
   BPF_FUNC_GPIO_READ_INPUT = 0x70,
@@ -67,6 +96,57 @@ enum {
   BPF_FUNC_HD44780_CLEAR = 0x81,
   BPF_FUNC_HD44780_PRINT = 0x82,
   BPF_FUNC_HD44780_SET_CURSOR = 0x83,
+  BPF_FUNC_KEYPAD_GET_INPUT = 0x84,
+  BPF_FUNC_HD44780_OPEN_ROWS = 0x85,
+
+  /* Program chaining */
+  BPF_FUNC_BPF_CALL_SLOT = 0x90,
+
+  /* Message queues */
+  BPF_FUNC_BPF_QUEUE_SEND = 0xA0,
+  BPF_FUNC_BPF_QUEUE_RECEIVE = 0xA1,
+
+  /* BPF maps */
+  BPF_FUNC_BPF_MAP_LOOKUP_ELEM = 0xB0,
+  BPF_FUNC_BPF_MAP_UPDATE_ELEM = 0xB1,
+  BPF_FUNC_BPF_MAP_DELETE_ELEM = 0xB2,
+  BPF_FUNC_BPF_RINGBUF_RESERVE = 0xB3,
+  BPF_FUNC_BPF_RINGBUF_SUBMIT = 0xB4,
+  BPF_FUNC_BPF_RINGBUF_DISCARD = 0xB5,
+  BPF_FUNC_BPF_RINGBUF_OUTPUT = 0xB6,
+  BPF_FUNC_BPF_MAP_CAS_ELEM = 0xB7,
+  BPF_FUNC_BPF_MAP_FETCH_ADD_ELEM = 0xB8,
+
+  /* UDP sockets */
+  BPF_FUNC_BPF_UDP_OPEN = 0xC0,
+  BPF_FUNC_BPF_UDP_SEND = 0xC1,
+  BPF_FUNC_BPF_UDP_RECV = 0xC2,
+
+  /* Checksums, hashes and random numbers */
+  BPF_FUNC_BPF_CRC16 = 0xD0,
+  BPF_FUNC_BPF_CRC32 = 0xD1,
+  BPF_FUNC_BPF_FLETCHER16 = 0xD2,
+  BPF_FUNC_BPF_FLETCHER32 = 0xD3,
+  BPF_FUNC_BPF_SHA256 = 0xD4,
+  BPF_FUNC_BPF_HMAC_SHA256 = 0xD5,
+  BPF_FUNC_BPF_RANDOM_BYTES = 0xD6,
+
+  /* Integer and fixed-point math */
+  BPF_FUNC_BPF_SAT_MUL = 0xE0,
+  BPF_FUNC_BPF_SAT_DIV = 0xE1,
+  BPF_FUNC_BPF_ISQRT = 0xE2,
+  BPF_FUNC_BPF_Q_MUL = 0xE3,
+  BPF_FUNC_BPF_Q_DIV = 0xE4,
+  BPF_FUNC_BPF_LOG2_Q = 0xE5,
+  BPF_FUNC_BPF_LOG10_Q = 0xE6,
+  BPF_FUNC_BPF_EMA_UPDATE = 0xE7,
+
+  /* Execution context introspection */
+  BPF_FUNC_BPF_GET_SLOT = 0xF0,
+  BPF_FUNC_BPF_GET_JOB_ID = 0xF1,
+  BPF_FUNC_BPF_GET_EXECUTION_MODEL = 0xF2,
+  BPF_FUNC_BPF_GET_REMAINING_BUDGET_MS = 0xF3,
+  BPF_FUNC_BPF_GET_WORKER = 0xF4,
 
 };
 
@@ -77,6 +157,60 @@ typedef struct {
   size_t buf_len;                   /**< Packet buffer length */
 } bpf_coap_ctx_t;
 
+/* Methods of the outbound CoAP requests */
+#define BPF_COAP_GET (1)
+#define BPF_COAP_POST (2)
+#define BPF_COAP_PUT (3)
+
+/* BPF map types */
+#define BPF_MAP_TYPE_HASH (0)
+#define BPF_MAP_TYPE_ARRAY (1)
+#define BPF_MAP_TYPE_PER_SLOT_ARRAY (2)
+#define BPF_MAP_TYPE_RINGBUF (3)
+
+/* Flags of bpf_map_update_elem */
+#define BPF_ANY (0)
+#define BPF_NOEXIST (1)
+#define BPF_EXIST (2)
+
+/* Flags of bpf_ringbuf_submit and bpf_ringbuf_output */
+#define BPF_RB_NO_WAKEUP (1)
+
+/* Flags of the map definitions. Maps are only accessible to the programs
+ * loaded into the SUIT slot of the program that created them unless they are
+ * shared. */
+#define BPF_F_SHARED (1)
+
+/* Definition of a map placed in the .maps section of the program, the
+ * server creates the map before the program starts executing. The program
+ * then refers to the map using its ID. */
+typedef struct {
+  uint32_t id;
+  uint32_t type;
+  uint32_t key_size;
+  uint32_t value_size;
+  uint32_t max_entries;
+  uint32_t flags;
+} bpf_map_def_t;
+
+#define BPF_MAP_DEF_FLAGS(name, map_id, map_type, key_sz, value_sz, entries,  \
+                          map_flags)                                          \
+  __attribute__((section(".maps"), used)) bpf_map_def_t name = {              \
+      .id = map_id,                                                           \
+      .type = map_type,                                                       \
+      .key_size = key_sz,                                                     \
+      .value_size = value_sz,                                                 \
+      .max_entries = entries,                                                 \
+      .flags = map_flags,                                                     \
+  }
+
+#define BPF_MAP_DEF(name, map_id, map_type, key_sz, value_sz, entries)        \
+  BPF_MAP_DEF_FLAGS(name, map_id, map_type, key_sz, value_sz, entries, 0)
+
+#define BPF_SHARED_MAP_DEF(name, map_id, map_type, key_sz, value_sz, entries) \
+  BPF_MAP_DEF_FLAGS(name, map_id, map_type, key_sz, value_sz, entries,        \
+                    BPF_F_SHARED)
+
 #ifdef __cplusplus
 }
 #endif
@@ -1,5 +1,5 @@
 #include <stdint.h>
-#include "helpers.h"
+#include "../../bpf/helpers.h"
 #include <stdbool.h>
 #include "constants.h"
 
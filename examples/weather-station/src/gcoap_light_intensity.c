@@ -1,6 +1,6 @@
 #include <stdint.h>
 #include "constants.h"
-#include "helpers.h"
+#include "../../bpf/helpers.h"
 
 #define SHARED_KEY 0x50
 #define COAP_OPT_FINISH_PAYLOAD (0x0001)
@@ -1,5 +1,5 @@
 #include <stdint.h>
-#include "helpers.h"
+#include "../../bpf/helpers.h"
 #include "constants.h"
 
 /* This program is responsible for periodically reading the values reported by
@@ -1,5 +1,5 @@
 #include "constants.h"
-#include "helpers.h"
+#include "../../bpf/helpers.h"
 #include <stdint.h>
 
 /* This program is responsible for periodically reading the values reported by
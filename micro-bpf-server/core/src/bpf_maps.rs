@@ -0,0 +1,858 @@
+//! This module implements eBPF-style maps that programs can use to store
+//! state and share it with other programs and the host. Contrary to the
+//! global storage (which maps `u32` keys onto `u32` values), maps have
+//! declared key and value sizes and come in a few different types:
+//!
+//! - hash maps, storing at most `max_entries` arbitrary key-value pairs,
+//! - arrays, indexed by `u32` keys in the range `[0, max_entries)`,
+//! - per-slot arrays, which are arrays with a separate copy of the values for
+//!   each SUIT storage slot, so that programs can use the same map definition
+//!   without interfering with each other,
+//! - ring buffers, holding variable-length records that are streamed out of
+//!   the programs, `max_entries` is then the capacity of the buffer in bytes.
+//!
+//! Maps are identified by their numeric ID and are created when the first
+//! job that declares them (either in the execution request or in the `.maps`
+//! section of the program) starts executing. The map is owned by the SUIT
+//! slot of the program that created it, programs loaded into other slots can
+//! only declare it if it was defined as `shared` or if it is a per-slot array
+//! (whose values are separate for each slot anyway). Maps stay alive after the
+//! program terminates so that the host can inspect them through the `/maps`
+//! endpoints. They are destroyed once all programs that declared them have
+//! been unloaded from their slots.
+//!
+//! All maps live in a [`MapRegistry`], the server keeps a single global one
+//! behind a mutex.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+/// Maximum number of maps that can exist at the same time.
+pub const MAX_BPF_MAPS: usize = 16;
+/// Maximum number of bytes that the keys and values of a single map (or the
+/// records of a ring buffer) can occupy. For per-slot arrays this limit
+/// applies to each slot separately.
+pub const MAX_BPF_MAP_SIZE: usize = 4096;
+
+/// Update flags compatible with the ones used by the eBPF
+/// `bpf_map_update_elem` helper.
+pub const BPF_ANY: u64 = 0;
+pub const BPF_NOEXIST: u64 = 1;
+pub const BPF_EXIST: u64 = 2;
+
+/// Size of the length prefix of each record stored in a ring buffer.
+pub const RING_BUFFER_RECORD_HEADER_SIZE: usize = 2;
+/// Maximum size of a single ring buffer record, it is limited so that each
+/// record fits into a single CoAP notification sent to the observers.
+pub const MAX_RING_BUFFER_RECORD_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapType {
+    Hash,
+    Array,
+    PerSlotArray,
+    RingBuffer,
+}
+
+/// Declaration of a map, it mirrors the definitions that programs and
+/// execution requests carry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapDefinition {
+    pub id: u32,
+    pub map_type: MapType,
+    pub key_size: u32,
+    pub value_size: u32,
+    pub max_entries: u32,
+    /// Allows programs loaded into other slots than the owner to use the map.
+    pub shared: bool,
+}
+
+/// A key-value pair as returned by [`MapRegistry::entries`].
+pub type MapEntry = (Vec<u8>, Vec<u8>);
+
+enum MapStorage {
+    Hash(BTreeMap<Vec<u8>, Vec<u8>>),
+    Array(Vec<u8>),
+    PerSlotArray(Vec<Vec<u8>>),
+    RingBuffer(RingBuffer),
+}
+
+struct RingBuffer {
+    /// Records together with their length prefixes.
+    data: VecDeque<u8>,
+    /// Number of bytes reserved by the producers that haven't submitted
+    /// their records yet.
+    reserved: usize,
+    capacity: usize,
+}
+
+struct BpfMap {
+    definition: MapDefinition,
+    storage: MapStorage,
+    /// SUIT slot of the program that created the map.
+    owner: usize,
+    /// SUIT slots of all programs that declared the map.
+    users: Vec<usize>,
+}
+
+/// All maps that currently exist, keyed by their IDs.
+pub struct MapRegistry {
+    maps: BTreeMap<u32, BpfMap>,
+    /// Number of SUIT slots, i.e. of the copies of per-slot arrays.
+    slots: usize,
+}
+
+impl MapRegistry {
+    pub const fn new(slots: usize) -> Self {
+        MapRegistry {
+            maps: BTreeMap::new(),
+            slots,
+        }
+    }
+
+    /// Creates the map for the program loaded into the given SUIT slot if it
+    /// doesn't exist yet. If it does, its definition needs to match the one
+    /// that it was created with and the program needs to be allowed to use
+    /// it, i.e. it has to be loaded into the slot owning the map or the map
+    /// has to be shared (per-slot arrays always are). Returns whether the map
+    /// was created.
+    pub fn create_map(&mut self, definition: &MapDefinition, slot: usize) -> Result<bool, String> {
+        if let Some(map) = self.maps.get_mut(&definition.id) {
+            if map.definition != *definition {
+                Err(format!(
+                    "Map {} already exists with a different definition: {:?}",
+                    definition.id, map.definition
+                ))?;
+            }
+            let shared = map.definition.shared || map.definition.map_type == MapType::PerSlotArray;
+            if map.owner != slot && !shared {
+                Err(format!(
+                    "Map {} is owned by the program in slot {} and isn't shared",
+                    definition.id, map.owner
+                ))?;
+            }
+            if !map.users.contains(&slot) {
+                map.users.push(slot);
+            }
+            return Ok(false);
+        }
+
+        if self.maps.len() >= MAX_BPF_MAPS {
+            Err(format!(
+                "Cannot create map {}, the limit of {} maps has been reached",
+                definition.id, MAX_BPF_MAPS
+            ))?;
+        }
+
+        let key_size = definition.key_size as usize;
+        let value_size = definition.value_size as usize;
+        let max_entries = definition.max_entries as usize;
+        let size = match definition.map_type {
+            MapType::Hash => key_size
+                .checked_add(value_size)
+                .and_then(|entry_size| entry_size.checked_mul(max_entries)),
+            MapType::Array | MapType::PerSlotArray => value_size.checked_mul(max_entries),
+            MapType::RingBuffer => Some(max_entries),
+        };
+        let Some(size) = size.filter(|size| max_entries > 0 && *size <= MAX_BPF_MAP_SIZE) else {
+            Err(format!(
+                "Invalid size of map {}, it needs to be non-empty and at most {} bytes",
+                definition.id, MAX_BPF_MAP_SIZE
+            ))?
+        };
+
+        let storage = match definition.map_type {
+            MapType::Hash => {
+                if key_size == 0 || value_size == 0 {
+                    Err("Hash map keys and values cannot be empty")?;
+                }
+                MapStorage::Hash(BTreeMap::new())
+            }
+            MapType::Array | MapType::PerSlotArray => {
+                if key_size != core::mem::size_of::<u32>() || value_size == 0 {
+                    Err("Array keys need to be u32 indices and values cannot be empty")?;
+                }
+                if definition.map_type == MapType::Array {
+                    MapStorage::Array(vec![0; size])
+                } else {
+                    MapStorage::PerSlotArray(vec![vec![0; size]; self.slots])
+                }
+            }
+            MapType::RingBuffer => MapStorage::RingBuffer(RingBuffer {
+                data: VecDeque::with_capacity(size),
+                reserved: 0,
+                capacity: size,
+            }),
+        };
+
+        self.maps.insert(
+            definition.id,
+            BpfMap {
+                definition: definition.clone(),
+                storage,
+                owner: slot,
+                users: vec![slot],
+            },
+        );
+        Ok(true)
+    }
+
+    /// Called when the program is unloaded from the SUIT slot. The program no
+    /// longer uses the maps that it declared, the maps that aren't used by
+    /// any other program are destroyed together with their contents. Returns
+    /// the IDs of the destroyed maps.
+    pub fn release_slot(&mut self, slot: usize) -> Vec<u32> {
+        let mut destroyed = Vec::new();
+        self.maps.retain(|id, map| {
+            map.users.retain(|user| *user != slot);
+            if map.users.is_empty() {
+                destroyed.push(*id);
+            }
+            !map.users.is_empty()
+        });
+        destroyed
+    }
+
+    /// Returns the definition of the map with a given ID.
+    pub fn definition(&self, id: u32) -> Result<MapDefinition, String> {
+        Ok(self.get_map(id)?.definition.clone())
+    }
+
+    /// Returns the definitions of all maps together with the number of
+    /// entries (or records in case of ring buffers) that they hold.
+    pub fn list_maps(&self) -> Vec<(MapDefinition, usize)> {
+        self.maps
+            .values()
+            .map(|map| (map.definition.clone(), self.entry_count(map)))
+            .collect()
+    }
+
+    fn entry_count(&self, map: &BpfMap) -> usize {
+        match &map.storage {
+            MapStorage::Hash(entries) => entries.len(),
+            MapStorage::Array(_) => map.definition.max_entries as usize,
+            MapStorage::PerSlotArray(_) => map.definition.max_entries as usize * self.slots,
+            MapStorage::RingBuffer(buffer) => ring_buffer_records(&buffer.data).len(),
+        }
+    }
+
+    /// Copies the value corresponding to the key into `value`. The `slot` is
+    /// the SUIT slot whose copy of a per-slot array should be accessed.
+    pub fn lookup(&self, id: u32, key: &[u8], value: &mut [u8], slot: usize) -> Result<(), String> {
+        let map = self.get_map(id)?;
+        check_sizes(map, key.len(), value.len())?;
+        match &map.storage {
+            MapStorage::Hash(entries) => {
+                let Some(stored) = entries.get(key) else {
+                    Err(format!("Key not found in map {}", id))?
+                };
+                value.copy_from_slice(stored);
+            }
+            MapStorage::Array(values) => {
+                let offset = array_offset(map, key)?;
+                value.copy_from_slice(&values[offset..offset + value.len()]);
+            }
+            MapStorage::PerSlotArray(slots) => {
+                let offset = array_offset(map, key)?;
+                let values = slot_values(slots, slot)?;
+                value.copy_from_slice(&values[offset..offset + value.len()]);
+            }
+            MapStorage::RingBuffer(_) => Err(ring_buffer_unsupported(id))?,
+        }
+        Ok(())
+    }
+
+    /// Sets the value corresponding to the key. The `flags` are one of
+    /// [`BPF_ANY`], [`BPF_NOEXIST`] and [`BPF_EXIST`]. Arrays always contain
+    /// all of their elements, so updating them with [`BPF_NOEXIST`] fails.
+    pub fn update(
+        &mut self,
+        id: u32,
+        key: &[u8],
+        value: &[u8],
+        flags: u64,
+        slot: usize,
+    ) -> Result<(), String> {
+        if flags > BPF_EXIST {
+            Err(format!("Invalid map update flags: {}", flags))?;
+        }
+        let map = self.get_map_mut(id)?;
+        check_sizes(map, key.len(), value.len())?;
+        let max_entries = map.definition.max_entries as usize;
+        let offset = match map.storage {
+            MapStorage::Array(_) | MapStorage::PerSlotArray(_) => {
+                if flags == BPF_NOEXIST {
+                    Err(format!("Element already exists in array map {}", id))?;
+                }
+                array_offset(map, key)?
+            }
+            _ => 0,
+        };
+
+        match &mut map.storage {
+            MapStorage::Hash(entries) => {
+                let exists = entries.contains_key(key);
+                if flags == BPF_NOEXIST && exists {
+                    Err(format!("Key already exists in map {}", id))?;
+                }
+                if flags == BPF_EXIST && !exists {
+                    Err(format!("Key not found in map {}", id))?;
+                }
+                if !exists && entries.len() >= max_entries {
+                    Err(format!("Map {} is full", id))?;
+                }
+                entries.insert(key.to_vec(), value.to_vec());
+            }
+            MapStorage::Array(values) => {
+                values[offset..offset + value.len()].copy_from_slice(value);
+            }
+            MapStorage::PerSlotArray(slots) => {
+                let values = slot_values_mut(slots, slot)?;
+                values[offset..offset + value.len()].copy_from_slice(value);
+            }
+            MapStorage::RingBuffer(_) => Err(ring_buffer_unsupported(id))?,
+        }
+        Ok(())
+    }
+
+    /// Atomically applies the operation to the value corresponding to the
+    /// key, which is interpreted as a little-endian `u32` or `u64` integer
+    /// depending on the value size of the map. A missing key of a hash map
+    /// reads as 0 and gets inserted only if the operation returns a new value.
+    /// Returns the previous value.
+    pub fn atomic_update(
+        &mut self,
+        id: u32,
+        key: &[u8],
+        slot: usize,
+        operation: impl FnOnce(u64) -> Option<u64>,
+    ) -> Result<u64, String> {
+        let map = self.get_map_mut(id)?;
+        let value_size = map.definition.value_size as usize;
+        if value_size != 4 && value_size != 8 {
+            Err(format!(
+                "Atomic operations require 4 or 8 byte values, map {} has {} byte values",
+                id, value_size
+            ))?;
+        }
+        check_sizes(map, key.len(), value_size)?;
+        let max_entries = map.definition.max_entries as usize;
+        let offset = match map.storage {
+            MapStorage::Array(_) | MapStorage::PerSlotArray(_) => array_offset(map, key)?,
+            _ => 0,
+        };
+
+        let current = match &map.storage {
+            MapStorage::Hash(entries) => entries.get(key).map(|value| value.as_slice()),
+            MapStorage::Array(values) => Some(&values[offset..offset + value_size]),
+            MapStorage::PerSlotArray(slots) => {
+                Some(&slot_values(slots, slot)?[offset..offset + value_size])
+            }
+            MapStorage::RingBuffer(_) => Err(ring_buffer_unsupported(id))?,
+        };
+        let mut bytes = [0u8; 8];
+        if let Some(current) = current {
+            bytes[..value_size].copy_from_slice(current);
+        }
+        let previous = u64::from_le_bytes(bytes);
+
+        let Some(new) = operation(previous) else {
+            return Ok(previous);
+        };
+        let new = &new.to_le_bytes()[..value_size];
+        match &mut map.storage {
+            MapStorage::Hash(entries) => {
+                if !entries.contains_key(key) && entries.len() >= max_entries {
+                    Err(format!("Map {} is full", id))?;
+                }
+                entries.insert(key.to_vec(), new.to_vec());
+            }
+            MapStorage::Array(values) => {
+                values[offset..offset + value_size].copy_from_slice(new);
+            }
+            MapStorage::PerSlotArray(slots) => {
+                slot_values_mut(slots, slot)?[offset..offset + value_size].copy_from_slice(new);
+            }
+            MapStorage::RingBuffer(_) => Err(ring_buffer_unsupported(id))?,
+        }
+        Ok(previous)
+    }
+
+    /// Removes the key from the map. Only supported by hash maps as the
+    /// elements of arrays cannot be removed.
+    pub fn delete(&mut self, id: u32, key: &[u8]) -> Result<(), String> {
+        let map = self.get_map_mut(id)?;
+        if key.len() != map.definition.key_size as usize {
+            Err(format!(
+                "Invalid key size {} for map {}, expected {}",
+                key.len(),
+                id,
+                map.definition.key_size
+            ))?;
+        }
+        match &mut map.storage {
+            MapStorage::Hash(entries) => match entries.remove(key) {
+                Some(_) => Ok(()),
+                None => Err(format!("Key not found in map {}", id)),
+            },
+            _ => Err(format!("Elements cannot be deleted from map {}", id)),
+        }
+    }
+
+    /// Returns all entries of the map as key-value pairs. Entries of per-slot
+    /// arrays are keyed by the slot number followed by the index (both `u32`)
+    /// and the records of ring buffers are keyed by their position in the
+    /// buffer.
+    pub fn entries(&self, id: u32) -> Result<Vec<MapEntry>, String> {
+        let map = self.get_map(id)?;
+        let value_size = map.definition.value_size as usize;
+        let index_key = |i: usize| (i as u32).to_le_bytes().to_vec();
+        let array_entries = |values: &Vec<u8>| -> Vec<MapEntry> {
+            values
+                .chunks(value_size)
+                .enumerate()
+                .map(|(i, value)| (index_key(i), value.to_vec()))
+                .collect()
+        };
+
+        Ok(match &map.storage {
+            MapStorage::Hash(entries) => entries
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            MapStorage::Array(values) => array_entries(values),
+            MapStorage::PerSlotArray(slots) => slots
+                .iter()
+                .enumerate()
+                .flat_map(|(slot, values)| {
+                    array_entries(values)
+                        .into_iter()
+                        .map(move |(index, value)| {
+                            let mut key = (slot as u32).to_le_bytes().to_vec();
+                            key.extend_from_slice(&index);
+                            (key, value)
+                        })
+                })
+                .collect(),
+            MapStorage::RingBuffer(buffer) => ring_buffer_records(&buffer.data)
+                .into_iter()
+                .enumerate()
+                .map(|(i, record)| (index_key(i), record))
+                .collect(),
+        })
+    }
+
+    /// Allows the host to set the value of an entry. The keys of per-slot
+    /// arrays follow the same format as in [`MapRegistry::entries`].
+    pub fn host_update(&mut self, id: u32, key: &[u8], value: &[u8]) -> Result<(), String> {
+        if self.get_map(id)?.definition.map_type == MapType::PerSlotArray {
+            let (slot, index) = split_per_slot_key(key)?;
+            return self.update(id, index, value, BPF_ANY, slot);
+        }
+        self.update(id, key, value, BPF_ANY, 0)
+    }
+
+    /// Resets the values of all per-slot arrays belonging to the slot, it is
+    /// called when a new program is loaded into it.
+    pub fn clear_slot(&mut self, slot: usize) {
+        for map in self.maps.values_mut() {
+            if let MapStorage::PerSlotArray(slots) = &mut map.storage {
+                if let Some(values) = slots.get_mut(slot) {
+                    values.fill(0);
+                }
+            }
+        }
+    }
+
+    /// Reserves space for a record of `len` bytes in the ring buffer so that
+    /// the following [`MapRegistry::ring_buffer_submit`] is guaranteed to
+    /// succeed. Fails if there isn't enough free space left, the host needs
+    /// to consume records first.
+    pub fn ring_buffer_reserve(&mut self, id: u32, len: usize) -> Result<(), String> {
+        if len == 0 || len > MAX_RING_BUFFER_RECORD_SIZE {
+            Err(format!(
+                "Invalid ring buffer record size {}, it needs to be between 1 and {} bytes",
+                len, MAX_RING_BUFFER_RECORD_SIZE
+            ))?;
+        }
+        let buffer = self.get_ring_buffer(id)?;
+        let required = RING_BUFFER_RECORD_HEADER_SIZE + len;
+        if buffer.data.len() + buffer.reserved + required > buffer.capacity {
+            Err(format!("Ring buffer {} is full", id))?;
+        }
+        buffer.reserved += required;
+        Ok(())
+    }
+
+    /// Appends the record to the ring buffer, its space needs to have been
+    /// reserved using [`MapRegistry::ring_buffer_reserve`].
+    pub fn ring_buffer_submit(&mut self, id: u32, record: &[u8]) -> Result<(), String> {
+        let buffer = self.get_ring_buffer(id)?;
+        // The map could have been recreated since the space was reserved.
+        let Some(reserved) = buffer
+            .reserved
+            .checked_sub(RING_BUFFER_RECORD_HEADER_SIZE + record.len())
+        else {
+            Err(format!(
+                "No space reserved for the record in ring buffer {}",
+                id
+            ))?
+        };
+        buffer.reserved = reserved;
+        buffer.data.extend(
+            (record.len() as u16)
+                .to_le_bytes()
+                .iter()
+                .chain(record.iter()),
+        );
+        Ok(())
+    }
+
+    /// Releases the space reserved for a record that won't be submitted.
+    pub fn ring_buffer_discard(&mut self, id: u32, len: usize) -> Result<(), String> {
+        let buffer = self.get_ring_buffer(id)?;
+        let Some(reserved) = buffer
+            .reserved
+            .checked_sub(RING_BUFFER_RECORD_HEADER_SIZE + len)
+        else {
+            Err(format!(
+                "No space reserved for the record in ring buffer {}",
+                id
+            ))?
+        };
+        buffer.reserved = reserved;
+        Ok(())
+    }
+
+    /// Reserves the space for the record and submits it in one step.
+    pub fn ring_buffer_output(&mut self, id: u32, record: &[u8]) -> Result<(), String> {
+        self.ring_buffer_reserve(id, record.len())?;
+        self.ring_buffer_submit(id, record)
+    }
+
+    /// Returns the IDs of all ring buffers that contain records.
+    pub fn non_empty_ring_buffers(&self) -> Vec<u32> {
+        self.maps
+            .values()
+            .filter(|map| matches!(&map.storage, MapStorage::RingBuffer(b) if !b.data.is_empty()))
+            .map(|map| map.definition.id)
+            .collect()
+    }
+
+    /// Removes the oldest record from the ring buffer and returns it.
+    pub fn ring_buffer_consume(&mut self, id: u32) -> Option<Vec<u8>> {
+        let buffer = self.get_ring_buffer(id).ok()?;
+        let (lo, hi) = (buffer.data.pop_front()?, buffer.data.pop_front()?);
+        let len = u16::from_le_bytes([lo, hi]) as usize;
+        Some(buffer.data.drain(..len).collect())
+    }
+
+    /// Removes the `count` oldest records from the ring buffer, e.g. once the
+    /// host has processed them. Returns the number of removed records, which
+    /// is lower than `count` if the buffer doesn't hold enough records.
+    pub fn ring_buffer_consume_records(&mut self, id: u32, count: usize) -> Result<usize, String> {
+        self.get_ring_buffer(id)?;
+        Ok((0..count)
+            .take_while(|_| self.ring_buffer_consume(id).is_some())
+            .count())
+    }
+
+    fn get_ring_buffer(&mut self, id: u32) -> Result<&mut RingBuffer, String> {
+        match &mut self.get_map_mut(id)?.storage {
+            MapStorage::RingBuffer(buffer) => Ok(buffer),
+            _ => Err(format!("Map {} is not a ring buffer", id)),
+        }
+    }
+
+    fn get_map(&self, id: u32) -> Result<&BpfMap, String> {
+        self.maps
+            .get(&id)
+            .ok_or(format!("Map {} does not exist", id))
+    }
+
+    fn get_map_mut(&mut self, id: u32) -> Result<&mut BpfMap, String> {
+        self.maps
+            .get_mut(&id)
+            .ok_or(format!("Map {} does not exist", id))
+    }
+}
+
+fn split_per_slot_key(key: &[u8]) -> Result<(usize, &[u8]), String> {
+    if key.len() != 2 * core::mem::size_of::<u32>() {
+        Err("Per-slot array keys need to consist of the slot and the index")?;
+    }
+    let (slot, index) = key.split_at(core::mem::size_of::<u32>());
+    let slot = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]) as usize;
+    Ok((slot, index))
+}
+
+fn check_sizes(map: &BpfMap, key_size: usize, value_size: usize) -> Result<(), String> {
+    if key_size != map.definition.key_size as usize
+        || value_size != map.definition.value_size as usize
+    {
+        Err(format!(
+            "Invalid key / value size ({}, {}) for map {}, expected ({}, {})",
+            key_size,
+            value_size,
+            map.definition.id,
+            map.definition.key_size,
+            map.definition.value_size
+        ))?;
+    }
+    Ok(())
+}
+
+/// Returns the offset of the value corresponding to the array index.
+fn array_offset(map: &BpfMap, key: &[u8]) -> Result<usize, String> {
+    let index = u32::from_le_bytes([key[0], key[1], key[2], key[3]]);
+    if index >= map.definition.max_entries {
+        Err(format!(
+            "Index {} out of bounds of array map {}",
+            index, map.definition.id
+        ))?;
+    }
+    Ok(index as usize * map.definition.value_size as usize)
+}
+
+fn slot_values(slots: &[Vec<u8>], slot: usize) -> Result<&Vec<u8>, String> {
+    slots
+        .get(slot)
+        .ok_or(format!("Invalid SUIT slot: {}", slot))
+}
+
+fn slot_values_mut(slots: &mut [Vec<u8>], slot: usize) -> Result<&mut Vec<u8>, String> {
+    slots
+        .get_mut(slot)
+        .ok_or(format!("Invalid SUIT slot: {}", slot))
+}
+
+fn ring_buffer_unsupported(id: u32) -> String {
+    format!(
+        "Map {} is a ring buffer, it only supports streaming records",
+        id
+    )
+}
+
+/// Splits the contents of the ring buffer into records, each of which is
+/// stored with a little-endian `u16` length prefix.
+fn ring_buffer_records(buffer: &VecDeque<u8>) -> Vec<Vec<u8>> {
+    let mut records = Vec::new();
+    let mut bytes = buffer.iter().copied();
+    while let (Some(lo), Some(hi)) = (bytes.next(), bytes.next()) {
+        let len = u16::from_le_bytes([lo, hi]) as usize;
+        records.push(bytes.by_ref().take(len).collect());
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(
+        id: u32,
+        map_type: MapType,
+        key_size: u32,
+        value_size: u32,
+        max_entries: u32,
+    ) -> MapDefinition {
+        MapDefinition {
+            id,
+            map_type,
+            key_size,
+            value_size,
+            max_entries,
+            shared: false,
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_map_sizes() {
+        let mut maps = MapRegistry::new(2);
+        assert!(maps
+            .create_map(&map(1, MapType::Hash, u32::MAX, 1, 2), 0)
+            .is_err());
+        assert!(maps
+            .create_map(&map(2, MapType::Array, 4, u32::MAX, u32::MAX), 0)
+            .is_err());
+        assert!(maps
+            .create_map(&map(3, MapType::Array, 4, 4, 0), 0)
+            .is_err());
+        assert_eq!(
+            maps.create_map(&map(4, MapType::Array, 4, 8, 512), 0),
+            Ok(true)
+        );
+        assert!(maps
+            .create_map(&map(5, MapType::Array, 4, 8, 513), 0)
+            .is_err());
+    }
+
+    #[test]
+    fn limits_the_number_of_maps() {
+        let mut maps = MapRegistry::new(2);
+        for id in 0..MAX_BPF_MAPS as u32 {
+            maps.create_map(&map(id, MapType::Hash, 1, 1, 1), 0)
+                .unwrap();
+        }
+        assert!(maps
+            .create_map(&map(100, MapType::Hash, 1, 1, 1), 0)
+            .is_err());
+        assert_eq!(
+            maps.create_map(&map(0, MapType::Hash, 1, 1, 1), 0),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn hash_map_update_lookup_delete() {
+        let mut maps = MapRegistry::new(2);
+        maps.create_map(&map(1, MapType::Hash, 2, 4, 1), 0).unwrap();
+        let mut value = [0u8; 4];
+        assert!(maps.lookup(1, &[1, 2], &mut value, 0).is_err());
+        assert!(maps
+            .update(1, &[1, 2], &[1, 2, 3, 4], BPF_EXIST, 0)
+            .is_err());
+        maps.update(1, &[1, 2], &[1, 2, 3, 4], BPF_NOEXIST, 0)
+            .unwrap();
+        maps.lookup(1, &[1, 2], &mut value, 0).unwrap();
+        assert_eq!(value, [1, 2, 3, 4]);
+        assert!(
+            maps.update(1, &[3, 4], &[0; 4], BPF_ANY, 0).is_err(),
+            "map is full"
+        );
+        assert!(
+            maps.update(1, &[1, 2], &[0; 3], BPF_ANY, 0).is_err(),
+            "wrong value size"
+        );
+        assert_eq!(maps.list_maps()[0].1, 1);
+        maps.delete(1, &[1, 2]).unwrap();
+        assert!(maps.delete(1, &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn array_indices_are_bounds_checked() {
+        let mut maps = MapRegistry::new(2);
+        maps.create_map(&map(1, MapType::Array, 4, 4, 2), 0)
+            .unwrap();
+        maps.update(1, &1u32.to_le_bytes(), &[9; 4], BPF_ANY, 0)
+            .unwrap();
+        assert!(maps
+            .update(1, &2u32.to_le_bytes(), &[9; 4], BPF_ANY, 0)
+            .is_err());
+        assert!(maps
+            .update(1, &0u32.to_le_bytes(), &[9; 4], BPF_NOEXIST, 0)
+            .is_err());
+        assert!(maps.delete(1, &0u32.to_le_bytes()).is_err());
+        let previous = maps
+            .atomic_update(1, &1u32.to_le_bytes(), 0, |v| Some(v + 1))
+            .unwrap();
+        assert_eq!(previous, u32::from_le_bytes([9; 4]) as u64);
+    }
+
+    #[test]
+    fn per_slot_arrays_are_separate() {
+        let mut maps = MapRegistry::new(2);
+        maps.create_map(&map(1, MapType::PerSlotArray, 4, 4, 1), 0)
+            .unwrap();
+        maps.create_map(&map(1, MapType::PerSlotArray, 4, 4, 1), 1)
+            .unwrap();
+        maps.update(1, &0u32.to_le_bytes(), &[1; 4], BPF_ANY, 0)
+            .unwrap();
+        let mut value = [0u8; 4];
+        maps.lookup(1, &0u32.to_le_bytes(), &mut value, 1).unwrap();
+        assert_eq!(value, [0; 4]);
+        assert!(maps.lookup(1, &0u32.to_le_bytes(), &mut value, 2).is_err());
+
+        let mut key = 1u32.to_le_bytes().to_vec();
+        key.extend_from_slice(&0u32.to_le_bytes());
+        maps.host_update(1, &key, &[2; 4]).unwrap();
+        let entries = maps.entries(1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1], (key, vec![2; 4]));
+
+        maps.clear_slot(0);
+        maps.lookup(1, &0u32.to_le_bytes(), &mut value, 0).unwrap();
+        assert_eq!(value, [0; 4]);
+    }
+
+    #[test]
+    fn maps_are_owned_by_their_slot() {
+        let mut maps = MapRegistry::new(2);
+        let private = map(1, MapType::Hash, 1, 1, 1);
+        maps.create_map(&private, 0).unwrap();
+        assert!(maps.create_map(&private, 1).is_err());
+        let shared = MapDefinition {
+            shared: true,
+            ..map(2, MapType::Hash, 1, 1, 1)
+        };
+        maps.create_map(&shared, 0).unwrap();
+        maps.create_map(&shared, 1).unwrap();
+        assert!(maps
+            .create_map(
+                &MapDefinition {
+                    max_entries: 2,
+                    ..shared
+                },
+                1
+            )
+            .is_err());
+        assert_eq!(maps.release_slot(0), vec![1]);
+        assert!(maps.definition(1).is_err());
+        assert!(maps.definition(2).is_ok());
+        assert_eq!(maps.release_slot(1), vec![2]);
+        assert!(maps.definition(2).is_err());
+    }
+
+    #[test]
+    fn ring_buffer_reservations() {
+        let mut maps = MapRegistry::new(2);
+        maps.create_map(&map(1, MapType::RingBuffer, 0, 0, 8), 0)
+            .unwrap();
+        assert!(maps
+            .ring_buffer_reserve(1, MAX_RING_BUFFER_RECORD_SIZE + 1)
+            .is_err());
+        maps.ring_buffer_reserve(1, 4).unwrap();
+        assert!(maps.ring_buffer_reserve(1, 1).is_err(), "buffer is full");
+        maps.ring_buffer_discard(1, 4).unwrap();
+        assert!(
+            maps.ring_buffer_discard(1, 4).is_err(),
+            "nothing is reserved"
+        );
+        assert!(
+            maps.ring_buffer_submit(1, &[1]).is_err(),
+            "nothing is reserved"
+        );
+        maps.ring_buffer_output(1, &[1, 2, 3]).unwrap();
+        assert_eq!(maps.non_empty_ring_buffers(), vec![1]);
+        assert_eq!(
+            maps.entries(1).unwrap(),
+            vec![(0u32.to_le_bytes().to_vec(), vec![1, 2, 3])]
+        );
+        assert_eq!(maps.ring_buffer_consume(1), Some(vec![1, 2, 3]));
+        assert_eq!(maps.ring_buffer_consume(1), None);
+        assert!(maps.non_empty_ring_buffers().is_empty());
+    }
+
+    #[test]
+    fn ring_buffer_records_are_consumed_explicitly() {
+        let mut maps = MapRegistry::new(2);
+        maps.create_map(&map(1, MapType::RingBuffer, 0, 0, 16), 0)
+            .unwrap();
+        maps.create_map(&map(2, MapType::Array, 4, 4, 1), 0)
+            .unwrap();
+        for record in [[1], [2], [3]] {
+            maps.ring_buffer_output(1, &record).unwrap();
+        }
+        assert_eq!(maps.entries(1).unwrap().len(), 3, "reading doesn't consume");
+        assert_eq!(maps.ring_buffer_consume_records(1, 2), Ok(2));
+        assert_eq!(maps.entries(1).unwrap()[0].1, vec![3]);
+        assert_eq!(maps.ring_buffer_consume_records(1, 2), Ok(1));
+        assert!(maps.ring_buffer_consume_records(2, 1).is_err());
+        assert!(maps.ring_buffer_consume_records(3, 1).is_err());
+    }
+}
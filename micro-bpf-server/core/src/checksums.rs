@@ -0,0 +1,91 @@
+//! The checksum algorithms that the server can compute natively. Only the
+//! CRC-32 is implemented here as RIOT doesn't provide it, the others are
+//! computed by the RIOT `checksum` and `hashes` modules.
+
+use alloc::{format, string::String};
+use core::convert::TryFrom;
+
+/// Algorithms that can be benchmarked through the native checksum endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc16,
+    Crc32,
+    Fletcher16,
+    Fletcher32,
+    Sha256,
+    HmacSha256,
+}
+
+impl TryFrom<&str> for ChecksumAlgorithm {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "crc16" => Ok(ChecksumAlgorithm::Crc16),
+            "crc32" => Ok(ChecksumAlgorithm::Crc32),
+            "fletcher16" => Ok(ChecksumAlgorithm::Fletcher16),
+            "fletcher32" => Ok(ChecksumAlgorithm::Fletcher32),
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "hmac-sha256" => Ok(ChecksumAlgorithm::HmacSha256),
+            _ => Err(format!("Unknown checksum algorithm: {}", name)),
+        }
+    }
+}
+
+/// The standard (IEEE 802.3) CRC-32 as used by e.g. zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_check_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn parses_algorithm_names() {
+        assert_eq!(
+            ChecksumAlgorithm::try_from("crc16"),
+            Ok(ChecksumAlgorithm::Crc16)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::try_from("crc32"),
+            Ok(ChecksumAlgorithm::Crc32)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::try_from("fletcher16"),
+            Ok(ChecksumAlgorithm::Fletcher16)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::try_from("fletcher32"),
+            Ok(ChecksumAlgorithm::Fletcher32)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::try_from("sha256"),
+            Ok(ChecksumAlgorithm::Sha256)
+        );
+        assert_eq!(
+            ChecksumAlgorithm::try_from("hmac-sha256"),
+            Ok(ChecksumAlgorithm::HmacSha256)
+        );
+        assert!(ChecksumAlgorithm::try_from("md5").is_err());
+        assert!(ChecksumAlgorithm::try_from("CRC32").is_err());
+    }
+}
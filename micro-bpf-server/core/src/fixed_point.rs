@@ -0,0 +1,260 @@
+//! Integer and fixed-point arithmetic backing the math helpers. eBPF programs
+//! can't use floating point, so fractional values are represented in the
+//! Q format: a signed 32 bit integer with a given number of fractional bits,
+//! e.g. 1.5 in Q16 is `1.5 * 2^16 = 98304`.
+//!
+//! All operations are exact unless stated otherwise. Results that don't fit
+//! into 32 bits saturate instead of wrapping around.
+
+use alloc::{format, string::String};
+
+/// Largest number of fractional bits of the Q-format operands.
+pub const MAX_FRACTIONAL_BITS: u32 = 31;
+/// Largest number of fractional bits of the logarithms, so that the integer
+/// part (at most 31) always fits.
+pub const MAX_LOG_FRACTIONAL_BITS: u32 = 16;
+/// Number of fractional bits of the smoothing factor of the moving average.
+pub const EMA_ALPHA_FRACTIONAL_BITS: u32 = 16;
+
+/// log10(2) in the Q32 format.
+const LOG10_2_Q32: u64 = 0x4D10_4D42;
+
+fn saturate(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Divides and rounds to the nearest integer, halfway cases away from zero.
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if 2 * remainder.unsigned_abs() >= denominator.unsigned_abs() {
+        quotient + numerator.signum() * denominator.signum()
+    } else {
+        quotient
+    }
+}
+
+/// Saturates a division by zero to the extreme value with the sign of the
+/// dividend, or 0 if the dividend is 0.
+fn saturated_division_by_zero(numerator: i64) -> i32 {
+    match numerator.signum() {
+        1 => i32::MAX,
+        -1 => i32::MIN,
+        _ => 0,
+    }
+}
+
+pub fn saturating_mul(a: i32, b: i32) -> i32 {
+    a.saturating_mul(b)
+}
+
+/// Integer division truncating towards zero, see
+/// [`saturated_division_by_zero`] for the handling of a zero divisor.
+pub fn saturating_div(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        return saturated_division_by_zero(a as i64);
+    }
+    a.saturating_div(b)
+}
+
+/// Floor of the square root, exact for all inputs.
+pub fn isqrt(value: u64) -> u32 {
+    let mut remainder = value;
+    let mut root: u64 = 0;
+    let mut bit: u64 = 1 << 62;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if remainder >= root + bit {
+            remainder -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root as u32
+}
+
+/// Multiplies two Q-format numbers with the given number of fractional bits.
+/// The result is rounded to the nearest representable value (error at most
+/// half of the least significant bit).
+pub fn q_mul(a: i32, b: i32, fractional_bits: u32) -> Result<i32, String> {
+    check_fractional_bits(fractional_bits, MAX_FRACTIONAL_BITS)?;
+    let product = a as i64 * b as i64;
+    Ok(saturate(div_round(product, 1 << fractional_bits)))
+}
+
+/// Divides two Q-format numbers with the given number of fractional bits.
+/// The result is rounded to the nearest representable value (error at most
+/// half of the least significant bit).
+pub fn q_div(a: i32, b: i32, fractional_bits: u32) -> Result<i32, String> {
+    check_fractional_bits(fractional_bits, MAX_FRACTIONAL_BITS)?;
+    let numerator = (a as i64) << fractional_bits;
+    if b == 0 {
+        return Ok(saturated_division_by_zero(numerator));
+    }
+    Ok(saturate(div_round(numerator, b as i64)))
+}
+
+/// Base 2 logarithm of a positive integer in the Q format with the given
+/// number of fractional bits (at most [`MAX_LOG_FRACTIONAL_BITS`]). The result
+/// is rounded down, so the error is less than one least significant bit.
+pub fn log2_q(value: u32, fractional_bits: u32) -> Result<i32, String> {
+    check_fractional_bits(fractional_bits, MAX_LOG_FRACTIONAL_BITS)?;
+    if value == 0 {
+        Err("The logarithm of 0 is undefined")?;
+    }
+    let integer_part = 31 - value.leading_zeros();
+    // Normalise the value into [1, 2) in the Q31 format and compute the bits
+    // of the fractional part one by one by repeated squaring.
+    let mut normalised = ((value as u64) << 31) >> integer_part;
+    let mut result = integer_part;
+    for _ in 0..fractional_bits {
+        normalised = (normalised * normalised) >> 31;
+        result <<= 1;
+        if normalised >= 1 << 32 {
+            normalised >>= 1;
+            result |= 1;
+        }
+    }
+    Ok(result as i32)
+}
+
+/// Base 10 logarithm of a positive integer in the Q format with the given
+/// number of fractional bits (at most [`MAX_LOG_FRACTIONAL_BITS`]). It is
+/// computed from the base 2 logarithm, the error is at most one least
+/// significant bit.
+pub fn log10_q(value: u32, fractional_bits: u32) -> Result<i32, String> {
+    check_fractional_bits(fractional_bits, MAX_LOG_FRACTIONAL_BITS)?;
+    let log2 = log2_q(value, MAX_LOG_FRACTIONAL_BITS)? as u64;
+    // Q16 * Q32 = Q48
+    let product = log2 * LOG10_2_Q32;
+    let shift = MAX_LOG_FRACTIONAL_BITS + 32 - fractional_bits;
+    Ok(((product + (1 << (shift - 1))) >> shift) as i32)
+}
+
+/// One step of the exponential moving average: moves the average towards the
+/// sample by the smoothing factor `alpha`, which is given in the Q16 format
+/// (65536 means that the average is replaced by the sample). The result is
+/// rounded to the nearest integer, so each update adds an error of at most
+/// one half. To keep fractional precision, the samples can be scaled up (i.e.
+/// converted to the Q format) before being averaged.
+pub fn ema_update(average: i64, sample: i64, alpha: u32) -> Result<i64, String> {
+    if alpha > 1 << EMA_ALPHA_FRACTIONAL_BITS {
+        Err(format!(
+            "Smoothing factor {} is larger than 1.0 in Q16",
+            alpha
+        ))?;
+    }
+    let delta = (sample as i128 - average as i128) * alpha as i128;
+    let step = delta / (1 << EMA_ALPHA_FRACTIONAL_BITS);
+    let remainder = delta % (1 << EMA_ALPHA_FRACTIONAL_BITS);
+    let step = if 2 * remainder.abs() >= 1 << EMA_ALPHA_FRACTIONAL_BITS {
+        step + delta.signum()
+    } else {
+        step
+    };
+    Ok((average as i128 + step) as i64)
+}
+
+fn check_fractional_bits(fractional_bits: u32, max: u32) -> Result<(), String> {
+    if fractional_bits > max {
+        Err(format!(
+            "{} fractional bits requested, at most {} are supported",
+            fractional_bits, max
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isqrt_is_the_floor_of_the_square_root() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u64::MAX), u32::MAX);
+        let max_square = u32::MAX as u64 * u32::MAX as u64;
+        assert_eq!(isqrt(max_square), u32::MAX);
+        assert_eq!(isqrt(max_square - 1), u32::MAX - 1);
+        for value in 0..10_000u64 {
+            let root = isqrt(value) as u64;
+            assert!(root * root <= value && value < (root + 1) * (root + 1));
+        }
+    }
+
+    #[test]
+    fn log2_rounds_down() {
+        // The expected values are floor(log2(value) * 2^16).
+        assert_eq!(log2_q(1, 16), Ok(0));
+        assert_eq!(log2_q(2, 16), Ok(65536));
+        assert_eq!(log2_q(3, 16), Ok(103872));
+        assert_eq!(log2_q(10, 16), Ok(217705));
+        assert_eq!(log2_q(65535, 16), Ok(1048574));
+        assert_eq!(log2_q(u32::MAX, 16), Ok(2097151));
+        assert_eq!(log2_q(1000, 0), Ok(9));
+        assert!(log2_q(0, 16).is_err());
+        assert!(log2_q(2, MAX_LOG_FRACTIONAL_BITS + 1).is_err());
+    }
+
+    #[test]
+    fn log10_is_within_one_lsb() {
+        // The expected values are log10(value) * 2^fractional_bits rounded to
+        // the nearest integer.
+        assert_eq!(log10_q(1, 16), Ok(0));
+        assert_eq!(log10_q(2, 16), Ok(19728));
+        assert_eq!(log10_q(3, 16), Ok(31269));
+        assert_eq!(log10_q(10, 16), Ok(65536));
+        assert_eq!(log10_q(1000, 16), Ok(196608));
+        assert_eq!(log10_q(u32::MAX, 16), Ok(631305));
+        assert_eq!(log10_q(3, 8), Ok(122));
+        assert_eq!(log10_q(65535, 8), Ok(1233));
+        assert_eq!(log10_q(1000, 0), Ok(3));
+        assert!(log10_q(0, 16).is_err());
+        assert!(log10_q(10, MAX_LOG_FRACTIONAL_BITS + 1).is_err());
+    }
+
+    #[test]
+    fn q_operations_round_to_nearest() {
+        assert_eq!(q_mul(3 << 15, 2 << 16, 16), Ok(3 << 16));
+        assert_eq!(q_div(1 << 16, 3 << 16, 16), Ok(21845));
+        assert_eq!(q_div(2 << 16, 3 << 16, 16), Ok(43691));
+        // Halfway cases are rounded away from zero.
+        assert_eq!(q_mul(1, 1, 1), Ok(1));
+        assert_eq!(q_mul(-1, 1, 1), Ok(-1));
+        assert_eq!(q_mul(0, 5 << 16, 16), Ok(0));
+        assert!(q_mul(1, 1, MAX_FRACTIONAL_BITS + 1).is_err());
+        assert!(q_div(1, 1, MAX_FRACTIONAL_BITS + 1).is_err());
+    }
+
+    #[test]
+    fn results_saturate() {
+        assert_eq!(saturating_mul(i32::MAX, 2), i32::MAX);
+        assert_eq!(saturating_mul(i32::MIN, 2), i32::MIN);
+        assert_eq!(saturating_div(i32::MIN, -1), i32::MAX);
+        assert_eq!(saturating_div(5, 0), i32::MAX);
+        assert_eq!(saturating_div(-5, 0), i32::MIN);
+        assert_eq!(saturating_div(0, 0), 0);
+        assert_eq!(q_mul(30000 << 16, 30000 << 16, 16), Ok(i32::MAX));
+        assert_eq!(q_mul(-30000 << 16, 30000 << 16, 16), Ok(i32::MIN));
+        assert_eq!(q_div(1 << 30, 1, 16), Ok(i32::MAX));
+        assert_eq!(q_div(-(3 << 15), 0, 16), Ok(i32::MIN));
+        assert_eq!(q_div(0, 0, 16), Ok(0));
+    }
+
+    #[test]
+    fn ema_moves_towards_the_sample() {
+        assert_eq!(ema_update(0, 100, 1 << 16), Ok(100));
+        assert_eq!(ema_update(0, 100, 1 << 15), Ok(50));
+        assert_eq!(ema_update(100, 0, 1 << 15), Ok(50));
+        assert_eq!(ema_update(0, 3, 1 << 15), Ok(2));
+        assert_eq!(ema_update(7, 100, 0), Ok(7));
+        assert!(ema_update(0, 100, (1 << 16) + 1).is_err());
+    }
+}
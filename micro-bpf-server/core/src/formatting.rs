@@ -0,0 +1,297 @@
+//! Printf-style formatting of helper arguments implemented in Rust. Contrary to
+//! passing the format string to the C `printf`, the conversion specifications
+//! are parsed here, which allows for validating the arguments against the
+//! memory regions of the calling program and for bounding the output.
+//!
+//! The supported conversions are `%d`, `%i`, `%u`, `%x`, `%X`, `%p`, `%c`, `%s`
+//! and `%%` together with the `-`, `0`, `+`, ` ` and `#` flags, field width,
+//! precision and the `l`/`ll`/`z` length modifiers (without them the integer
+//! arguments are treated as 32 bit values). `%n` and `*` widths are rejected.
+//!
+//! Additionally, the non-standard `%q` conversion prints a signed fixed-point
+//! number, with the precision giving the number of fractional digits, e.g.
+//! `%.1q` prints 215 as `21.5`. It replaces the `bpf_fmt_s16_dfp` helper when
+//! formatting sensor readings.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::iter::Peekable;
+
+/// Largest field width or precision accepted in a conversion specification,
+/// it prevents a short format string from producing an arbitrarily long output.
+pub const MAX_FIELD_WIDTH: usize = 64;
+
+/// Largest number of fractional digits of a `%q` conversion.
+pub const MAX_FIXED_POINT_DIGITS: usize = 18;
+
+#[derive(Default)]
+struct Specification {
+    left_align: bool,
+    zero_pad: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+    long: bool,
+}
+
+/// Formats the arguments according to the format string. Arguments printed
+/// using `%s` are pointers which are resolved by `read_string`, it is expected
+/// to return `None` if the pointer doesn't point to a valid string that the
+/// program can access, in which case formatting fails.
+pub fn printf_format(
+    fmt: &[u8],
+    args: &[u64],
+    read_string: impl Fn(u64) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut args = args.iter().copied();
+    let mut bytes = fmt.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            out.push(b);
+            continue;
+        }
+
+        let mut spec = Specification::default();
+        while let Some(flag) = bytes.peek() {
+            match flag {
+                b'-' => spec.left_align = true,
+                b'0' => spec.zero_pad = true,
+                b'+' => spec.plus = true,
+                b' ' => spec.space = true,
+                b'#' => spec.alternate = true,
+                _ => break,
+            }
+            bytes.next();
+        }
+        spec.width = parse_number(&mut bytes)?;
+        if bytes.peek() == Some(&b'.') {
+            bytes.next();
+            spec.precision = Some(parse_number(&mut bytes)?);
+        }
+        while let Some(modifier) = bytes.peek() {
+            match modifier {
+                b'l' | b'z' => spec.long = true,
+                b'h' => {}
+                _ => break,
+            }
+            bytes.next();
+        }
+
+        let Some(conversion) = bytes.next() else {
+            Err("Incomplete conversion specification at the end of the format string")?
+        };
+        if conversion == b'%' {
+            out.push(b'%');
+            continue;
+        }
+        if conversion == b'n' || conversion == b'*' {
+            Err(format!(
+                "The %{} conversion is not allowed",
+                conversion as char
+            ))?;
+        }
+        let Some(arg) = args.next() else {
+            Err("Too few arguments for the format string")?
+        };
+        let integer = if spec.long { arg } else { arg as u32 as u64 };
+
+        let (prefix, mut body, numeric): (&str, Vec<u8>, bool) = match conversion {
+            b'd' | b'i' => {
+                let value = signed(arg, &spec);
+                (
+                    sign(value, &spec),
+                    format!("{}", value.unsigned_abs()).into_bytes(),
+                    true,
+                )
+            }
+            b'q' => {
+                let value = signed(arg, &spec);
+                let digits = spec.precision.take().unwrap_or(0);
+                if digits > MAX_FIXED_POINT_DIGITS {
+                    Err(format!(
+                        "Fixed-point conversion can have at most {} fractional digits",
+                        MAX_FIXED_POINT_DIGITS
+                    ))?;
+                }
+                let magnitude = value.unsigned_abs();
+                let scale = 10u64.pow(digits as u32);
+                let body = if digits == 0 {
+                    format!("{}", magnitude)
+                } else {
+                    format!(
+                        "{}.{:0width$}",
+                        magnitude / scale,
+                        magnitude % scale,
+                        width = digits
+                    )
+                };
+                (sign(value, &spec), body.into_bytes(), true)
+            }
+            b'u' => ("", format!("{}", integer).into_bytes(), true),
+            b'x' => {
+                let prefix = if spec.alternate && integer != 0 {
+                    "0x"
+                } else {
+                    ""
+                };
+                (prefix, format!("{:x}", integer).into_bytes(), true)
+            }
+            b'X' => {
+                let prefix = if spec.alternate && integer != 0 {
+                    "0X"
+                } else {
+                    ""
+                };
+                (prefix, format!("{:X}", integer).into_bytes(), true)
+            }
+            b'p' => ("0x", format!("{:x}", arg).into_bytes(), true),
+            b'c' => ("", vec![arg as u8], false),
+            b's' => {
+                let Some(mut string) = read_string(arg) else {
+                    Err("Invalid string argument")?
+                };
+                if let Some(precision) = spec.precision {
+                    string.truncate(precision);
+                }
+                ("", string, false)
+            }
+            c => Err(format!("Unsupported conversion: %{}", c as char))?,
+        };
+
+        // For integers the precision is the minimum number of digits.
+        if numeric {
+            if let Some(precision) = spec.precision {
+                if precision > body.len() {
+                    let mut padded = vec![b'0'; precision - body.len()];
+                    padded.append(&mut body);
+                    body = padded;
+                }
+            }
+        }
+
+        let padding = spec.width.saturating_sub(prefix.len() + body.len());
+        if spec.left_align {
+            out.extend_from_slice(prefix.as_bytes());
+            out.append(&mut body);
+            out.resize(out.len() + padding, b' ');
+        } else if spec.zero_pad && numeric && spec.precision.is_none() {
+            out.extend_from_slice(prefix.as_bytes());
+            out.resize(out.len() + padding, b'0');
+            out.append(&mut body);
+        } else {
+            out.resize(out.len() + padding, b' ');
+            out.extend_from_slice(prefix.as_bytes());
+            out.append(&mut body);
+        }
+    }
+    Ok(out)
+}
+
+fn signed(arg: u64, spec: &Specification) -> i64 {
+    if spec.long {
+        arg as i64
+    } else {
+        arg as u32 as i32 as i64
+    }
+}
+
+fn sign(value: i64, spec: &Specification) -> &'static str {
+    if value < 0 {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    }
+}
+
+fn parse_number(bytes: &mut Peekable<impl Iterator<Item = u8>>) -> Result<usize, String> {
+    let mut number: usize = 0;
+    while let Some(digit @ b'0'..=b'9') = bytes.peek().copied() {
+        number = number * 10 + (digit - b'0') as usize;
+        if number > MAX_FIELD_WIDTH {
+            Err(format!(
+                "Field width exceeds the limit of {}",
+                MAX_FIELD_WIDTH
+            ))?;
+        }
+        bytes.next();
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRING_ADDRESS: u64 = 0x1000;
+
+    fn format(fmt: &str, args: &[u64]) -> Result<String, String> {
+        let read_string = |address| (address == STRING_ADDRESS).then(|| b"hello".to_vec());
+        printf_format(fmt.as_bytes(), args, read_string).map(|out| String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn formats_integers() {
+        let minus_five = -5i32 as u32 as u64;
+        assert_eq!(format("%d %i", &[minus_five, 42]).unwrap(), "-5 42");
+        assert_eq!(format("%ld", &[-5i64 as u64]).unwrap(), "-5");
+        assert_eq!(format("%u", &[u64::MAX]).unwrap(), "4294967295");
+        assert_eq!(format("%lu", &[u64::MAX]).unwrap(), "18446744073709551615");
+        assert_eq!(
+            format("%x %X %p", &[255, 255, 0xdead_beef]).unwrap(),
+            "ff FF 0xdeadbeef"
+        );
+        assert_eq!(format("%#x %#x", &[255, 0]).unwrap(), "0xff 0");
+    }
+
+    #[test]
+    fn applies_flags_width_and_precision() {
+        assert_eq!(
+            format("%5d|%-5d|%05d|%+d|% d", &[42, 42, 42, 42, 42]).unwrap(),
+            "   42|42   |00042|+42| 42"
+        );
+        assert_eq!(format("%05d", &[-42i32 as u32 as u64]).unwrap(), "-0042");
+        assert_eq!(format("%.3d", &[7]).unwrap(), "007");
+        // The zero flag is ignored if a precision is given.
+        assert_eq!(format("%08.3d", &[7]).unwrap(), "     007");
+        assert_eq!(format("%#06x", &[255]).unwrap(), "0x00ff");
+    }
+
+    #[test]
+    fn formats_fixed_point_numbers() {
+        assert_eq!(format("%.1q", &[215]).unwrap(), "21.5");
+        assert_eq!(format("%.2q", &[-5i32 as u32 as u64]).unwrap(), "-0.05");
+        assert_eq!(format("%q", &[7]).unwrap(), "7");
+        assert_eq!(format("%+.3lq", &[1_000_500]).unwrap(), "+1000.500");
+        assert!(format("%.19q", &[1]).is_err());
+    }
+
+    #[test]
+    fn formats_characters_and_strings() {
+        assert_eq!(format("%c%c", &[b'o' as u64, b'k' as u64]).unwrap(), "ok");
+        assert_eq!(format("%s!", &[STRING_ADDRESS]).unwrap(), "hello!");
+        assert_eq!(
+            format("%.3s|%7s|%-6s|", &[STRING_ADDRESS; 3]).unwrap(),
+            "hel|  hello|hello |"
+        );
+        assert_eq!(format("100%%", &[]).unwrap(), "100%");
+        assert!(format("%s", &[0x2000]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_format_strings() {
+        assert!(format("%n", &[0]).is_err());
+        assert!(format("%*d", &[4, 2]).is_err());
+        assert!(format("%y", &[0]).is_err());
+        assert!(format("trailing %", &[]).is_err());
+        assert!(format("%d %d", &[1]).is_err());
+        assert!(format("%65d", &[1]).is_err());
+        assert!(format("%.65d", &[1]).is_err());
+        assert_eq!(format("%64d", &[1]).unwrap().len(), MAX_FIELD_WIDTH);
+    }
+}
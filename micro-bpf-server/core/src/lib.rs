@@ -0,0 +1,13 @@
+//! Logic of the micro-bpf server that doesn't depend on RIOT: the eBPF maps,
+//! the printf-style formatting, the checksums and the fixed-point math backing
+//! the helpers. The server re-exports these modules and wraps the global state
+//! in RIOT mutexes, keeping them in a separate crate allows for testing them
+//! on the host using `cargo test`.
+#![no_std]
+
+extern crate alloc;
+
+pub mod bpf_maps;
+pub mod checksums;
+pub mod fixed_point;
+pub mod formatting;
@@ -0,0 +1,315 @@
+use alloc::{
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::convert::TryInto;
+
+use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
+
+use crate::{infra::bpf_maps, util::admin_token};
+
+use super::{generic_request_error::GenericRequestError, util};
+
+// This module contains the endpoints allowing the host to inspect and modify
+// the BPF maps. Keys and values are transferred as hex strings and the
+// listings are paginated (see [`util::paginate`]) because they don't fit into
+// a single CoAP response.
+
+/// Lists all maps, each line of the response describes one map in the form
+/// `<id>,<type>,<key size>,<value size>,<max entries>,<entries>`.
+pub struct MapsHandler;
+impl coap_handler::Handler for MapsHandler {
+    /// Response code and the index of the first map to list.
+    type RequestData = (u8, usize);
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::GET {
+            return Ok((coap_numbers::code::METHOD_NOT_ALLOWED, 0));
+        }
+        match util::numeric_query_param(request, "start", 0) {
+            Ok(start) => Ok((coap_numbers::code::CONTENT, start)),
+            Err(code) => Ok((code, 0)),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        util::PAGINATED_RESPONSE_BUDGET
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        let (code, start) = request;
+        response.set_code(code.try_into().map_err(|_| ()).unwrap());
+        if code != coap_numbers::code::CONTENT {
+            return response.set_payload(&[]);
+        }
+        let lines: Vec<String> = bpf_maps::list_maps()
+            .into_iter()
+            .map(|(map, entries)| {
+                format!(
+                    "{},{:?},{},{},{},{}",
+                    map.id, map.map_type, map.key_size, map.value_size, map.max_entries, entries
+                )
+            })
+            .collect();
+        response.set_payload(util::paginate(&lines, start).as_bytes())
+    }
+}
+
+/// Dumps the entries of the map specified using the `id` query parameter,
+/// each line of the response contains one entry in the form `<key>=<value>`.
+pub struct MapEntriesHandler {
+    lines: Result<Vec<String>, String>,
+}
+
+impl MapEntriesHandler {
+    pub fn new() -> Self {
+        Self {
+            lines: Ok(Vec::new()),
+        }
+    }
+}
+
+impl coap_handler::Handler for MapEntriesHandler {
+    /// Response code and the index of the first entry to list.
+    type RequestData = (u8, usize);
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::GET {
+            return Ok((coap_numbers::code::METHOD_NOT_ALLOWED, 0));
+        }
+        let (Ok(start), Some(Ok(id))) = (
+            util::numeric_query_param(request, "start", 0),
+            util::query_param(request, "id").map(|id| id.parse::<u32>()),
+        ) else {
+            return Ok((coap_numbers::code::BAD_REQUEST, 0));
+        };
+
+        self.lines = bpf_maps::entries(id).map(|entries| {
+            entries
+                .iter()
+                .map(|(key, value)| format!("{}={}", util::to_hex(key), util::to_hex(value)))
+                .collect()
+        });
+        match self.lines {
+            Ok(_) => Ok((coap_numbers::code::CONTENT, start)),
+            Err(_) => Ok((coap_numbers::code::NOT_FOUND, 0)),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        util::PAGINATED_RESPONSE_BUDGET
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        let (code, start) = request;
+        response.set_code(code.try_into().map_err(|_| ()).unwrap());
+        match &self.lines {
+            Ok(lines) if code == coap_numbers::code::CONTENT => {
+                response.set_payload(util::paginate(lines, start).as_bytes())
+            }
+            Err(e) => response.set_payload(e.as_bytes()),
+            _ => response.set_payload(&[]),
+        }
+    }
+}
+
+/// Modifies the entries of a map. The request payload needs to be of the form
+/// `<admin token>;<id>;<key>=<value>`, an empty value removes the key from a
+/// hash map. See [`crate::util::admin_token`] for the details.
+pub struct MapUpdateHandler {
+    last_request_status: Result<(), String>,
+}
+
+impl MapUpdateHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(()),
+        }
+    }
+
+    fn handle_update(payload: &str) -> Result<(), String> {
+        let payload = admin_token::authenticate(payload)?;
+        let Some((id, entry)) = payload.split_once(';') else {
+            Err("Malformed map update request")?
+        };
+        let Some((key, value)) = entry.split_once('=') else {
+            Err("Malformed map entry")?
+        };
+        let id = id
+            .parse::<u32>()
+            .map_err(|e| format!("Invalid map ID: {}", e))?;
+        let key = util::from_hex(key)?;
+        if value.is_empty() {
+            return bpf_maps::delete(id, &key);
+        }
+        bpf_maps::host_update(id, &key, &util::from_hex(value)?)
+    }
+}
+
+impl coap_handler::Handler for MapUpdateHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let payload = match util::preprocess_request_raw(request) {
+            Ok(payload) => payload,
+            Err(code) => return Ok(code),
+        };
+        self.last_request_status = Self::handle_update(&payload);
+        match &self.last_request_status {
+            Ok(()) => Ok(coap_numbers::code::CHANGED),
+            Err(_) => Ok(coap_numbers::code::BAD_REQUEST),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let result = match &self.last_request_status {
+            Ok(()) => "Success",
+            Err(e) => e.as_str(),
+        };
+        response.set_payload(result.as_bytes())
+    }
+}
+
+/// Removes the ring buffer records that the host has processed. The request
+/// payload needs to be of the form `<admin token>;<id>:<count>`, it removes the
+/// `count` oldest records of the ring buffer. Reading the `/maps/ringbuf`
+/// resource (see `ffi/ringbuf_observe.c`) doesn't remove any records, so the
+/// host acknowledges the lines it has received from it using this endpoint.
+pub struct RingBufferConsumeHandler {
+    last_request_status: Result<usize, String>,
+}
+
+impl RingBufferConsumeHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(0),
+        }
+    }
+
+    fn handle_consume(payload: &str) -> Result<usize, String> {
+        let payload = admin_token::authenticate(payload)?;
+        let Some((id, count)) = payload.split_once(':') else {
+            Err("Malformed ring buffer consume request")?
+        };
+        let id = id
+            .parse::<u32>()
+            .map_err(|e| format!("Invalid map ID: {}", e))?;
+        let count = count
+            .parse::<usize>()
+            .map_err(|e| format!("Invalid record count: {}", e))?;
+        let consumed = bpf_maps::ring_buffer_consume_records(id, count)?;
+        if !bpf_maps::non_empty_ring_buffers().is_empty() {
+            // Let the observers know about the records that didn't fit into
+            // the previous notification.
+            unsafe { ringbuf_observe_notify() };
+        }
+        Ok(consumed)
+    }
+}
+
+impl coap_handler::Handler for RingBufferConsumeHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let payload = match util::preprocess_request_raw(request) {
+            Ok(payload) => payload,
+            Err(code) => return Ok(code),
+        };
+        self.last_request_status = Self::handle_consume(&payload);
+        match &self.last_request_status {
+            Ok(_) => Ok(coap_numbers::code::DELETED),
+            Err(_) => Ok(coap_numbers::code::BAD_REQUEST),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let result = match &self.last_request_status {
+            Ok(consumed) => format!("Consumed {} records", consumed),
+            Err(e) => e.clone(),
+        };
+        response.set_payload(result.as_bytes())
+    }
+}
+
+extern "C" {
+    /// Schedules a notification of the observers of the `/maps/ringbuf`
+    /// resource.
+    fn ringbuf_observe_notify();
+}
+
+/// Called by the `/maps/ringbuf` resource (see `ffi/ringbuf_observe.c`) to
+/// fill the response or notification with the pending ring buffer records.
+/// Each record is written as a `<map id>:<hex record>` line, oldest first. The
+/// records stay in their ring buffers until the host consumes them through
+/// [`RingBufferConsumeHandler`], the ones that don't fit into the buffer are
+/// left out. Returns the number of bytes written into the buffer.
+#[no_mangle]
+pub extern "C" fn bpf_ringbuf_peek_records(buf: *mut u8, len: usize) -> isize {
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+    let mut written = 0;
+    for id in bpf_maps::non_empty_ring_buffers() {
+        let Ok(records) = bpf_maps::entries(id) else {
+            continue;
+        };
+        for (_, record) in records {
+            let line = format!("{}:{}\n", id, util::to_hex(&record));
+            if written + line.len() > buf.len() {
+                return written as isize;
+            }
+            buf[written..written + line.len()].copy_from_slice(line.as_bytes());
+            written += line.len();
+        }
+    }
+    written as isize
+}
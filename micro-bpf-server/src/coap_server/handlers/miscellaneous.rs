@@ -1,11 +1,21 @@
-use alloc::format;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
 use core::{convert::TryInto, ops::DerefMut};
 use riot_wrappers::{riot_sys, stdio::println};
 
-use crate::vm::RUNNING_WORKERS;
+use crate::{
+    infra::message_queues,
+    vm::{
+        middleware::{helper_profiles, ALL_HELPERS},
+        RUNNING_WORKERS,
+    },
+};
 
-use super::generic_request_error::GenericRequestError;
+use super::{generic_request_error::GenericRequestError, util};
 
 pub struct RiotBoardHandler;
 impl coap_handler::Handler for RiotBoardHandler {
@@ -112,3 +122,166 @@ impl coap_handler::Handler for ConsoleWriteHandler {
         response.set_payload(result.as_bytes())
     }
 }
+
+/// Lists the helpers offered by this firmware build so that the tools can check
+/// programs against them before deploying. Each line of the response contains
+/// the compact description of one helper (see [`HelperFunction::describe`]).
+/// Since the full list doesn't fit into a single response, it is paginated
+/// (see [`util::paginate`]) and the following pages can be requested using
+/// the `/helpers?start=<index>` query.
+///
+/// [`HelperFunction::describe`]: crate::vm::middleware::helpers::HelperFunction::describe
+pub struct HelpersHandler;
+impl coap_handler::Handler for HelpersHandler {
+    /// Response code and the index of the first helper to list.
+    type RequestData = (u8, usize);
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::GET {
+            return Ok((coap_numbers::code::METHOD_NOT_ALLOWED, 0));
+        }
+        match util::numeric_query_param(request, "start", 0) {
+            Ok(start) => Ok((coap_numbers::code::CONTENT, start)),
+            Err(code) => Ok((code, 0)),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        util::PAGINATED_RESPONSE_BUDGET
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        let (code, start) = request;
+        response.set_code(code.try_into().map_err(|_| ()).unwrap());
+        if code != coap_numbers::code::CONTENT {
+            return response.set_payload(&[]);
+        }
+        let lines: Vec<String> = ALL_HELPERS.iter().map(|helper| helper.describe()).collect();
+        response.set_payload(util::paginate(&lines, start).as_bytes())
+    }
+}
+
+/// Allows operators to override the helper permission profiles at runtime.
+/// The request payload needs to be of the form `<admin token>;<name>=<helper IDs>`,
+/// see [`helper_profiles::override_profile`] for the details.
+pub struct HelperProfilesHandler {
+    last_request_status: Result<(), String>,
+}
+
+impl HelperProfilesHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(()),
+        }
+    }
+}
+
+impl coap_handler::Handler for HelperProfilesHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::POST {
+            return Ok(coap_numbers::code::METHOD_NOT_ALLOWED);
+        }
+        let Ok(payload) = core::str::from_utf8(request.payload()) else {
+            self.last_request_status = Err("Request payload is not valid UTF-8".to_string());
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        };
+        self.last_request_status = helper_profiles::override_profile(payload);
+        match &self.last_request_status {
+            Ok(()) => Ok(coap_numbers::code::CHANGED),
+            Err(_) => Ok(coap_numbers::code::BAD_REQUEST),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let result = match &self.last_request_status {
+            Ok(()) => "Success",
+            Err(e) => e.as_str(),
+        };
+        response.set_payload(result.as_bytes())
+    }
+}
+
+/// Allows operators to configure which SUIT slots can be granted access to the
+/// message queues. The request payload needs to be of the form
+/// `<admin token>;<name>=<slot>:<access>,...`, see
+/// [`message_queues::configure_access`] for the details.
+pub struct MessageQueueAccessHandler {
+    last_request_status: Result<(), String>,
+}
+
+impl MessageQueueAccessHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(()),
+        }
+    }
+}
+
+impl coap_handler::Handler for MessageQueueAccessHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::POST {
+            return Ok(coap_numbers::code::METHOD_NOT_ALLOWED);
+        }
+        let Ok(payload) = core::str::from_utf8(request.payload()) else {
+            self.last_request_status = Err("Request payload is not valid UTF-8".to_string());
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        };
+        self.last_request_status = message_queues::configure_access(payload);
+        match &self.last_request_status {
+            Ok(()) => Ok(coap_numbers::code::CHANGED),
+            Err(_) => Ok(coap_numbers::code::BAD_REQUEST),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let result = match &self.last_request_status {
+            Ok(()) => "Success",
+            Err(e) => e.as_str(),
+        };
+        response.set_payload(result.as_bytes())
+    }
+}
@@ -1,5 +1,8 @@
 mod generic_request_error;
+pub mod maps_endpoints;
 pub mod miscellaneous;
+pub mod persistent_storage_endpoints;
+mod native_checksum_endpoint;
 mod native_fletcher16_endpoint;
 pub mod suit_pull_endpoint;
 mod util;
@@ -8,5 +11,5 @@ mod vm_long_execution_handler;
 mod vm_short_execution_handlers;
 
 pub use util::TimedHandler;
-pub use vm_long_execution_handler::VMLongExecutionHandler;
+pub use vm_long_execution_handler::{VMLongExecutionHandler, VMStopHandler};
 pub use vm_short_execution_handlers::{VMExecutionNoDataHandler, VMExecutionOnCoapPktHandler};
@@ -0,0 +1,97 @@
+//! This module contains an endpoint for benchmarking the native checksum and
+//! hashing implementations that back the checksum helpers, so that they can be
+//! compared against the same algorithms implemented in eBPF programs.
+
+use alloc::format;
+use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
+use core::convert::{TryFrom, TryInto};
+use log::debug;
+
+use crate::infra::checksums::{self, ChecksumAlgorithm};
+
+use super::{generic_request_error::GenericRequestError, util::preprocess_request_raw};
+
+extern "C" {
+    /// The largest of the data sets used by the Fletcher16 benchmarks (see
+    /// `ffi/fletcher16_benchmarks.c`), the benchmarks checksum its prefixes.
+    static DATA_2560B: [u8; 2561];
+}
+
+/// Size of the largest data set that can be checksummed.
+const MAX_BENCHMARK_DATA_SIZE: usize = 2560;
+
+/// This handler checksums a prefix of the benchmark data using the native
+/// implementation of the algorithm and measures the time it took. The request
+/// payload needs to be of the form `<algorithm>;<data size>`, where the
+/// algorithm is one of `crc16`, `crc32`, `fletcher16`, `fletcher32`, `sha256`
+/// and `hmac-sha256`.
+pub struct NativeChecksumBenchmarkHandler {
+    execution_time: u32,
+    result: u32,
+}
+
+impl NativeChecksumBenchmarkHandler {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self {
+            execution_time: 0,
+            result: 0,
+        }
+    }
+}
+
+impl coap_handler::Handler for NativeChecksumBenchmarkHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let request_data = match preprocess_request_raw(request) {
+            Ok(request_data) => request_data,
+            Err(code) => return Ok(code),
+        };
+
+        let Some((algorithm, size)) = request_data.trim().split_once(';') else {
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        };
+        let (Ok(algorithm), Ok(size)) =
+            (ChecksumAlgorithm::try_from(algorithm), size.parse::<usize>())
+        else {
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        };
+        if size > MAX_BENCHMARK_DATA_SIZE {
+            debug!("Invalid data size: {}", size);
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        }
+        let data = unsafe { &DATA_2560B[..size] };
+
+        let clock = unsafe { riot_sys::ZTIMER_USEC as *mut riot_sys::inline::ztimer_clock_t };
+        let start: u32 = unsafe { riot_sys::inline::ztimer_now(clock) };
+        self.result = checksums::compute(algorithm, data);
+        self.execution_time = unsafe { riot_sys::inline::ztimer_now(clock) } - start;
+        debug!("Native {:?} of {} bytes: {}", algorithm, size, self.result);
+
+        Ok(coap_numbers::code::CHANGED)
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let resp = format!(
+            "{{\"execution_time\": {}, \"result\": {}}}",
+            self.execution_time, self.result
+        );
+        response.set_payload(resp.as_bytes())
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::{format, string::String, vec::Vec};
+use core::convert::TryInto;
+
+use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
+
+use crate::infra::persistent_storage;
+
+use super::{generic_request_error::GenericRequestError, util};
+
+// This module contains the endpoints allowing the host to export and clear the
+// persistent storage of the programs loaded into the SUIT storage slots.
+
+/// Number of value bytes listed per line, chosen so that a line with the
+/// largest key fits into a single page of the listing.
+const VALUE_BYTES_PER_LINE: usize = 32;
+
+/// Exports the persistent storage of the slot specified using the `slot` query
+/// parameter, each line of the response contains one entry in the form
+/// `<key>=<hex value>`. Values longer than [`VALUE_BYTES_PER_LINE`] bytes are
+/// split into several lines, the continuation lines have the form
+/// `<key>+=<hex value>` and their bytes are appended to the preceding ones.
+/// The listing is paginated (see [`util::paginate`]).
+pub struct PersistentStorageHandler {
+    lines: Result<Vec<String>, String>,
+}
+
+impl PersistentStorageHandler {
+    pub fn new() -> Self {
+        Self {
+            lines: Ok(Vec::new()),
+        }
+    }
+}
+
+impl coap_handler::Handler for PersistentStorageHandler {
+    /// Response code and the index of the first entry to list.
+    type RequestData = (u8, usize);
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        if request.code().into() != coap_numbers::code::GET {
+            return Ok((coap_numbers::code::METHOD_NOT_ALLOWED, 0));
+        }
+        let (Ok(start), Some(Ok(slot))) = (
+            util::numeric_query_param(request, "start", 0),
+            util::query_param(request, "slot").map(|slot| slot.parse::<usize>()),
+        ) else {
+            return Ok((coap_numbers::code::BAD_REQUEST, 0));
+        };
+
+        self.lines = persistent_storage::entries(slot).map(|entries| {
+            let mut lines = Vec::new();
+            for (key, value) in entries {
+                let first = &value[..value.len().min(VALUE_BYTES_PER_LINE)];
+                lines.push(format!("{}={}", key, util::to_hex(first)));
+                for chunk in value.chunks(VALUE_BYTES_PER_LINE).skip(1) {
+                    lines.push(format!("{}+={}", key, util::to_hex(chunk)));
+                }
+            }
+            lines
+        });
+        match self.lines {
+            Ok(_) => Ok((coap_numbers::code::CONTENT, start)),
+            Err(_) => Ok((coap_numbers::code::INTERNAL_SERVER_ERROR, 0)),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        util::PAGINATED_RESPONSE_BUDGET
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        let (code, start) = request;
+        response.set_code(code.try_into().map_err(|_| ()).unwrap());
+        match &self.lines {
+            Ok(lines) if code == coap_numbers::code::CONTENT => {
+                response.set_payload(util::paginate(lines, start).as_bytes())
+            }
+            Err(e) => response.set_payload(e.as_bytes()),
+            _ => response.set_payload(&[]),
+        }
+    }
+}
+
+/// Removes all entries from the persistent storage of the slot whose number
+/// is sent in the request payload.
+pub struct PersistentStorageClearHandler {
+    last_request_status: Result<(), String>,
+}
+
+impl PersistentStorageClearHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(()),
+        }
+    }
+}
+
+impl coap_handler::Handler for PersistentStorageClearHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let payload = match util::preprocess_request_raw(request) {
+            Ok(payload) => payload,
+            Err(code) => return Ok(code),
+        };
+        self.last_request_status = payload
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| format!("Invalid slot number: {}", e))
+            .and_then(persistent_storage::clear);
+        match &self.last_request_status {
+            Ok(()) => Ok(coap_numbers::code::DELETED),
+            Err(_) => Ok(coap_numbers::code::BAD_REQUEST),
+        }
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        let result = match &self.last_request_status {
+            Ok(()) => "Success",
+            Err(e) => e.as_str(),
+        };
+        response.set_payload(result.as_bytes())
+    }
+}
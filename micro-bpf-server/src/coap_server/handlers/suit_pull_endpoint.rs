@@ -3,19 +3,21 @@ use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
 use log::{debug, error};
 use micro_bpf_common::{
     BinaryFileLayout, HelperAccessListSource, HelperAccessVerification, SuitPullRequest,
     VMConfiguration,
 };
-use micro_bpf_elf_utils::extract_allowed_helpers;
 
 use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
 
 use crate::{
     infra::suit_storage::{self, SUIT_STORAGE_SLOT_SIZE},
-    vm::{middleware::helpers::HelperAccessList, rbpf_vm},
+    vm::{
+        middleware::{helper_profiles, helpers::HelperAccessList},
+        rbpf_vm,
+    },
 };
 
 use super::{generic_request_error::GenericRequestError, util::preprocess_request_raw};
@@ -85,17 +87,32 @@ impl coap_handler::Handler for SuitPullHandler {
             let program = suit_storage::load_program(&mut program_buffer, config.suit_slot);
 
             let helper_idxs: Vec<u32> = match config.helper_access_list_source {
-                HelperAccessListSource::ExecuteRequest => HelperAccessList::from(request.helpers)
-                    .0
-                    .into_iter()
-                    .map(|f| f.id as u32)
-                    .collect(),
+                HelperAccessListSource::ExecuteRequest => {
+                    let helpers = HelperAccessList::try_from(request.helpers).and_then(|list| {
+                        let listed = list.0.into_iter().map(|f| f.id).collect();
+                        helper_profiles::with_profile(listed, request.helper_profile.as_deref())
+                    });
+                    match helpers {
+                        Ok(helpers) => helpers.into_iter().map(|id| id as u32).collect(),
+                        Err(e) => {
+                            error!("{}", e);
+                            self.last_request_status = Err(e);
+                            let _ = suit_storage::suit_erase(config.suit_slot);
+                            Err(coap_numbers::code::BAD_REQUEST)?
+                        }
+                    }
+                }
                 HelperAccessListSource::BinaryMetadata => {
                     if config.binary_layout == BinaryFileLayout::ExtendedHeader {
-                        extract_allowed_helpers(&program)
-                            .into_iter()
-                            .map(|id| id as u32)
-                            .collect()
+                        match helper_profiles::metadata_helpers(&program) {
+                            Ok(helpers) => helpers.into_iter().map(|id| id as u32).collect(),
+                            Err(e) => {
+                                error!("{}", e);
+                                self.last_request_status = Err(e);
+                                let _ = suit_storage::suit_erase(config.suit_slot);
+                                Err(coap_numbers::code::BAD_REQUEST)?
+                            }
+                        }
                     } else {
                         let error_msg = "Tried to extract allowed helper functions from an incompatible binary file.";
                         error!("{}", error_msg);
@@ -1,5 +1,10 @@
-use alloc::string::{String, ToString};
-use coap_message::ReadableMessage;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+use coap_message::{MessageOption, ReadableMessage};
 use micro_bpf_common::VMExecutionRequest;
 use riot_wrappers::gcoap::PacketBuffer;
 
@@ -106,3 +111,97 @@ where
 
     Ok(request_data)
 }
+
+/// Maximum number of payload bytes that the paginated listings can use, the
+/// whole response needs to fit into the gcoap PDU buffer (CONFIG_GCOAP_PDU_BUF_SIZE).
+pub const PAGINATED_RESPONSE_BUDGET: usize = 96;
+
+/// Returns the value of the URI query parameter with a given name, e.g. the
+/// value of `start` for the request `/helpers?start=10`.
+pub fn query_param(request: &impl ReadableMessage, name: &str) -> Option<String> {
+    for option in request.options() {
+        if option.number() != coap_numbers::option::URI_QUERY {
+            continue;
+        }
+        let Ok(query) = core::str::from_utf8(option.value()) else {
+            continue;
+        };
+        if let Some((key, value)) = query.split_once('=') {
+            if key == name {
+                return Some(value.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Returns the numeric URI query parameter with a given name, `default` if
+/// it isn't present and a bad request error if it isn't a valid number.
+pub fn numeric_query_param(
+    request: &impl ReadableMessage,
+    name: &str,
+    default: usize,
+) -> Result<usize, u8> {
+    match query_param(request, name) {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| coap_numbers::code::BAD_REQUEST),
+        None => Ok(default),
+    }
+}
+
+/// Lists the lines starting from the one with index `start` that fit into
+/// [`PAGINATED_RESPONSE_BUDGET`]. If not all of them fit, the last line of the
+/// listing is `next=<index>`, which tells the client which `start` to request next.
+/// The first listed line is always included so that the client makes progress,
+/// if it doesn't fit into the budget on its own, it is truncated.
+pub fn paginate(lines: &[String], start: usize) -> String {
+    // Space that needs to stay available for the pagination line.
+    let next_line_len = format!("next={}", lines.len()).len();
+    let mut listing = String::new();
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let remaining = lines.len() - i - 1;
+        let reserved = if remaining > 0 { next_line_len + 1 } else { 0 };
+        let available = PAGINATED_RESPONSE_BUDGET.saturating_sub(listing.len() + 1 + reserved);
+        if line.len() > available {
+            if i != start {
+                listing.push_str(&format!("next={}", i));
+                break;
+            }
+            let mut end = available;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            listing.push_str(&line[..end]);
+        } else {
+            listing.push_str(line);
+        }
+        listing.push('\n');
+    }
+    listing
+}
+
+/// Encodes the bytes as a lowercase hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Decodes a hex string, e.g. a map key sent by the host.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 {
+        Err(format!("Invalid hex string: {}", hex))?;
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            core::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or(format!("Invalid hex string: {}", hex))
+        })
+        .collect()
+}
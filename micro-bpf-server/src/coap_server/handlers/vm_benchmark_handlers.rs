@@ -9,7 +9,15 @@ use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessa
 
 use crate::{
     coap_server::handlers::util::preprocess_request_concrete_impl,
-    vm::{construct_vm, timed_vm::BenchmarkResult, TimedVm},
+    vm::{
+        construct_vm,
+        middleware::{
+            helper_profiles,
+            job_context::{self, ExecutionModel},
+        },
+        timed_vm::BenchmarkResult,
+        TimedVm,
+    },
 };
 
 use micro_bpf_common::VMExecutionRequest;
@@ -37,7 +45,11 @@ impl VMExecutionBenchmarkHandler {
     }
 
     fn handle_benchmark_execution(&mut self, request: VMExecutionRequest) -> Result<u8, u8> {
-        let vm = construct_vm(request.configuration, request.allowed_helpers)
+        let _job = job_context::enter_job(&request, ExecutionModel::ShortLived)
+            .map_err(util::internal_server_error)?;
+        let allowed_helpers =
+            helper_profiles::request_helpers(&request).map_err(util::internal_server_error)?;
+        let vm = construct_vm(request.configuration, allowed_helpers)
             .map_err(util::internal_server_error)?;
 
         let mut vm = TimedVm::new(vm);
@@ -128,7 +140,13 @@ impl VMExecutionOnCoapPktBenchmarkHandler {
         request: VMExecutionRequest,
         pkt: PacketBuffer,
     ) -> isize {
-        let Ok(vm) = construct_vm(request.configuration, request.allowed_helpers) else {
+        let Ok(_job) = job_context::enter_job(&request, ExecutionModel::CoapPacket) else {
+            return Self::NO_BYTES_WRITTEN;
+        };
+        let Ok(allowed_helpers) = helper_profiles::request_helpers(&request) else {
+            return Self::NO_BYTES_WRITTEN;
+        };
+        let Ok(vm) = construct_vm(request.configuration, allowed_helpers) else {
             return Self::NO_BYTES_WRITTEN;
         };
 
@@ -1,4 +1,4 @@
-use alloc::{boxed::Box, sync::Arc};
+use alloc::{boxed::Box, format, sync::Arc};
 use core::convert::TryInto;
 use log::{error, info};
 use riot_wrappers::{msg::v2 as msg, mutex::Mutex};
@@ -7,8 +7,9 @@ use crate::
     model::requests::VMExecutionRequestIPC
 ;
 use crate::
-    vm::VM_EXEC_REQUEST
+    vm::{middleware::job_context, VM_EXEC_REQUEST}
 ;
+use crate::util::admin_token;
 use super::{generic_request_error::GenericRequestError, util};
 
 pub struct VMLongExecutionHandler {
@@ -74,3 +75,77 @@ impl coap_handler::Handler for VMLongExecutionHandler {
         }
     }
 }
+
+/// Asks the long-running programs executing the program loaded into the SUIT
+/// slot to stop. The payload has the form `<admin token>;<slot>` (see
+/// [`admin_token::authenticate`]). Programs can't be preempted, so they only
+/// stop once they reach a cancellation point (e.g. `bpf_sleep_ms` or
+/// `bpf_timer_wait`), where the helper returns an error and the program is
+/// expected to exit.
+pub struct VMStopHandler {
+    /// Number of jobs asked to stop by the last request or the reason why it
+    /// was rejected.
+    last_request_status: Result<usize, String>,
+}
+
+impl VMStopHandler {
+    pub fn new() -> Self {
+        Self {
+            last_request_status: Ok(0),
+        }
+    }
+}
+
+impl coap_handler::Handler for VMStopHandler {
+    type RequestData = u8;
+    type ExtractRequestError = GenericRequestError;
+    type BuildResponseError<M: MinimalWritableMessage> =
+        <M as coap_message::MinimalWritableMessage>::SetPayloadError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let payload = match util::preprocess_request_raw(request) {
+            Ok(payload) => payload,
+            Err(code) => return Ok(code),
+        };
+        let slot = match admin_token::authenticate(&payload) {
+            Ok(slot) => slot,
+            Err(e) => {
+                self.last_request_status = Err(e);
+                return Ok(coap_numbers::code::UNAUTHORIZED);
+            }
+        };
+        let Ok(slot) = slot.trim().parse::<usize>() else {
+            self.last_request_status = Err(format!("Invalid slot number: {}", slot));
+            return Ok(coap_numbers::code::BAD_REQUEST);
+        };
+        let stopped_jobs = job_context::request_stop(slot);
+        self.last_request_status = Ok(stopped_jobs);
+        if stopped_jobs == 0 {
+            return Ok(coap_numbers::code::NOT_FOUND);
+        }
+        info!("Requested {} job(s) executing slot {} to stop", stopped_jobs, slot);
+        Ok(coap_numbers::code::CHANGED)
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        1
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        response.set_code(request.try_into().map_err(|_| ()).unwrap());
+        match &self.last_request_status {
+            Ok(stopped_jobs) if request == coap_numbers::code::CHANGED => {
+                response.set_payload(format!("{}", stopped_jobs).as_bytes())
+            }
+            Err(e) => response.set_payload(e.as_bytes()),
+            _ => response.set_payload(&[]),
+        }
+    }
+}
@@ -7,9 +7,13 @@ use riot_wrappers::gcoap::PacketBuffer;
 
 use coap_message::{MinimalWritableMessage, MutableWritableMessage, ReadableMessage};
 
-use crate::
-    vm::construct_vm
-;
+use crate::vm::{
+    construct_vm,
+    middleware::{
+        helper_profiles,
+        job_context::{self, ExecutionModel},
+    },
+};
 
 use micro_bpf_common::VMExecutionRequest;
 
@@ -43,7 +47,16 @@ impl riot_wrappers::gcoap::Handler for VMExecutionOnCoapPktHandler {
 
         debug!("Received VM Execution Request: {:?}", request.configuration);
 
-        let init_result = construct_vm(request.configuration, request.allowed_helpers);
+        let _job = match job_context::enter_job(&request, ExecutionModel::CoapPacket) {
+            Ok(job) => job,
+            Err(e) => {
+                error!("Failed to set up the job context: {}", e);
+                return NO_BYTES_WRITTEN;
+            }
+        };
+
+        let init_result = helper_profiles::request_helpers(&request)
+            .and_then(|allowed_helpers| construct_vm(request.configuration, allowed_helpers));
 
         let Ok(mut vm) = init_result else {
             error!(
@@ -76,7 +89,11 @@ impl VMExecutionNoDataHandler {
     }
 
     fn handle_vm_execution(&mut self, request: VMExecutionRequest) -> Result<u8, u8> {
-        let mut vm = construct_vm(request.configuration, request.allowed_helpers)
+        let _job = job_context::enter_job(&request, ExecutionModel::ShortLived)
+            .map_err(util::internal_server_error)?;
+        let allowed_helpers =
+            helper_profiles::request_helpers(&request).map_err(util::internal_server_error)?;
+        let mut vm = construct_vm(request.configuration, allowed_helpers)
             .map_err(util::internal_server_error)?;
 
         self.result = vm.full_run().unwrap() as i64;
@@ -15,14 +15,23 @@ use riot_wrappers::{
 use crate::{model::requests::VMExecutionRequestIPC, vm::VM_EXEC_REQUEST};
 
 use super::handlers::{
-    miscellaneous::RunningVMHandler,
+    maps_endpoints::{MapEntriesHandler, MapUpdateHandler, MapsHandler, RingBufferConsumeHandler},
+    miscellaneous::{
+        HelperProfilesHandler, HelpersHandler, MessageQueueAccessHandler, RunningVMHandler,
+    },
+    persistent_storage_endpoints::{PersistentStorageClearHandler, PersistentStorageHandler},
     suit_pull_endpoint::SuitPullHandler,
     TimedHandler,
     VMExecutionOnCoapPktHandler,
     VMLongExecutionHandler,
+    VMStopHandler,
 };
 use super::handlers::VMExecutionNoDataHandler;
 
+extern "C" {
+    fn ringbuf_observe_init();
+}
+
 /// The main entrypoint of the gCoAP server. It is responsible for handling
 /// requests from the deployment frame work to load / execute programs.
 ///
@@ -45,6 +54,20 @@ pub fn gcoap_server_main(
 
     // Handlers for querying the state of the deployed system
     let mut running_vm_handler = GcoapHandler(RunningVMHandler);
+    let mut helpers_handler = GcoapHandler(HelpersHandler);
+    let mut helper_profiles_handler = GcoapHandler(HelperProfilesHandler::new());
+    let mut message_queue_access_handler = GcoapHandler(MessageQueueAccessHandler::new());
+
+    // Handlers for inspecting and modifying the BPF maps
+    let mut maps_handler = GcoapHandler(MapsHandler);
+    let mut map_entries_handler = GcoapHandler(MapEntriesHandler::new());
+    let mut map_update_handler = GcoapHandler(MapUpdateHandler::new());
+    let mut ringbuf_consume_handler = GcoapHandler(RingBufferConsumeHandler::new());
+
+    // Handlers for exporting and clearing the persistent storage
+    let mut persistent_storage_handler = GcoapHandler(PersistentStorageHandler::new());
+    let mut persistent_storage_clear_handler =
+        GcoapHandler(PersistentStorageClearHandler::new());
 
     // Suit pull handler for deploying eBPF binaries
     let mut suit_pull_handler = GcoapHandler(SuitPullHandler::new());
@@ -55,6 +78,7 @@ pub fn gcoap_server_main(
     let mut no_data_execution_handler = GcoapHandler(VMExecutionNoDataHandler::new());
     let mut long_execution_handler =
         GcoapHandler(VMLongExecutionHandler::new(execution_send.clone()));
+    let mut stop_handler = GcoapHandler(VMStopHandler::new());
 
     /* Definitions of listeners for the handlers */
     let mut running_vm_listener = SingleHandlerListener::new(
@@ -62,6 +86,48 @@ pub fn gcoap_server_main(
         riot_sys::COAP_GET,
         &mut running_vm_handler,
     );
+    let mut helpers_listener = SingleHandlerListener::new(
+        cstr!("/helpers"),
+        riot_sys::COAP_GET,
+        &mut helpers_handler,
+    );
+    let mut helper_profiles_listener = SingleHandlerListener::new(
+        cstr!("/helpers/profiles"),
+        riot_sys::COAP_POST,
+        &mut helper_profiles_handler,
+    );
+    let mut message_queue_access_listener = SingleHandlerListener::new(
+        cstr!("/queues/access"),
+        riot_sys::COAP_POST,
+        &mut message_queue_access_handler,
+    );
+    let mut maps_listener =
+        SingleHandlerListener::new(cstr!("/maps"), riot_sys::COAP_GET, &mut maps_handler);
+    let mut map_entries_listener = SingleHandlerListener::new(
+        cstr!("/maps/entries"),
+        riot_sys::COAP_GET,
+        &mut map_entries_handler,
+    );
+    let mut map_update_listener = SingleHandlerListener::new(
+        cstr!("/maps/update"),
+        riot_sys::COAP_POST,
+        &mut map_update_handler,
+    );
+    let mut ringbuf_consume_listener = SingleHandlerListener::new(
+        cstr!("/maps/ringbuf/consume"),
+        riot_sys::COAP_POST,
+        &mut ringbuf_consume_handler,
+    );
+    let mut persistent_storage_listener = SingleHandlerListener::new(
+        cstr!("/storage/persistent"),
+        riot_sys::COAP_GET,
+        &mut persistent_storage_handler,
+    );
+    let mut persistent_storage_clear_listener = SingleHandlerListener::new(
+        cstr!("/storage/persistent/clear"),
+        riot_sys::COAP_POST,
+        &mut persistent_storage_clear_handler,
+    );
     let mut suit_pull_listener = SingleHandlerListener::new(
         cstr!("/suit/pull"),
         riot_sys::COAP_POST,
@@ -82,12 +148,31 @@ pub fn gcoap_server_main(
         riot_sys::COAP_POST,
         &mut long_execution_handler,
     );
+    let mut vm_stop_listener = SingleHandlerListener::new(
+        cstr!("/long-running/stop"),
+        riot_sys::COAP_POST,
+        &mut stop_handler,
+    );
+    // The ring buffer resource supports observation, which the gcoap
+    // wrappers don't, so it is implemented in C and registered directly.
+    unsafe { ringbuf_observe_init() };
+
     gcoap::scope(|greg| {
         // Endpoint handlers are registered here.
         greg.register(&mut coap_pkt_vm_listener);
         greg.register(&mut running_vm_listener);
+        greg.register(&mut helpers_listener);
+        greg.register(&mut helper_profiles_listener);
+        greg.register(&mut message_queue_access_listener);
+        greg.register(&mut maps_listener);
+        greg.register(&mut map_entries_listener);
+        greg.register(&mut map_update_listener);
+        greg.register(&mut ringbuf_consume_listener);
+        greg.register(&mut persistent_storage_listener);
+        greg.register(&mut persistent_storage_clear_listener);
         greg.register(&mut vm_listener);
         greg.register(&mut vm_spawn_listener);
+        greg.register(&mut vm_stop_listener);
         greg.register(&mut suit_pull_listener);
 
         println!(
@@ -125,6 +210,7 @@ pub fn gcoap_server_testing() -> Result<(), ()> {
     let mut benchmark_handler = GcoapHandler(VMExecutionBenchmarkHandler::new());
     let mut benchmark_on_coap_pkt_handler = VMExecutionOnCoapPktBenchmarkHandler::new();
     let mut fletcher16_handler = GcoapHandler(Fletcher16NativeTestHandler::new());
+    let mut checksum_handler = GcoapHandler(NativeChecksumBenchmarkHandler::new());
 
     /* Definitions of listeners for the handlers */
     let mut console_write_listener = SingleHandlerListener::new(
@@ -143,6 +229,12 @@ pub fn gcoap_server_testing() -> Result<(), ()> {
         riot_sys::COAP_POST,
         &mut fletcher16_handler,
     );
+    // Benchmarks the native implementations backing the checksum helpers.
+    let mut checksum_listener = SingleHandlerListener::new(
+        cstr!("/native/checksum"),
+        riot_sys::COAP_POST,
+        &mut checksum_handler,
+    );
     let mut benchmark_listener = SingleHandlerListener::new(
         cstr!("/benchmark/short-execution"),
         riot_sys::COAP_POST,
@@ -159,6 +251,7 @@ pub fn gcoap_server_testing() -> Result<(), ()> {
         greg.register(&mut console_write_listener);
         greg.register(&mut riot_board_listener);
         greg.register(&mut fletcher16_listener);
+        greg.register(&mut checksum_listener);
         greg.register(&mut benchmark_listener);
         greg.register(&mut benchmark_on_coap_listener);
 
@@ -1,5 +1,34 @@
-
 #include <bpf.h>
 #include <bpf/store.h>
+#include "mutex.h"
+
+/* Serialises all accesses to the global storage. The linker wraps
+ * bpf_store_update_global and bpf_store_fetch_global (see the Makefile), so
+ * that the Femto-Container VM, which calls them directly instead of going
+ * through the helpers implemented in Rust, takes the lock as well. The atomic
+ * helpers hold the lock across their read-modify-write sequences and use the
+ * __real_ variants of the functions in the meantime. */
+static mutex_t _global_store_lock = MUTEX_INIT;
+
+int __real_bpf_store_update_global(uint32_t key, uint32_t value);
+int __real_bpf_store_fetch_global(uint32_t key, uint32_t *value);
+
+void global_store_lock(void) { mutex_lock(&_global_store_lock); }
+
+void global_store_unlock(void) { mutex_unlock(&_global_store_lock); }
 
+int __wrap_bpf_store_update_global(uint32_t key, uint32_t value)
+{
+    mutex_lock(&_global_store_lock);
+    int res = __real_bpf_store_update_global(key, value);
+    mutex_unlock(&_global_store_lock);
+    return res;
+}
 
+int __wrap_bpf_store_fetch_global(uint32_t key, uint32_t *value)
+{
+    mutex_lock(&_global_store_lock);
+    int res = __real_bpf_store_fetch_global(key, value);
+    mutex_unlock(&_global_store_lock);
+    return res;
+}
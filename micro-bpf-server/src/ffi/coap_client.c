@@ -0,0 +1,133 @@
+#include <errno.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/types.h>
+
+#include "irq.h"
+#include "log.h"
+#include "mutex.h"
+#include "net/gcoap.h"
+#include "net/sock/util.h"
+#include "ztimer.h"
+
+/* Outbound CoAP requests sent on behalf of the eBPF programs (see
+ * infra/coap_client.rs). Only one request can be in flight at a time. The
+ * response handler runs on the gcoap thread, so the response is passed to the
+ * waiting caller through the static state below. A request that the caller has
+ * given up on can still be answered later, which is why every request gets a
+ * new generation number and stale responses are ignored. */
+
+static mutex_t _client_lock = MUTEX_INIT;
+
+static struct {
+    unsigned generation;
+    mutex_t done;
+    uint8_t *response;
+    size_t response_len;
+    ssize_t result;
+    unsigned code;
+} _pending = { .done = MUTEX_INIT_LOCKED };
+
+static void _response_handler(const gcoap_request_memo_t *memo,
+                              coap_pkt_t *pdu, const sock_udp_ep_t *remote)
+{
+    (void)remote;
+    unsigned irq_state = irq_disable();
+    if ((unsigned)(uintptr_t)memo->context != _pending.generation) {
+        irq_restore(irq_state);
+        return;
+    }
+    if (memo->state != GCOAP_MEMO_RESP) {
+        _pending.result = -ETIMEDOUT;
+    }
+    else {
+        size_t len = pdu->payload_len < _pending.response_len
+                         ? pdu->payload_len
+                         : _pending.response_len;
+        memcpy(_pending.response, pdu->payload, len);
+        _pending.code = coap_get_code_raw(pdu);
+        _pending.result = len;
+    }
+    /* Invalidate the request so that it can only be completed once. */
+    _pending.generation++;
+    irq_restore(irq_state);
+    mutex_unlock(&_pending.done);
+}
+
+/// Sends a confirmable request with a given method code to the peer (e.g.
+/// `[fe80::1]:5683`) and waits for at most `timeout_ms` milliseconds for the
+/// response. The response payload is copied into the buffer (truncated if it
+/// doesn't fit) and its code is written into `code`. Returns the number of
+/// payload bytes copied or a negative errno on failure.
+ssize_t coap_client_send(const char *peer, unsigned method, const char *uri,
+                         const uint8_t *payload, size_t payload_len,
+                         uint8_t *response, size_t response_len,
+                         uint32_t timeout_ms, unsigned *code)
+{
+    sock_udp_ep_t remote;
+    if (sock_udp_name2ep(&remote, peer) < 0) {
+        LOG_ERROR("[CoAP client]: invalid peer address %s\n", peer);
+        return -EINVAL;
+    }
+    if (remote.port == 0) {
+        remote.port = COAP_PORT;
+    }
+
+    mutex_lock(&_client_lock);
+
+    coap_pkt_t pdu;
+    uint8_t buf[CONFIG_GCOAP_PDU_BUF_SIZE];
+    ssize_t len;
+    if (gcoap_req_init(&pdu, buf, sizeof(buf), method, uri) < 0) {
+        mutex_unlock(&_client_lock);
+        return -ENOSPC;
+    }
+    coap_hdr_set_type(pdu.hdr, COAP_TYPE_CON);
+    if (payload_len > 0) {
+        coap_opt_add_format(&pdu, COAP_FORMAT_OCTET);
+        len = coap_opt_finish(&pdu, COAP_OPT_FINISH_PAYLOAD);
+        if (len < 0 || pdu.payload_len < payload_len) {
+            mutex_unlock(&_client_lock);
+            return -ENOSPC;
+        }
+        memcpy(pdu.payload, payload, payload_len);
+        len += payload_len;
+    }
+    else {
+        len = coap_opt_finish(&pdu, COAP_OPT_FINISH_NONE);
+    }
+
+    unsigned irq_state = irq_disable();
+    unsigned generation = _pending.generation;
+    _pending.response = response;
+    _pending.response_len = response_len;
+    irq_restore(irq_state);
+
+    ssize_t result;
+    if (gcoap_req_send(buf, len, &remote, NULL, _response_handler,
+                       (void *)(uintptr_t)generation,
+                       GCOAP_SOCKET_TYPE_UNDEF) <= 0) {
+        result = -EIO;
+    }
+    else if (ztimer_mutex_lock_timeout(ZTIMER_MSEC, &_pending.done,
+                                       timeout_ms) < 0) {
+        result = -ETIMEDOUT;
+    }
+    else {
+        result = _pending.result;
+        *code = _pending.code;
+    }
+
+    irq_state = irq_disable();
+    if (_pending.generation == generation) {
+        /* The response hasn't arrived, make sure that it is ignored. */
+        _pending.generation++;
+    }
+    irq_restore(irq_state);
+    /* If the response arrived right after the timeout expired, the handler
+     * has unlocked the mutex, it needs to be locked for the next request. */
+    mutex_trylock(&_pending.done);
+
+    mutex_unlock(&_client_lock);
+    return result;
+}
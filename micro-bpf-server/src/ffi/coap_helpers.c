@@ -0,0 +1,84 @@
+#include "net/nanocoap.h"
+#include <stdbool.h>
+#include <stdint.h>
+#include <string.h>
+#include <sys/types.h>
+
+/* Wrappers around the nanocoap functions used by the CoAP packet inspection
+ * helpers. All buffers passed in here have already been checked against the
+ * memory regions of the calling program. */
+
+static size_t _min(size_t a, size_t b) { return a < b ? a : b; }
+
+uint8_t *coap_helper_get_pdu(coap_pkt_t *pkt) { return (uint8_t *)pkt->hdr; }
+
+unsigned coap_helper_get_method(coap_pkt_t *pkt) { return coap_get_method(pkt); }
+
+unsigned coap_helper_get_content_format(coap_pkt_t *pkt)
+{
+    return coap_get_content_type(pkt);
+}
+
+/// Copies the value of the `index`-th occurrence of option `optnum` into the
+/// buffer (truncating it if the buffer is too small). Returns the full length
+/// of the option value or -1 if the option isn't present.
+ssize_t coap_helper_get_option(coap_pkt_t *pkt, unsigned optnum, unsigned index,
+                               uint8_t *buf, size_t buf_len)
+{
+    coap_optpos_t opt;
+    uint8_t *value;
+    bool init_opt = true;
+    ssize_t len;
+
+    while ((len = coap_opt_get_next(pkt, &opt, &value, init_opt)) >= 0) {
+        init_opt = false;
+        if (opt.opt_num != optnum) {
+            continue;
+        }
+        if (index-- == 0) {
+            memcpy(buf, value, _min((size_t)len, buf_len));
+            return len;
+        }
+    }
+    return -1;
+}
+
+/// Writes the null-terminated URI path (e.g. "/temperature") into the buffer.
+/// Returns the length of the string including the null byte or a negative
+/// value if it doesn't fit into the buffer.
+ssize_t coap_helper_get_uri_path(coap_pkt_t *pkt, uint8_t *buf, size_t buf_len)
+{
+    return coap_opt_get_string(pkt, COAP_OPT_URI_PATH, buf, buf_len, '/');
+}
+
+/// Copies the payload into the buffer (truncating it if the buffer is too
+/// small) and returns the full length of the payload.
+ssize_t coap_helper_get_payload(coap_pkt_t *pkt, uint8_t *buf, size_t buf_len)
+{
+    memcpy(buf, pkt->payload, _min(pkt->payload_len, buf_len));
+    return pkt->payload_len;
+}
+
+ssize_t coap_helper_add_option(coap_pkt_t *pkt, unsigned optnum,
+                               const uint8_t *value, size_t len)
+{
+    return coap_opt_add_opaque(pkt, optnum, value, len);
+}
+
+ssize_t coap_helper_add_uint_option(coap_pkt_t *pkt, unsigned optnum,
+                                    uint32_t value)
+{
+    return coap_opt_add_uint(pkt, optnum, value);
+}
+
+/// Copies the data into the payload of the response. It needs to be called
+/// after the options have been finished with COAP_OPT_FINISH_PAYLOAD. Returns
+/// the number of bytes written or -1 if the payload doesn't fit.
+ssize_t coap_helper_add_payload(coap_pkt_t *pkt, const uint8_t *data, size_t len)
+{
+    if (len > pkt->payload_len) {
+        return -1;
+    }
+    memcpy(pkt->payload, data, len);
+    return len;
+}
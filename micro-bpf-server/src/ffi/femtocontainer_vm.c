@@ -5,10 +5,15 @@
 #include "suit/storage.h"
 #include "suit/storage/ram.h"
 #include "suit/transport/coap.h"
+#include <stdbool.h>
 #include <stdint.h>
 #include <stdlib.h>
+#include <string.h>
 
-static f12r_t _bpf = {
+/* Default configuration of a Femto-Container VM instance. Each VM owns a copy
+ * of this struct so that multiple instances can execute concurrently without
+ * overwriting each other's application, stack and memory regions. */
+static const f12r_t _default_bpf = {
     .stack_region = NULL,
     .rodata_region = NULL,
     .data_region = NULL,
@@ -17,7 +22,8 @@ static f12r_t _bpf = {
     .application_len = 0, /**< Application length */
     .stack = NULL,        /** < We set the stack to null and enforce that the
                                 caller of the methods needs to pass the stack in */
-    .stack_size = 512,    // In line with the eBPF specification
+    .stack_size = 512,    /** < Default in line with the eBPF specification,
+                                it is overridden by the VM configuration */
     .flags = FC_CONFIG_NO_RETURN,
     // TODO: set branches rem to something sensible
     .branches_remaining =
@@ -45,64 +51,128 @@ typedef struct {
     size_t len;
 } pkt_buf;
 
-uint32_t verify_fc_program(uint8_t *program, uint32_t program_len)
+/* State of a single Femto-Container VM instance. The memory regions are
+ * linked into the f12r_t context when the program is given access to a CoAP
+ * packet, so they need to live as long as the context itself. */
+typedef struct {
+    f12r_t bpf;
+    f12r_mem_region_t mem_pdu;
+    f12r_mem_region_t mem_pkt;
+    f12r_mem_region_t mem_buff;
+    bool pkt_regions_added;
+} fc_vm_t;
+
+/// Allocates the context of a new Femto-Container VM instance. The returned
+/// struct is owned by the caller and needs to be released using free_fc_vm.
+fc_vm_t *create_fc_vm(void)
+{
+    fc_vm_t *vm = malloc(sizeof(fc_vm_t));
+    if (vm == NULL) {
+        LOG_ERROR("[BPF handler]: failed to allocate the VM context\n");
+        return NULL;
+    }
+    memset(vm, 0, sizeof(fc_vm_t));
+    vm->bpf = _default_bpf;
+    return vm;
+}
+
+void free_fc_vm(fc_vm_t *vm)
+{
+    free(vm);
+}
+
+uint32_t verify_fc_program(fc_vm_t *vm, uint8_t *program, size_t program_len,
+                           size_t stack_size)
 {
+    f12r_t *bpf = &vm->bpf;
 
     LOG_DEBUG("[BPF handler]: verifying the eBPF program\n");
-    _bpf.application = program;
-    _bpf.application_len = program_len;
+    bpf->application = program;
+    bpf->application_len = program_len;
+    // The stack region used for checking stack accesses is set up in
+    // f12r_setup so the size needs to be known before it is called.
+    bpf->stack_size = stack_size;
     // The verification should have already been done
-    _bpf.flags = FC_CONFIG_NO_RETURN;
+    bpf->flags = FC_CONFIG_NO_RETURN;
     LOG_DEBUG("Program address: %p\n", program);
 
     LOG_DEBUG("[BPF]: executing gcoap handler\n");
 
-    f12r_setup(&_bpf);
-    return f12r_verify_preflight(&_bpf);
+    f12r_setup(bpf);
+    return f12r_verify_preflight(bpf);
 }
 
-void initialize_fc_vm(uint8_t *program, uint32_t program_len)
+void initialize_fc_vm(fc_vm_t *vm, uint8_t *program, size_t program_len,
+                      size_t stack_size)
 {
+    f12r_t *bpf = &vm->bpf;
+
     LOG_DEBUG("[BPF handler]: initialising the eBPF application struct\n");
-    _bpf.application = program;
-    _bpf.application_len = program_len;
+    bpf->application = program;
+    bpf->application_len = program_len;
+    bpf->stack_size = stack_size;
     // The verification should have already been done
-    _bpf.flags |= FC_FLAG_PREFLIGHT_DONE;
-    f12r_setup(&_bpf);
+    bpf->flags |= FC_FLAG_PREFLIGHT_DONE;
+    f12r_setup(bpf);
+}
+
+uint32_t execute_fc_vm(fc_vm_t *vm, uint8_t *stack, int64_t *result)
+{
+    vm->bpf.stack = stack;
+    return f12r_execute(&vm->bpf, 0, 64, result);
 }
 
-uint32_t execute_fc_vm(uint8_t *stack, int64_t *result)
+/// Returns the number of bytes from `start` to the end of the buffer, or 0 if
+/// `start` doesn't point into it.
+static size_t _remaining_len(const uint8_t *buf, size_t len,
+                             const uint8_t *start)
 {
-    _bpf.stack = stack;
-    return f12r_execute(&_bpf, 0, 64, result);
+    if (start == NULL || start < buf || start > buf + len) {
+        return 0;
+    }
+    return (size_t)(buf + len - start);
 }
 
-uint32_t execute_fc_vm_on_coap_pkt(uint8_t *stack, pkt_buf *ctx,
+uint32_t execute_fc_vm_on_coap_pkt(fc_vm_t *vm, uint8_t *stack, pkt_buf *ctx,
                                    int64_t *result)
 {
+    f12r_t *bpf = &vm->bpf;
 
     coap_pkt_t *pdu = ctx->pdu;
     uint8_t *buf = ctx->buf;
     size_t len = ctx->len;
 
-    f12r_mem_region_t mem_pdu;
-    f12r_mem_region_t mem_pkt;
-    f12r_mem_region_t mem_buff;
-
     f12r_coap_ctx_t bpf_ctx = {
         .pkt = pdu,
         .buf = buf,
         .buf_len = len,
     };
 
-    f12r_add_region(&_bpf, &mem_pdu, pdu->hdr, 256,
-                    FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
-    f12r_add_region(&_bpf, &mem_pkt, pdu, sizeof(coap_pkt_t),
-                    FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
-    // Allow for reading and writing to the whole packet payload,
-    f12r_add_region(&_bpf, &mem_buff, pdu->payload, 512,
-                    FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
-
-    _bpf.stack = stack;
-    return f12r_execute_ctx(&_bpf, &bpf_ctx, 12, result);
+    // The header and the payload regions extend to the end of the packet
+    // buffer, so the program can't access memory past a shorter buffer and
+    // can use the whole of a longer one.
+    size_t hdr_len = _remaining_len(buf, len, (uint8_t *)pdu->hdr);
+    size_t payload_len = _remaining_len(buf, len, pdu->payload);
+
+    // The regions are linked into the context only once, adding the same
+    // region again would make the list of regions cyclic.
+    if (!vm->pkt_regions_added) {
+        f12r_add_region(bpf, &vm->mem_pdu, pdu->hdr, hdr_len,
+                        FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
+        f12r_add_region(bpf, &vm->mem_pkt, pdu, sizeof(coap_pkt_t),
+                        FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
+        // Allow for reading and writing to the whole packet payload,
+        f12r_add_region(bpf, &vm->mem_buff, pdu->payload, payload_len,
+                        FC_MEM_REGION_READ | FC_MEM_REGION_WRITE);
+        vm->pkt_regions_added = true;
+    } else {
+        vm->mem_pdu.start = (uint8_t *)pdu->hdr;
+        vm->mem_pdu.len = hdr_len;
+        vm->mem_pkt.start = (uint8_t *)pdu;
+        vm->mem_buff.start = pdu->payload;
+        vm->mem_buff.len = payload_len;
+    }
+
+    bpf->stack = stack;
+    return f12r_execute_ctx(bpf, &bpf_ctx, 12, result);
 }
@@ -1,6 +1,10 @@
+#include "log.h"
+#include "mbox.h"
 #include "suit/transport/coap.h"
+#include "ztimer.h"
 #include <stdint.h>
 #include <stdio.h>
+#include <stdlib.h>
 
 #define MAIN_QUEUE_SIZE (8)
 
@@ -12,3 +16,50 @@ void init_message_queue(void) {
   msg_init_queue(_main_msg_queue, MAIN_QUEUE_SIZE);
   puts("GNRC msg queue initialized");
 }
+
+/* Message queue shared between the eBPF programs. The message buffer is
+ * allocated together with the mailbox so that both can be released at once. */
+typedef struct {
+  mbox_t mbox;
+  msg_t queue[];
+} vm_message_queue_t;
+
+/// Allocates a new message queue that can hold up to `capacity` messages.
+/// The capacity needs to be a power of two as required by the mbox API.
+vm_message_queue_t *create_vm_message_queue(unsigned int capacity) {
+  vm_message_queue_t *queue =
+      malloc(sizeof(vm_message_queue_t) + capacity * sizeof(msg_t));
+  if (queue == NULL) {
+    LOG_ERROR("[Message queues]: failed to allocate the message queue\n");
+    return NULL;
+  }
+  mbox_init(&queue->mbox, queue->queue, capacity);
+  return queue;
+}
+
+void free_vm_message_queue(vm_message_queue_t *queue) { free(queue); }
+
+/// Puts the value into the queue without blocking. Returns 0 on success and
+/// -1 if the queue is full.
+int vm_message_queue_send(vm_message_queue_t *queue, uint32_t value) {
+  msg_t msg = {.type = 0, .content.value = value};
+  return mbox_try_put(&queue->mbox, &msg) ? 0 : -1;
+}
+
+/// Takes the next value out of the queue, waiting for at most `timeout_ms`
+/// milliseconds for it to arrive. A timeout of 0 means that the call doesn't
+/// block. Returns 0 on success and -1 if no value was received in time.
+int vm_message_queue_receive(vm_message_queue_t *queue, uint32_t *value,
+                             uint32_t timeout_ms) {
+  msg_t msg;
+  if (timeout_ms == 0) {
+    if (!mbox_try_get(&queue->mbox, &msg)) {
+      return -1;
+    }
+  } else if (ztimer_mbox_get_timeout(ZTIMER_MSEC, &queue->mbox, &msg,
+                                     timeout_ms) < 0) {
+    return -1;
+  }
+  *value = msg.content.value;
+  return 0;
+}
@@ -0,0 +1,99 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/types.h>
+
+#include "log.h"
+#include "vfs.h"
+#include "vfs_default.h"
+
+/* File operations backing the persistent storage of the eBPF programs (see
+ * infra/persistent_storage.rs). The files live on the default NVM mount point
+ * which is backed by fs_native on native and by the flash of real boards. */
+
+static void _path(char *buf, size_t len, const char *name)
+{
+    snprintf(buf, len, "%s/%s", VFS_DEFAULT_NVM(0), name);
+}
+
+/// Reads the contents of the file into the buffer. Returns the number of bytes
+/// read, 0 if the file doesn't exist and a negative errno on failure.
+ssize_t persistent_storage_read(const char *name, uint8_t *buf, size_t len)
+{
+    char path[64];
+    _path(path, sizeof(path), name);
+
+    int fd = vfs_open(path, O_RDONLY, 0);
+    if (fd == -ENOENT) {
+        return 0;
+    }
+    if (fd < 0) {
+        LOG_ERROR("[Persistent storage]: failed to open %s: %d\n", path, fd);
+        return fd;
+    }
+    ssize_t read = vfs_read(fd, buf, len);
+    vfs_close(fd);
+    return read;
+}
+
+/// Replaces the contents of the file. The data is written into a temporary
+/// file first which is then renamed, so that the old contents are kept if
+/// the device resets in the middle of the write. Returns 0 on success and a
+/// negative errno on failure.
+int persistent_storage_write(const char *name, const uint8_t *buf, size_t len)
+{
+    char path[64];
+    char tmp_path[68];
+    _path(path, sizeof(path), name);
+    snprintf(tmp_path, sizeof(tmp_path), "%s.tmp", path);
+
+    int fd = vfs_open(tmp_path, O_WRONLY | O_CREAT | O_TRUNC, 0);
+    if (fd < 0) {
+        LOG_ERROR("[Persistent storage]: failed to open %s: %d\n", tmp_path, fd);
+        return fd;
+    }
+    ssize_t written = vfs_write(fd, buf, len);
+    int res = vfs_close(fd);
+    if (written < 0 || (size_t)written != len || res < 0) {
+        vfs_unlink(tmp_path);
+        return written < 0 ? (int)written : -EIO;
+    }
+    res = vfs_rename(tmp_path, path);
+    if (res == -EEXIST) {
+        /* Not all file systems allow renaming over an existing file. */
+        vfs_unlink(path);
+        res = vfs_rename(tmp_path, path);
+    }
+    return res;
+}
+
+/// Appends the data to the end of the file, creating it if it doesn't exist.
+/// Returns 0 on success and a negative errno on failure.
+int persistent_storage_append(const char *name, const uint8_t *buf, size_t len)
+{
+    char path[64];
+    _path(path, sizeof(path), name);
+
+    int fd = vfs_open(path, O_WRONLY | O_CREAT | O_APPEND, 0);
+    if (fd < 0) {
+        LOG_ERROR("[Persistent storage]: failed to open %s: %d\n", path, fd);
+        return fd;
+    }
+    ssize_t written = vfs_write(fd, buf, len);
+    int res = vfs_close(fd);
+    if (written < 0) {
+        return (int)written;
+    }
+    return ((size_t)written != len || res < 0) ? -EIO : 0;
+}
+
+/// Removes the file, succeeds if it doesn't exist.
+int persistent_storage_remove(const char *name)
+{
+    char path[64];
+    _path(path, sizeof(path), name);
+
+    int res = vfs_unlink(path);
+    return res == -ENOENT ? 0 : res;
+}
@@ -0,0 +1,95 @@
+#include <stdint.h>
+#include <stddef.h>
+#include <sys/types.h>
+#include "event.h"
+#include "event/thread.h"
+#include "net/gcoap.h"
+#include "net/nanocoap.h"
+
+/* CoAP resource through which the host reads the records that programs write
+ * into the ring buffer maps. Clients can either poll it using GET or register
+ * as observers, in which case they are notified whenever a record is
+ * submitted. Reading the resource doesn't remove the records: each response
+ * and notification carries the oldest pending records and the host removes
+ * the ones it has processed by POSTing to /maps/ringbuf/consume (see
+ * `maps_endpoints.rs`), so records carried by a lost message are delivered
+ * again in the next one. */
+
+/// Implemented in Rust (see `maps_endpoints.rs`). Writes as many pending
+/// records as fit into the buffer, one `<map id>:<hex record>` line each, and
+/// returns the number of bytes written.
+extern ssize_t bpf_ringbuf_peek_records(uint8_t *buf, size_t len);
+
+static ssize_t _ringbuf_handler(coap_pkt_t *pdu, uint8_t *buf, size_t len,
+                                coap_request_ctx_t *ctx);
+static void _notify_handler(event_t *event);
+
+static const coap_resource_t _resources[] = {
+    { "/maps/ringbuf", COAP_GET, _ringbuf_handler, NULL },
+};
+
+static gcoap_listener_t _listener = {
+    &_resources[0],
+    ARRAY_SIZE(_resources),
+    GCOAP_SOCKET_TYPE_UNDEF,
+    NULL,
+    NULL,
+    NULL
+};
+
+/* Notifications are sent by the event thread. Posting the event while it is
+ * still queued has no effect, so records submitted in quick succession result
+ * in a single notification. */
+static event_t _notify_event = { .handler = _notify_handler };
+
+/* Only used by the event thread, which handles one event at a time. */
+static uint8_t _notify_buf[CONFIG_GCOAP_PDU_BUF_SIZE];
+
+static ssize_t _ringbuf_handler(coap_pkt_t *pdu, uint8_t *buf, size_t len,
+                                coap_request_ctx_t *ctx)
+{
+    (void)ctx;
+    gcoap_resp_init(pdu, buf, len, COAP_CODE_CONTENT);
+    coap_opt_add_format(pdu, COAP_FORMAT_TEXT);
+    ssize_t header_len = coap_opt_finish(pdu, COAP_OPT_FINISH_PAYLOAD);
+    if (header_len < 0) {
+        return header_len;
+    }
+    ssize_t payload_len = bpf_ringbuf_peek_records(pdu->payload,
+                                                   pdu->payload_len);
+    if (payload_len < 0) {
+        return payload_len;
+    }
+    return header_len + payload_len;
+}
+
+static void _notify_handler(event_t *event)
+{
+    (void)event;
+    coap_pkt_t pdu;
+
+    if (gcoap_obs_init(&pdu, _notify_buf, sizeof(_notify_buf),
+                       &_resources[0]) != GCOAP_OBS_INIT_OK) {
+        return;
+    }
+    coap_opt_add_format(&pdu, COAP_FORMAT_TEXT);
+    ssize_t header_len = coap_opt_finish(&pdu, COAP_OPT_FINISH_PAYLOAD);
+    if (header_len < 0) {
+        return;
+    }
+    ssize_t payload_len = bpf_ringbuf_peek_records(pdu.payload,
+                                                   pdu.payload_len);
+    if (payload_len <= 0) {
+        return;
+    }
+    gcoap_obs_send(_notify_buf, header_len + payload_len, &_resources[0]);
+}
+
+void ringbuf_observe_init(void) { gcoap_register_listener(&_listener); }
+
+/// Schedules a notification of the observers of the resource, it is safe to
+/// call from any thread.
+void ringbuf_observe_notify(void)
+{
+    event_post(EVENT_PRIO_MEDIUM, &_notify_event);
+}
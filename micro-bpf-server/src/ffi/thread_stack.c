@@ -0,0 +1,21 @@
+#include "thread.h"
+#include <stddef.h>
+#include <stdint.h>
+
+/// Returns the location of the stack of the currently running thread. The
+/// stack bounds are only tracked by RIOT when DEVELHELP is enabled (or the
+/// stack is tested / guarded), otherwise -1 is returned.
+int get_active_thread_stack(uint8_t **start, size_t *size)
+{
+#if defined(DEVELHELP) || IS_ACTIVE(SCHED_TEST_STACK) || \
+    defined(MODULE_MPU_STACK_GUARD)
+    thread_t *active = thread_get_active();
+    *start = (uint8_t *)active->stack_start;
+    *size = active->stack_size;
+    return 0;
+#else
+    (void)start;
+    (void)size;
+    return -1;
+#endif
+}
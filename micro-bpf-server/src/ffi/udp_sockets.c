@@ -0,0 +1,80 @@
+#include <errno.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/types.h>
+
+#include "log.h"
+#include "net/sock/udp.h"
+#include "timex.h"
+
+/* UDP sockets of the eBPF programs (see infra/udp_sockets.rs). Each socket is
+ * allocated on the heap so that it can stay bound for the whole lifetime of
+ * the job that opened it. */
+
+/// Binds a new socket to the local port. Returns NULL on failure.
+sock_udp_t *udp_socket_open(uint16_t port)
+{
+    sock_udp_t *sock = malloc(sizeof(sock_udp_t));
+    if (sock == NULL) {
+        LOG_ERROR("[UDP sockets]: failed to allocate the socket\n");
+        return NULL;
+    }
+    sock_udp_ep_t local = SOCK_IPV6_EP_ANY;
+    local.port = port;
+    int res = sock_udp_create(sock, &local, NULL, 0);
+    if (res < 0) {
+        LOG_ERROR("[UDP sockets]: failed to bind port %u: %d\n", port, res);
+        free(sock);
+        return NULL;
+    }
+    return sock;
+}
+
+void udp_socket_close(sock_udp_t *sock)
+{
+    sock_udp_close(sock);
+    free(sock);
+}
+
+/// Sends the datagram to the IPv6 address and port. Returns 0 on success and
+/// a negative errno on failure.
+int udp_socket_send(sock_udp_t *sock, const uint8_t *addr, uint16_t port,
+                    const void *data, size_t len)
+{
+    sock_udp_ep_t remote = {
+        .family = AF_INET6,
+        .netif = SOCK_ADDR_ANY_NETIF,
+        .port = port,
+    };
+    memcpy(remote.addr.ipv6, addr, sizeof(remote.addr.ipv6));
+    ssize_t res = sock_udp_send(sock, data, len, &remote);
+    return res < 0 ? (int)res : 0;
+}
+
+/// Waits for at most `timeout_ms` milliseconds for a datagram and copies it
+/// into the buffer, truncating it if the buffer is too small. The address and
+/// port of the sender are written into `remote_addr` (16 bytes) and
+/// `remote_port`. Returns the number of copied bytes or a negative errno, e.g.
+/// -ETIMEDOUT if no datagram arrived in time.
+ssize_t udp_socket_recv(sock_udp_t *sock, uint8_t *buf, size_t len,
+                        uint32_t timeout_ms, uint8_t *remote_addr,
+                        uint16_t *remote_port)
+{
+    void *data = NULL;
+    void *ctx = NULL;
+    sock_udp_ep_t remote;
+    ssize_t res = sock_udp_recv_buf(sock, &data, &ctx, timeout_ms * US_PER_MS,
+                                    &remote);
+    if (res < 0) {
+        return res;
+    }
+    size_t copied = (size_t)res < len ? (size_t)res : len;
+    memcpy(buf, data, copied);
+    /* The second call releases the packet buffer of the datagram. */
+    sock_udp_recv_buf(sock, &data, &ctx, 0, NULL);
+
+    memcpy(remote_addr, remote.addr.ipv6, sizeof(remote.addr.ipv6));
+    *remote_port = remote.port;
+    return copied;
+}
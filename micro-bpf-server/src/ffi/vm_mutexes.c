@@ -0,0 +1,32 @@
+#include "mutex.h"
+#include "ztimer.h"
+#include <stdint.h>
+
+/* Pool of mutexes backing the named mutexes that eBPF programs can use, see
+ * infra/named_mutexes.rs. The size needs to match MAX_NAMED_MUTEXES there.
+ * Zero-initialised mutexes are unlocked (equivalent to MUTEX_INIT). */
+#define MAX_VM_MUTEXES (8)
+
+static mutex_t _vm_mutexes[MAX_VM_MUTEXES];
+
+/// Locks the mutex at a given index, waiting for at most `timeout_ms`
+/// milliseconds. A timeout of 0 means that the call doesn't block. Returns 0
+/// on success and -1 if the mutex couldn't be locked in time.
+int vm_mutex_lock(uint32_t index, uint32_t timeout_ms) {
+  if (index >= MAX_VM_MUTEXES) {
+    return -1;
+  }
+  if (timeout_ms == 0) {
+    return mutex_trylock(&_vm_mutexes[index]) ? 0 : -1;
+  }
+  return ztimer_mutex_lock_timeout(ZTIMER_MSEC, &_vm_mutexes[index],
+                                   timeout_ms) == 0
+             ? 0
+             : -1;
+}
+
+void vm_mutex_unlock(uint32_t index) {
+  if (index < MAX_VM_MUTEXES) {
+    mutex_unlock(&_vm_mutexes[index]);
+  }
+}
@@ -0,0 +1,142 @@
+//! Global registry of the eBPF maps used by the programs and the `/maps`
+//! endpoints. The maps themselves are implemented in
+//! [`micro_bpf_server_core::bpf_maps`] (see there for the semantics of the
+//! map types and their ownership), this module keeps the single instance
+//! behind a mutex and translates the map definitions of the execution
+//! requests.
+
+use alloc::{string::String, vec::Vec};
+use log::debug;
+use micro_bpf_common::{MapDefinition, MapType};
+use micro_bpf_server_core::bpf_maps::{self as core_maps, MapEntry, MapRegistry};
+use riot_wrappers::mutex::Mutex;
+
+use super::suit_storage::SUIT_STORAGE_SLOTS;
+
+static BPF_MAPS: Mutex<MapRegistry> = Mutex::new(MapRegistry::new(SUIT_STORAGE_SLOTS));
+
+/// Creates the map for the program loaded into the given SUIT slot if it
+/// doesn't exist yet, see [`MapRegistry::create_map`].
+pub fn create_map(definition: &MapDefinition, slot: usize) -> Result<(), String> {
+    if BPF_MAPS.lock().create_map(&to_core(definition), slot)? {
+        debug!("Created map: {:?}", definition);
+    }
+    Ok(())
+}
+
+/// Called when the program is unloaded from the SUIT slot, destroys the maps
+/// that no other loaded program uses.
+pub fn release_slot(slot: usize) {
+    for id in BPF_MAPS.lock().release_slot(slot) {
+        debug!("Destroyed map {} as no loaded program uses it", id);
+    }
+}
+
+/// Returns the definition of the map with a given ID.
+pub fn definition(id: u32) -> Result<MapDefinition, String> {
+    Ok(from_core(&BPF_MAPS.lock().definition(id)?))
+}
+
+/// Returns the definitions of all maps together with the number of entries
+/// (or records in case of ring buffers) that they hold.
+pub fn list_maps() -> Vec<(MapDefinition, usize)> {
+    BPF_MAPS
+        .lock()
+        .list_maps()
+        .iter()
+        .map(|(definition, entries)| (from_core(definition), *entries))
+        .collect()
+}
+
+pub fn lookup(id: u32, key: &[u8], value: &mut [u8], slot: usize) -> Result<(), String> {
+    BPF_MAPS.lock().lookup(id, key, value, slot)
+}
+
+pub fn update(id: u32, key: &[u8], value: &[u8], flags: u64, slot: usize) -> Result<(), String> {
+    BPF_MAPS.lock().update(id, key, value, flags, slot)
+}
+
+pub fn atomic_update(
+    id: u32,
+    key: &[u8],
+    slot: usize,
+    operation: impl FnOnce(u64) -> Option<u64>,
+) -> Result<u64, String> {
+    BPF_MAPS.lock().atomic_update(id, key, slot, operation)
+}
+
+pub fn delete(id: u32, key: &[u8]) -> Result<(), String> {
+    BPF_MAPS.lock().delete(id, key)
+}
+
+pub fn entries(id: u32) -> Result<Vec<MapEntry>, String> {
+    BPF_MAPS.lock().entries(id)
+}
+
+pub fn host_update(id: u32, key: &[u8], value: &[u8]) -> Result<(), String> {
+    BPF_MAPS.lock().host_update(id, key, value)
+}
+
+pub fn clear_slot(slot: usize) {
+    BPF_MAPS.lock().clear_slot(slot)
+}
+
+pub fn ring_buffer_reserve(id: u32, len: usize) -> Result<(), String> {
+    BPF_MAPS.lock().ring_buffer_reserve(id, len)
+}
+
+pub fn ring_buffer_submit(id: u32, record: &[u8]) -> Result<(), String> {
+    BPF_MAPS.lock().ring_buffer_submit(id, record)
+}
+
+/// Releases the space reserved for a record that won't be submitted. The map
+/// could have been destroyed in the meantime, so failures are only logged.
+pub fn ring_buffer_discard(id: u32, len: usize) {
+    if let Err(e) = BPF_MAPS.lock().ring_buffer_discard(id, len) {
+        debug!("{}", e);
+    }
+}
+
+pub fn ring_buffer_output(id: u32, record: &[u8]) -> Result<(), String> {
+    BPF_MAPS.lock().ring_buffer_output(id, record)
+}
+
+pub fn non_empty_ring_buffers() -> Vec<u32> {
+    BPF_MAPS.lock().non_empty_ring_buffers()
+}
+
+pub fn ring_buffer_consume_records(id: u32, count: usize) -> Result<usize, String> {
+    BPF_MAPS.lock().ring_buffer_consume_records(id, count)
+}
+
+fn to_core(definition: &MapDefinition) -> core_maps::MapDefinition {
+    core_maps::MapDefinition {
+        id: definition.id,
+        map_type: match definition.map_type {
+            MapType::Hash => core_maps::MapType::Hash,
+            MapType::Array => core_maps::MapType::Array,
+            MapType::PerSlotArray => core_maps::MapType::PerSlotArray,
+            MapType::RingBuffer => core_maps::MapType::RingBuffer,
+        },
+        key_size: definition.key_size,
+        value_size: definition.value_size,
+        max_entries: definition.max_entries,
+        shared: definition.shared,
+    }
+}
+
+fn from_core(definition: &core_maps::MapDefinition) -> MapDefinition {
+    MapDefinition {
+        id: definition.id,
+        map_type: match definition.map_type {
+            core_maps::MapType::Hash => MapType::Hash,
+            core_maps::MapType::Array => MapType::Array,
+            core_maps::MapType::PerSlotArray => MapType::PerSlotArray,
+            core_maps::MapType::RingBuffer => MapType::RingBuffer,
+        },
+        key_size: definition.key_size,
+        value_size: definition.value_size,
+        max_entries: definition.max_entries,
+        shared: definition.shared,
+    }
+}
@@ -0,0 +1,95 @@
+//! This module provides the checksum, hashing and randomness primitives that
+//! are exposed to the eBPF programs through helpers. Computing them natively
+//! is an order of magnitude faster than doing so inside of the VM.
+//!
+//! CRC-16 and the Fletcher checksums come from the RIOT `checksum` module,
+//! SHA-256 and HMAC-SHA256 from the `hashes` module and the random bytes from
+//! the `random` module. RIOT doesn't provide the standard CRC-32, so it is
+//! implemented in [`micro_bpf_server_core::checksums`] together with the
+//! parsing of the algorithm names.
+
+use alloc::vec::Vec;
+
+pub use micro_bpf_server_core::checksums::{crc32, ChecksumAlgorithm};
+
+/// Length of a SHA-256 digest in bytes.
+pub const SHA256_DIGEST_LENGTH: usize = 32;
+
+extern "C" {
+    fn crc16_ccitt_false_calc(buf: *const u8, len: usize) -> u16;
+    fn fletcher16(buf: *const u8, bytes: usize) -> u16;
+    fn fletcher32(buf: *const u16, words: usize) -> u32;
+    fn sha256(data: *const u8, len: usize, digest: *mut u8) -> *mut u8;
+    fn hmac_sha256(
+        key: *const u8,
+        key_length: usize,
+        data: *const u8,
+        len: usize,
+        digest: *mut u8,
+    );
+    fn random_bytes(buf: *mut u8, size: usize);
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF).
+pub fn crc16(data: &[u8]) -> u16 {
+    unsafe { crc16_ccitt_false_calc(data.as_ptr(), data.len()) }
+}
+
+pub fn fletcher_16(data: &[u8]) -> u16 {
+    unsafe { fletcher16(data.as_ptr(), data.len()) }
+}
+
+/// Fletcher-32 over the data interpreted as native-endian 16 bit words, an
+/// odd trailing byte is padded with zero. The data is copied so that the words
+/// are aligned.
+pub fn fletcher_32(data: &[u8]) -> u32 {
+    let words: Vec<u16> = data
+        .chunks(2)
+        .map(|chunk| u16::from_ne_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]))
+        .collect();
+    unsafe { fletcher32(words.as_ptr(), words.len()) }
+}
+
+pub fn sha_256(data: &[u8]) -> [u8; SHA256_DIGEST_LENGTH] {
+    let mut digest = [0u8; SHA256_DIGEST_LENGTH];
+    unsafe { sha256(data.as_ptr(), data.len(), digest.as_mut_ptr()) };
+    digest
+}
+
+pub fn hmac_sha_256(key: &[u8], data: &[u8]) -> [u8; SHA256_DIGEST_LENGTH] {
+    let mut digest = [0u8; SHA256_DIGEST_LENGTH];
+    unsafe {
+        hmac_sha256(
+            key.as_ptr(),
+            key.len(),
+            data.as_ptr(),
+            data.len(),
+            digest.as_mut_ptr(),
+        )
+    };
+    digest
+}
+
+/// Fills the buffer with bytes from the RIOT pseudo-random number generator.
+/// It isn't cryptographically secure, so the bytes are not suitable for
+/// generating key material or nonces.
+pub fn fill_random(buf: &mut [u8]) {
+    unsafe { random_bytes(buf.as_mut_ptr(), buf.len()) }
+}
+
+/// Computes the checksum of the data using the algorithm, the digests of the
+/// hashes are truncated to their first four bytes (big-endian). It is used by
+/// the benchmarks, which only need to report a result to compare against.
+pub fn compute(algorithm: ChecksumAlgorithm, data: &[u8]) -> u32 {
+    let truncate = |digest: [u8; SHA256_DIGEST_LENGTH]| {
+        u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+    };
+    match algorithm {
+        ChecksumAlgorithm::Crc16 => crc16(data) as u32,
+        ChecksumAlgorithm::Crc32 => crc32(data),
+        ChecksumAlgorithm::Fletcher16 => fletcher_16(data) as u32,
+        ChecksumAlgorithm::Fletcher32 => fletcher_32(data),
+        ChecksumAlgorithm::Sha256 => truncate(sha_256(data)),
+        ChecksumAlgorithm::HmacSha256 => truncate(hmac_sha_256(b"benchmark-key", data)),
+    }
+}
@@ -0,0 +1,97 @@
+//! This module allows eBPF programs to send CoAP requests to other devices,
+//! e.g. to report their measurements to a gateway instead of waiting to be
+//! polled. The requests are sent using gcoap (see `ffi/coap_client.c`) and
+//! only one request can be in flight at a time.
+//!
+//! Programs can only send requests to the peers and URIs that were declared in
+//! their execution request, this module doesn't perform any access control.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::{convert::TryFrom, ffi::c_char};
+
+/// Upper bound on the time that a program can wait for a response.
+pub const MAX_COAP_CLIENT_TIMEOUT_MS: u32 = 5000;
+/// Maximum size of a response payload that is kept for the program.
+pub const MAX_COAP_CLIENT_RESPONSE_SIZE: usize = 64;
+
+/// Request methods that the programs can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoapMethod {
+    Get = 1,
+    Post = 2,
+    Put = 3,
+}
+
+impl TryFrom<u64> for CoapMethod {
+    type Error = String;
+
+    fn try_from(code: u64) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(CoapMethod::Get),
+            2 => Ok(CoapMethod::Post),
+            3 => Ok(CoapMethod::Put),
+            _ => Err(format!("Unsupported CoAP method: {}", code)),
+        }
+    }
+}
+
+/// Response to a request sent by a program.
+pub struct CoapResponse {
+    /// Raw CoAP response code, e.g. 0x45 for 2.05 Content.
+    pub code: u8,
+    pub payload: Vec<u8>,
+}
+
+extern "C" {
+    fn coap_client_send(
+        peer: *const c_char,
+        method: u32,
+        uri: *const c_char,
+        payload: *const u8,
+        payload_len: usize,
+        response: *mut u8,
+        response_len: usize,
+        timeout_ms: u32,
+        code: *mut u32,
+    ) -> isize;
+}
+
+/// Sends the request to the peer (e.g. `[fe80::1]:5683`) and waits for the
+/// response for at most `timeout_ms` milliseconds (capped at
+/// [`MAX_COAP_CLIENT_TIMEOUT_MS`]). Responses with larger payloads than
+/// [`MAX_COAP_CLIENT_RESPONSE_SIZE`] are truncated.
+pub fn send(
+    peer: &str,
+    method: CoapMethod,
+    uri: &str,
+    payload: &[u8],
+    timeout_ms: u32,
+) -> Result<CoapResponse, String> {
+    let peer = format!("{}\0", peer);
+    let uri = format!("{}\0", uri);
+    let mut response = vec![0u8; MAX_COAP_CLIENT_RESPONSE_SIZE];
+    let mut code: u32 = 0;
+    let timeout_ms = core::cmp::min(timeout_ms, MAX_COAP_CLIENT_TIMEOUT_MS);
+
+    let len = unsafe {
+        coap_client_send(
+            peer.as_ptr() as *const c_char,
+            method as u32,
+            uri.as_ptr() as *const c_char,
+            payload.as_ptr(),
+            payload.len(),
+            response.as_mut_ptr(),
+            response.len(),
+            timeout_ms,
+            &mut code as *mut u32,
+        )
+    };
+    if len < 0 {
+        Err(format!("CoAP request to {} failed: {}", peer.trim_end_matches('\0'), len))?;
+    }
+    response.truncate(len as usize);
+    Ok(CoapResponse {
+        code: code as u8,
+        payload: response,
+    })
+}
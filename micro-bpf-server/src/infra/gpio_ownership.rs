@@ -0,0 +1,55 @@
+//! Keeps track of which running job owns each GPIO output pin. Two programs
+//! driving the same pin at the same time would interfere with each other (e.g.
+//! when communicating with a DHT sensor that has strict timing requirements),
+//! so a job can only be started if none of the output pins that it declares
+//! is already owned by some other running job. Owners are identified by their
+//! job IDs, as all jobs executed directly by the CoAP server share the PID of
+//! its thread.
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use log::debug;
+use riot_wrappers::mutex::Mutex;
+
+/// Maps (port, pin) pairs onto the IDs of the jobs that own them.
+static GPIO_OUTPUT_OWNERS: Mutex<BTreeMap<(u32, u32), u32>> = Mutex::new(BTreeMap::new());
+
+/// Claims all given output pins for the job with a given ID. Either all pins
+/// are claimed or none of them.
+pub fn claim_output_pins(pins: &[(u32, u32)], owner: u32) -> Result<(), String> {
+    let mut owners = GPIO_OUTPUT_OWNERS.lock();
+    if let Some((port, pin)) = pins
+        .iter()
+        .find(|pin| owners.get(*pin).is_some_and(|job_id| *job_id != owner))
+    {
+        Err(format!(
+            "GPIO output pin ({}, {}) is already owned by a running program",
+            port, pin
+        ))?;
+    }
+
+    for pin in pins {
+        owners.insert(*pin, owner);
+    }
+    debug!("Job {} claimed GPIO output pins: {:?}", owner, pins);
+    Ok(())
+}
+
+/// Checks whether the pin is an output pin owned by a job other than the one
+/// with a given ID (`None` if the caller isn't running a job).
+pub fn owned_by_other_job(port: u32, pin: u32, job_id: Option<u32>) -> bool {
+    let owners = GPIO_OUTPUT_OWNERS.lock();
+    owners.get(&(port, pin)).is_some_and(|owner| Some(*owner) != job_id)
+}
+
+/// Releases all output pins owned by the job with a given ID.
+pub fn release_output_pins(owner: u32) {
+    let mut owners = GPIO_OUTPUT_OWNERS.lock();
+    let pins: Vec<(u32, u32)> = owners
+        .iter()
+        .filter(|(_, job_id)| **job_id == owner)
+        .map(|(pin, _)| *pin)
+        .collect();
+    for pin in pins {
+        owners.remove(&pin);
+    }
+}
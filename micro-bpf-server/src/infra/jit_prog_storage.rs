@@ -9,12 +9,19 @@
 //! Note that by default the number of JIT storage slots is half of the number
 //! of actual SUIT storage slots to save memory.
 
-use alloc::{format, string::String};
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
 use log::debug;
+use micro_bpf_common::HelperFunctionID;
 use riot_wrappers::mutex::{Mutex, MutexGuard};
 
 use super::suit_storage::{SUIT_STORAGE_SLOTS, SUIT_STORAGE_SLOT_SIZE};
 
+/// Signature of the jitted programs. The first two arguments are the memory
+/// buffer and its length, the following two the metadata buffer and its length
+/// and the last one points to the end of the program stack, which the program
+/// uses as its frame pointer.
+pub type JittedFunction = unsafe fn(*mut u8, usize, *mut u8, usize, *mut u8) -> u32;
+
 pub const JIT_STORAGE_SLOTS_NUM: usize = SUIT_STORAGE_SLOTS / 2;
 pub const JIT_SLOT_SIZE: usize = SUIT_STORAGE_SLOT_SIZE;
 
@@ -42,6 +49,17 @@ static JIT_SLOT_STATE: Mutex<[bool; JIT_STORAGE_SLOTS_NUM]> =
 static JIT_SLOT_TEXT_OFFSETS: Mutex<[usize; JIT_STORAGE_SLOTS_NUM]> =
     Mutex::new([0; JIT_STORAGE_SLOTS_NUM]);
 
+/// Size of the program stack that the program in each slot has been compiled
+/// for. The program needs to be given a stack of that size when it's executed.
+static JIT_SLOT_STACK_SIZES: Mutex<[usize; JIT_STORAGE_SLOTS_NUM]> =
+    Mutex::new([0; JIT_STORAGE_SLOTS_NUM]);
+
+/// Helpers that the program in each slot has been compiled to call. The
+/// jitted program calls them directly, so they can't be restricted any further
+/// without recompiling it.
+static JIT_SLOT_HELPERS: Mutex<BTreeMap<usize, Vec<HelperFunctionID>>> =
+    Mutex::new(BTreeMap::new());
+
 /// Should be used to get access to one jit storage slots to be able to write
 /// the jit-compiled program into it.
 pub fn acquire_storage_slot(
@@ -73,6 +91,7 @@ pub fn free_storage_slot(slot_index: usize) -> Result<(), String> {
     }
 
     slot_states[slot_index] = false;
+    JIT_SLOT_HELPERS.lock().remove(&slot_index);
     let mut guard = JIT_PROGRAM_SLOTS[slot_index].lock();
     guard.0.fill(0);
     guard.1 = 0;
@@ -81,7 +100,7 @@ pub fn free_storage_slot(slot_index: usize) -> Result<(), String> {
 
 pub fn get_program_from_slot(
     slot_index: usize,
-) -> Result<unsafe fn(*mut u8, usize, *mut u8, usize) -> u32, String> {
+) -> Result<JittedFunction, String> {
     validate_slot_index(slot_index)?;
 
     let slot_states = JIT_SLOT_STATE.lock();
@@ -104,6 +123,47 @@ pub fn get_program_from_slot(
     ))
 }
 
+/// Records the size of the program stack that the program written into the
+/// slot has been compiled for.
+pub fn set_stack_size(slot_index: usize, stack_size: usize) -> Result<(), String> {
+    validate_slot_index(slot_index)?;
+    JIT_SLOT_STACK_SIZES.lock()[slot_index] = stack_size;
+    Ok(())
+}
+
+/// Returns the size of the program stack that the program in the slot needs.
+pub fn get_stack_size(slot_index: usize) -> Result<usize, String> {
+    validate_slot_index(slot_index)?;
+    Ok(JIT_SLOT_STACK_SIZES.lock()[slot_index])
+}
+
+/// Records the helpers that the program written into the slot has been
+/// compiled to call.
+pub fn set_helpers(slot_index: usize, helpers: Vec<HelperFunctionID>) -> Result<(), String> {
+    validate_slot_index(slot_index)?;
+    JIT_SLOT_HELPERS.lock().insert(slot_index, helpers);
+    Ok(())
+}
+
+/// Returns the helpers that the program in the slot can call, fails if the
+/// slot doesn't contain a jitted program.
+pub fn get_helpers(slot_index: usize) -> Result<Vec<HelperFunctionID>, String> {
+    validate_slot_index(slot_index)?;
+    JIT_SLOT_HELPERS
+        .lock()
+        .get(&slot_index)
+        .cloned()
+        .ok_or_else(|| format!("Slot index {} doesn't contain a jitted program", slot_index))
+}
+
+/// Returns the location of the jit storage slot with a given index. The jitted
+/// program reads its .rodata and .data sections directly from the slot.
+pub fn get_slot_region(slot_index: usize) -> Result<(*const u8, usize), String> {
+    validate_slot_index(slot_index)?;
+    let guard = JIT_PROGRAM_SLOTS[slot_index].lock();
+    Ok((guard.0.as_ptr(), JIT_SLOT_SIZE))
+}
+
 #[allow(dead_code)]
 fn log_program_contents(program: &[u8], length: usize) {
     let mut prog_str: String = String::new();
@@ -0,0 +1,205 @@
+//! The HD44780 LCD display is a single device shared by all programs. It is
+//! owned by the server and initialized once when a program first opens it.
+//! Programs don't get access to the device itself, but to a handle giving
+//! them write access to a range of rows of the display, e.g. rows 0-1 for one
+//! program and row 2 for another. A handle spanning all rows gives the program
+//! exclusive access to the display.
+//!
+//! The row ranges of handles owned by different running jobs can't overlap,
+//! so the programs can't overwrite each other's output. Handles are released
+//! once the job that opened them finishes.
+
+use alloc::{format, string::String, vec::Vec};
+use log::debug;
+use riot_wrappers::mutex::Mutex;
+
+use crate::peripherals::hd44780_lcd::HD44780LCD;
+
+/// Maximum number of handles that can be open at the same time.
+pub const MAX_LCD_HANDLES: usize = 8;
+
+struct LcdHandle {
+    /// ID of the job that opened the handle.
+    owner: u32,
+    first_row: u8,
+    row_count: u8,
+    /// Position (column, row) at which the next print starts. The cursor of
+    /// the display is shared, so each handle keeps track of its own.
+    cursor: (u8, u8),
+}
+
+impl LcdHandle {
+    fn contains_row(&self, row: u8) -> bool {
+        row >= self.first_row && row - self.first_row < self.row_count
+    }
+
+    fn overlaps(&self, first_row: u8, row_count: u8) -> bool {
+        self.first_row < first_row + row_count && first_row < self.first_row + self.row_count
+    }
+}
+
+struct SharedLcd {
+    lcd: HD44780LCD,
+    handles: Vec<Option<LcdHandle>>,
+}
+
+static LCD: Mutex<Option<SharedLcd>> = Mutex::new(None);
+
+/// Runs the operation on the display, initializing it first if no program has
+/// used it yet.
+fn with_lcd<T>(
+    operation: impl FnOnce(&mut SharedLcd) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut shared = LCD.lock();
+    if shared.is_none() {
+        *shared = Some(SharedLcd {
+            lcd: HD44780LCD::new()?,
+            handles: Vec::new(),
+        });
+    }
+    operation(shared.as_mut().unwrap())
+}
+
+/// Runs the operation on the handle, checking that it is owned by the job.
+fn with_handle<T>(
+    handle: u32,
+    owner: u32,
+    operation: impl FnOnce(&HD44780LCD, &mut LcdHandle) -> Result<T, String>,
+) -> Result<T, String> {
+    with_lcd(|shared| {
+        let entry = (handle as usize)
+            .checked_sub(1)
+            .and_then(|i| shared.handles.get_mut(i))
+            .and_then(Option::as_mut)
+            .filter(|entry| entry.owner == owner);
+        let Some(entry) = entry else {
+            Err(format!("Invalid LCD handle: {}", handle))?
+        };
+        operation(&shared.lcd, entry)
+    })
+}
+
+/// Opens a handle giving the job write access to `row_count` rows of the
+/// display starting at `first_row`, a `row_count` of 0 means all rows of the
+/// display. It fails if any of the rows is written to by another running job.
+/// Opening the same rows again returns the same handle.
+pub fn open(first_row: u8, row_count: u8, owner: u32) -> Result<u32, String> {
+    with_lcd(|shared| {
+        let rows = shared.lcd.rows();
+        let (first_row, row_count) = if row_count == 0 {
+            (0, rows)
+        } else {
+            (first_row, row_count)
+        };
+        if first_row as usize + row_count as usize > rows as usize {
+            Err(format!(
+                "Rows {}-{} are outside of the display with {} rows",
+                first_row,
+                first_row as usize + row_count as usize - 1,
+                rows
+            ))?;
+        }
+
+        for other in shared.handles.iter().flatten() {
+            if other.owner != owner && other.overlaps(first_row, row_count) {
+                Err(format!(
+                    "Rows {}-{} of the LCD display are owned by another running program",
+                    other.first_row,
+                    other.first_row + other.row_count - 1
+                ))?;
+            }
+        }
+        let existing = shared.handles.iter().position(|entry| {
+            entry.as_ref().is_some_and(|entry| {
+                entry.owner == owner
+                    && entry.first_row == first_row
+                    && entry.row_count == row_count
+            })
+        });
+        if let Some(i) = existing {
+            return Ok(i as u32 + 1);
+        }
+
+        let handle = LcdHandle {
+            owner,
+            first_row,
+            row_count,
+            cursor: (0, first_row),
+        };
+        let index = match shared.handles.iter().position(Option::is_none) {
+            Some(i) => i,
+            None if shared.handles.len() < MAX_LCD_HANDLES => {
+                shared.handles.push(None);
+                shared.handles.len() - 1
+            }
+            None => Err(format!("At most {} LCD handles can be open", MAX_LCD_HANDLES))?,
+        };
+        shared.handles[index] = Some(handle);
+        debug!(
+            "Job {} opened rows {}-{} of the LCD display",
+            owner,
+            first_row,
+            first_row + row_count - 1
+        );
+        Ok(index as u32 + 1)
+    })
+}
+
+/// Clears the rows of the handle and moves its cursor to the start of the
+/// first row.
+pub fn clear(handle: u32, owner: u32) -> Result<(), String> {
+    with_handle(handle, owner, |lcd, entry| {
+        if entry.row_count == lcd.rows() {
+            lcd.clear();
+        } else {
+            for row in entry.first_row..entry.first_row + entry.row_count {
+                lcd.set_cursor(0, row);
+                for _ in 0..lcd.cols() {
+                    lcd.write(b' ');
+                }
+            }
+        }
+        entry.cursor = (0, entry.first_row);
+        Ok(())
+    })
+}
+
+/// Moves the cursor of the handle, the row needs to be one of its rows.
+pub fn set_cursor(handle: u32, owner: u32, col: u8, row: u8) -> Result<(), String> {
+    with_handle(handle, owner, |lcd, entry| {
+        if !entry.contains_row(row) || col >= lcd.cols() {
+            Err(format!("Cursor position ({}, {}) is outside of the handle rows", col, row))?;
+        }
+        entry.cursor = (col, row);
+        Ok(())
+    })
+}
+
+/// Prints the text at the cursor of the handle. The text doesn't wrap around
+/// into the next row, whatever doesn't fit into the current one is cut off.
+/// Returns the number of characters printed.
+pub fn print(handle: u32, owner: u32, text: &[u8]) -> Result<usize, String> {
+    with_handle(handle, owner, |lcd, entry| {
+        let (col, row) = entry.cursor;
+        let printed = core::cmp::min(text.len(), (lcd.cols() - col) as usize);
+        lcd.set_cursor(col, row);
+        for c in &text[..printed] {
+            lcd.write(*c);
+        }
+        entry.cursor = (col + printed as u8, row);
+        Ok(printed)
+    })
+}
+
+/// Releases all handles opened by the job, called once the job terminates.
+pub fn release_handles(owner: u32) {
+    let mut shared = LCD.lock();
+    let Some(shared) = shared.as_mut() else {
+        return;
+    };
+    for entry in shared.handles.iter_mut() {
+        if entry.as_ref().is_some_and(|handle| handle.owner == owner) {
+            *entry = None;
+        }
+    }
+}
@@ -25,7 +25,7 @@ use alloc::{collections::BTreeMap, vec::Vec};
 use log::{debug, error};
 use riot_wrappers::{mutex::Mutex, thread};
 
-use super::suit_storage::SUIT_STORAGE_SLOTS;
+use super::{bpf_maps, suit_storage::SUIT_STORAGE_SLOTS};
 
 const EMPTY_MAP: BTreeMap<usize, i32> = BTreeMap::new();
 /// Each SUIT storage slot has its associated BTreeMap storage.
@@ -66,7 +66,8 @@ pub fn local_storage_fetch(key: usize) -> Option<i32> {
     return storage[slot_number.unwrap()].get(&key).copied();
 }
 
-fn lookup_slot_number() -> Option<usize> {
+/// Returns the SUIT slot of the program that is executing on the current thread.
+pub fn lookup_slot_number() -> Option<usize> {
     let pid = thread::get_pid().into();
     let map = THREAD_TO_STORAGE_SLOT.lock();
     return map.get(&pid).copied();
@@ -99,4 +100,8 @@ pub fn deregister_suit_slot(slot: usize) {
 
     let mut storage = LOCAL_STORAGE.lock();
     storage[slot] = BTreeMap::new();
+    drop(storage);
+
+    bpf_maps::clear_slot(slot);
+    bpf_maps::release_slot(slot);
 }
@@ -0,0 +1,269 @@
+//! This module manages the named message queues that allow long-running eBPF
+//! programs to communicate with each other without having to poll the global
+//! key-value storage.
+//!
+//! Queues are identified by their numeric name and are created lazily when the
+//! first execution request that declares a given queue starts executing. Each
+//! job that was granted access to the queue holds a reference to it, once the
+//! last such job terminates, the queue is destroyed together with all messages
+//! that are still in it. This ensures that a queue cannot be freed while some
+//! program is blocked waiting for a message to arrive.
+//!
+//! The queues are backed by RIOT mailboxes and so the messages are single
+//! `u32` values.
+//!
+//! The queue grants in the execution requests aren't trusted on their own,
+//! any client can send a request. The operator configures which SUIT slots can
+//! send to and receive from each queue (see [`configure_access`]) and a job is
+//! only started if all of its grants are covered by that configuration. Queues
+//! without any configured access can't be used at all.
+
+use core::ffi::c_void;
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use log::{debug, error};
+use riot_wrappers::mutex::Mutex;
+
+use crate::util::{admin_token, interruptible_wait};
+
+/// Maximum number of message queues that can exist at the same time.
+pub const MAX_MESSAGE_QUEUES: usize = 8;
+/// Maximum number of messages that a single queue can hold.
+pub const MAX_MESSAGE_QUEUE_CAPACITY: usize = 32;
+
+struct MessageQueue {
+    /// Opaque pointer to the C struct holding the mailbox and its message buffer.
+    queue: *mut c_void,
+    capacity: usize,
+    /// Number of running jobs that were granted access to the queue.
+    users: usize,
+}
+
+// The queue pointer is only ever dereferenced by the mbox API, which is
+// thread-safe.
+unsafe impl Send for MessageQueue {}
+
+static MESSAGE_QUEUES: Mutex<BTreeMap<u32, MessageQueue>> = Mutex::new(BTreeMap::new());
+
+/// Access to a queue that the operator allows the programs loaded into a given
+/// SUIT slot to request.
+#[derive(Clone, Copy)]
+struct QueueAccess {
+    suit_slot: usize,
+    can_send: bool,
+    can_receive: bool,
+}
+
+/// Access configured for each queue name, filled in by [`load_build_access`]
+/// and [`configure_access`].
+static QUEUE_ACCESS: Mutex<BTreeMap<u32, Vec<QueueAccess>>> = Mutex::new(BTreeMap::new());
+
+extern "C" {
+    fn create_vm_message_queue(capacity: u32) -> *mut c_void;
+    fn free_vm_message_queue(queue: *mut c_void);
+    fn vm_message_queue_send(queue: *mut c_void, value: u32) -> i32;
+    fn vm_message_queue_receive(queue: *mut c_void, value: *mut u32, timeout_ms: u32) -> i32;
+}
+
+/// Parses the queue access configured at build time using the
+/// `MESSAGE_QUEUE_ACCESS` environment variable, formatted in the same way as
+/// the runtime configuration requests (without the admin token), e.g.
+/// `MESSAGE_QUEUE_ACCESS="7=0:s,1:r;8=2:sr"`. It needs to be called once when
+/// the server starts. Malformed definitions are logged and skipped.
+pub fn load_build_access() {
+    let Some(definitions) = option_env!("MESSAGE_QUEUE_ACCESS") else {
+        return;
+    };
+    let mut access = QUEUE_ACCESS.lock();
+    for definition in definitions.split(';').filter(|d| !d.trim().is_empty()) {
+        match parse_access_definition(definition) {
+            Ok((name, slots)) => {
+                access.insert(name, slots);
+            }
+            Err(e) => error!("Ignoring message queue access defined at build time: {}", e),
+        }
+    }
+}
+
+/// Handles a queue access configuration request of the form
+/// `<token>;<name>=<slot>:<access>,...` where the access is `s` (send), `r`
+/// (receive) or `sr` (both), e.g. `<token>;7=0:s,1:r` lets the program in slot
+/// 0 send to queue 7 and the one in slot 1 receive from it. It replaces the
+/// access previously configured for the queue. A request of the form
+/// `<token>;<name>` removes it, so that the queue can't be granted anymore.
+/// Running jobs keep the access they were started with.
+pub fn configure_access(request: &str) -> Result<(), String> {
+    let definition = admin_token::authenticate(request)?;
+
+    if !definition.contains('=') {
+        let Ok(name) = definition.trim().parse::<u32>() else {
+            Err(format!("Invalid message queue name: {}", definition))?
+        };
+        QUEUE_ACCESS.lock().remove(&name);
+        return Ok(());
+    }
+
+    let (name, slots) = parse_access_definition(definition)?;
+    let mut access = QUEUE_ACCESS.lock();
+    if !access.contains_key(&name) && access.len() >= MAX_MESSAGE_QUEUES {
+        Err(format!(
+            "Cannot configure access to queue {}, the limit of {} queues has been reached",
+            name, MAX_MESSAGE_QUEUES
+        ))?;
+    }
+    access.insert(name, slots);
+    Ok(())
+}
+
+/// Checks that the operator allows the programs loaded into the SUIT slot to
+/// be granted the requested access to the queue.
+pub fn check_access(
+    name: u32,
+    suit_slot: usize,
+    can_send: bool,
+    can_receive: bool,
+) -> Result<(), String> {
+    let access = QUEUE_ACCESS.lock();
+    let allowed = access
+        .get(&name)
+        .and_then(|slots| slots.iter().find(|access| access.suit_slot == suit_slot));
+    let Some(allowed) = allowed else {
+        Err(format!("Program in slot {} cannot access message queue {}", suit_slot, name))?
+    };
+    if (can_send && !allowed.can_send) || (can_receive && !allowed.can_receive) {
+        Err(format!(
+            "Program in slot {} was granted more access to message queue {} than allowed",
+            suit_slot, name
+        ))?;
+    }
+    Ok(())
+}
+
+/// Parses an access definition of the form `<name>=<slot>:<access>,...`.
+fn parse_access_definition(definition: &str) -> Result<(u32, Vec<QueueAccess>), String> {
+    let Some((name, slots)) = definition.trim().split_once('=') else {
+        Err(format!("Malformed message queue access definition: {}", definition))?
+    };
+    let Ok(name) = name.parse::<u32>() else {
+        Err(format!("Invalid message queue name: {}", name))?
+    };
+    let mut access = Vec::new();
+    for slot in slots.split(',').filter(|slot| !slot.is_empty()) {
+        let Some((suit_slot, mode)) = slot.split_once(':') else {
+            Err(format!("Malformed message queue access: {}", slot))?
+        };
+        let Ok(suit_slot) = suit_slot.parse::<usize>() else {
+            Err(format!("Invalid SUIT slot: {}", suit_slot))?
+        };
+        let (can_send, can_receive) = match mode {
+            "s" => (true, false),
+            "r" => (false, true),
+            "sr" | "rs" => (true, true),
+            _ => Err(format!("Invalid message queue access mode: {}", mode))?,
+        };
+        access.push(QueueAccess {
+            suit_slot,
+            can_send,
+            can_receive,
+        });
+    }
+    Ok((name, access))
+}
+
+/// Attaches the calling job to the queue with a given name, creating it if it
+/// doesn't exist yet. If the queue already exists, the requested capacity
+/// needs to match the one that it was created with.
+pub fn open_queue(name: u32, capacity: usize) -> Result<(), String> {
+    let mut queues = MESSAGE_QUEUES.lock();
+
+    if let Some(queue) = queues.get_mut(&name) {
+        if queue.capacity != capacity {
+            Err(format!(
+                "Message queue {} already exists with capacity {}",
+                name, queue.capacity
+            ))?;
+        }
+        queue.users += 1;
+        return Ok(());
+    }
+
+    // The mailbox implementation requires the capacity to be a power of two.
+    if capacity == 0 || capacity > MAX_MESSAGE_QUEUE_CAPACITY || !capacity.is_power_of_two() {
+        Err(format!(
+            "Invalid message queue capacity {}, it needs to be a power of two no larger than {}",
+            capacity, MAX_MESSAGE_QUEUE_CAPACITY
+        ))?;
+    }
+
+    if queues.len() >= MAX_MESSAGE_QUEUES {
+        Err(format!(
+            "Cannot create message queue {}, the limit of {} queues has been reached",
+            name, MAX_MESSAGE_QUEUES
+        ))?;
+    }
+
+    let queue = unsafe { create_vm_message_queue(capacity as u32) };
+    if queue.is_null() {
+        Err(format!("Failed to allocate message queue {}", name))?;
+    }
+
+    debug!("Created message queue {} with capacity {}", name, capacity);
+    queues.insert(
+        name,
+        MessageQueue {
+            queue,
+            capacity,
+            users: 1,
+        },
+    );
+    Ok(())
+}
+
+/// Detaches the calling job from the queue and destroys the queue if no other
+/// job is using it anymore.
+pub fn close_queue(name: u32) {
+    let mut queues = MESSAGE_QUEUES.lock();
+    let Some(queue) = queues.get_mut(&name) else {
+        return;
+    };
+
+    queue.users -= 1;
+    if queue.users == 0 {
+        let queue = queues.remove(&name).unwrap();
+        debug!("Destroying message queue {}", name);
+        unsafe { free_vm_message_queue(queue.queue) };
+    }
+}
+
+/// Puts the value into the queue without blocking, fails if the queue is full.
+pub fn send(name: u32, value: u32) -> Result<(), String> {
+    let queue = lookup_queue(name)?;
+    if unsafe { vm_message_queue_send(queue, value) } < 0 {
+        Err(format!("Message queue {} is full", name))?;
+    }
+    Ok(())
+}
+
+/// Takes the next value out of the queue, blocking for at most `timeout_ms`
+/// milliseconds until it arrives. The wait is interrupted once `should_stop`
+/// returns true (see [`interruptible_wait::wait`]).
+pub fn receive(name: u32, timeout_ms: u32, should_stop: impl Fn() -> bool) -> Result<u32, String> {
+    let queue = lookup_queue(name)?;
+    // The global lock isn't held while we are waiting for the message. The
+    // queue cannot be destroyed in the meantime because the calling job is
+    // one of its users.
+    let received = interruptible_wait::wait(timeout_ms, should_stop, |step| {
+        let mut value: u32 = 0;
+        let received = unsafe { vm_message_queue_receive(queue, &mut value as *mut u32, step) };
+        Ok((received == 0).then_some(value))
+    })?;
+    received.ok_or_else(|| format!("No message received from queue {} in time", name))
+}
+
+fn lookup_queue(name: u32) -> Result<*mut c_void, String> {
+    let queues = MESSAGE_QUEUES.lock();
+    match queues.get(&name) {
+        Some(queue) => Ok(queue.queue),
+        None => Err(format!("Message queue {} does not exist", name)),
+    }
+}
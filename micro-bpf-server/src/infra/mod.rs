@@ -2,4 +2,13 @@ pub mod allocator;
 pub mod suit_storage;
 pub mod local_storage;
 pub mod jit_prog_storage;
+pub mod message_queues;
 
+pub mod gpio_ownership;
+pub mod bpf_maps;
+pub mod named_mutexes;
+pub mod persistent_storage;
+pub mod coap_client;
+pub mod udp_sockets;
+pub mod checksums;
+pub mod lcd_display;
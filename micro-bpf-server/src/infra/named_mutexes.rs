@@ -0,0 +1,148 @@
+//! This module manages the named mutexes that allow eBPF programs to protect
+//! critical sections spanning multiple helper calls, e.g. a read-modify-write
+//! sequence on several keys of the global storage.
+//!
+//! The mutexes are backed by a fixed pool of RIOT mutexes and are assigned to
+//! names when a program locks a given name. The name keeps its mutex for as
+//! long as some program holds it or waits for it, after that the mutex is
+//! returned to the pool and can be assigned to another name. The pool limits
+//! the number of distinct names that can be in use at the same time.
+//!
+//! Locking is always bounded by a timeout so that a misbehaving program can't
+//! block other programs indefinitely. The mutexes that a program holds are
+//! recorded in its helper context and released when it terminates (see
+//! [`crate::vm::middleware::helper_context::HelperContextGuard`]).
+
+use alloc::{format, string::String, vec::Vec};
+use log::debug;
+use riot_wrappers::mutex::Mutex;
+
+use crate::util::interruptible_wait;
+
+/// Number of RIOT mutexes in the pool, needs to match `MAX_VM_MUTEXES` in
+/// `ffi/vm_mutexes.c`.
+pub const MAX_NAMED_MUTEXES: usize = 8;
+/// Maximum length of the name of a mutex.
+pub const MAX_MUTEX_NAME_LENGTH: usize = 16;
+/// Upper bound on the time that a program can wait for a mutex.
+pub const MAX_MUTEX_TIMEOUT_MS: u32 = 1000;
+
+struct NamedMutex {
+    name: String,
+    /// Thread executing the program that currently holds the mutex.
+    owner: Option<riot_sys::kernel_pid_t>,
+    /// Number of programs that hold the mutex or are waiting for it.
+    users: usize,
+}
+
+/// The index of the mutex in this list is its index in the C pool, `None`
+/// entries are mutexes that aren't assigned to any name.
+static NAMED_MUTEXES: Mutex<Vec<Option<NamedMutex>>> = Mutex::new(Vec::new());
+
+extern "C" {
+    fn vm_mutex_lock(index: u32, timeout_ms: u32) -> i32;
+    fn vm_mutex_unlock(index: u32);
+}
+
+/// Locks the mutex with a given name on behalf of the program running on the
+/// `owner` thread, waiting for at most `timeout_ms` milliseconds (capped at
+/// [`MAX_MUTEX_TIMEOUT_MS`]). The wait is interrupted once `should_stop`
+/// returns true (see [`interruptible_wait::wait`]). Returns the index of the
+/// mutex that needs to be passed into [`unlock`].
+pub fn lock(
+    name: &str,
+    timeout_ms: u32,
+    owner: riot_sys::kernel_pid_t,
+    should_stop: impl Fn() -> bool,
+) -> Result<usize, String> {
+    let index = {
+        let mut mutexes = NAMED_MUTEXES.lock();
+        let index = match position(&mutexes, name) {
+            Some(index) => {
+                // RIOT mutexes aren't recursive, a program that tried to lock
+                // a mutex held by its own thread would only wait until the
+                // timeout expires.
+                if mutexes[index].as_ref().unwrap().owner == Some(owner) {
+                    Err(format!("Mutex {} is already held by this thread", name))?;
+                }
+                index
+            }
+            None => {
+                if name.is_empty() || name.len() > MAX_MUTEX_NAME_LENGTH {
+                    Err(format!(
+                        "Invalid mutex name {}, it needs to have between 1 and {} characters",
+                        name, MAX_MUTEX_NAME_LENGTH
+                    ))?;
+                }
+                let index = match mutexes.iter().position(Option::is_none) {
+                    Some(index) => index,
+                    None if mutexes.len() < MAX_NAMED_MUTEXES => {
+                        mutexes.push(None);
+                        mutexes.len() - 1
+                    }
+                    None => Err(format!(
+                        "Cannot create mutex {}, the limit of {} mutexes in use has been reached",
+                        name, MAX_NAMED_MUTEXES
+                    ))?,
+                };
+                debug!("Assigning mutex {} to name {}", index, name);
+                mutexes[index] = Some(NamedMutex {
+                    name: String::from(name),
+                    owner: None,
+                    users: 0,
+                });
+                index
+            }
+        };
+        mutexes[index].as_mut().unwrap().users += 1;
+        index
+    };
+
+    // The table lock isn't held while waiting so that the owner can unlock it.
+    let timeout_ms = core::cmp::min(timeout_ms, MAX_MUTEX_TIMEOUT_MS);
+    let locked = interruptible_wait::wait(timeout_ms, should_stop, |step| {
+        Ok((unsafe { vm_mutex_lock(index as u32, step) } == 0).then_some(()))
+    });
+    let mut mutexes = NAMED_MUTEXES.lock();
+    if !matches!(locked, Ok(Some(()))) {
+        release_user(&mut mutexes, index);
+        Err(locked.err().unwrap_or(format!("Timed out waiting for mutex {}", name)))?;
+    }
+    mutexes[index].as_mut().unwrap().owner = Some(owner);
+    Ok(index)
+}
+
+/// Returns the index of the mutex with a given name.
+pub fn find(name: &str) -> Option<usize> {
+    position(&NAMED_MUTEXES.lock(), name)
+}
+
+/// Unlocks the mutex, the caller needs to ensure that it is the owner.
+pub fn unlock(index: usize) {
+    let mut mutexes = NAMED_MUTEXES.lock();
+    let Some(Some(mutex)) = mutexes.get_mut(index) else {
+        return;
+    };
+    if mutex.owner.take().is_some() {
+        unsafe { vm_mutex_unlock(index as u32) };
+        release_user(&mut mutexes, index);
+    }
+}
+
+fn position(mutexes: &[Option<NamedMutex>], name: &str) -> Option<usize> {
+    mutexes
+        .iter()
+        .position(|mutex| mutex.as_ref().is_some_and(|mutex| mutex.name == name))
+}
+
+/// Returns the mutex to the pool once no program holds it or waits for it.
+fn release_user(mutexes: &mut [Option<NamedMutex>], index: usize) {
+    let Some(mutex) = mutexes[index].as_mut() else {
+        return;
+    };
+    mutex.users -= 1;
+    if mutex.users == 0 {
+        debug!("Releasing mutex {} assigned to name {}", index, mutex.name);
+        mutexes[index] = None;
+    }
+}
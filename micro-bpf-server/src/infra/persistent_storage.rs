@@ -0,0 +1,220 @@
+//! This module implements the persistent key-value storage of the eBPF
+//! programs. Contrary to the local and global storage, which live in RAM, the
+//! persistent storage survives reboots and so it allows programs to keep e.g.
+//! calibration values or counters.
+//!
+//! Similarly to the local storage, each SUIT storage slot has its own
+//! namespace, which is stored in a single file on the default NVM mount point
+//! of RIOT VFS (a native file system on `native` and the flash on real boards).
+//! The namespace belongs to the program in the slot, it is cleared whenever a
+//! new image is loaded into the slot (see [`super::suit_storage::suit_fetch`])
+//! and it can be cleared explicitly through the CoAP endpoints.
+//!
+//! The file is an append-only log of records, each of which consists of a
+//! `u32` key, a `u16` value length (both little-endian) and the value. A record
+//! with the length [`TOMBSTONE`] and no value deletes the key. Writes only
+//! append a single record, the log is compacted (rewritten with only the live
+//! entries) once appending would make it exceed the per-slot quota, which can be
+//! configured at build time using the `PERSISTENT_STORAGE_QUOTA` environment
+//! variable. A record truncated by a reset in the middle of an append is
+//! dropped and the log is compacted on the next write.
+
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::ffi::c_char;
+use log::debug;
+use riot_wrappers::mutex::Mutex;
+
+use super::suit_storage::SUIT_STORAGE_SLOTS;
+
+/// Quota used if none was configured at build time.
+pub const DEFAULT_PERSISTENT_STORAGE_QUOTA: usize = 512;
+/// Maximum size of a single value in the persistent storage.
+pub const MAX_PERSISTENT_VALUE_SIZE: usize = 64;
+/// Size of the key and the value length preceding each value in the file.
+const ENTRY_HEADER_SIZE: usize = 6;
+/// Value length of the records that delete a key.
+const TOMBSTONE: u16 = u16::MAX;
+
+/// Serialises the accesses to the files, each write reads the whole log to
+/// decide whether it can be appended to or needs to be compacted.
+static PERSISTENT_STORAGE_LOCK: Mutex<()> = Mutex::new(());
+
+extern "C" {
+    fn persistent_storage_read(name: *const c_char, buf: *mut u8, len: usize) -> isize;
+    fn persistent_storage_write(name: *const c_char, buf: *const u8, len: usize) -> i32;
+    fn persistent_storage_append(name: *const c_char, buf: *const u8, len: usize) -> i32;
+    fn persistent_storage_remove(name: *const c_char) -> i32;
+}
+
+type Namespace = BTreeMap<u32, Vec<u8>>;
+
+/// Contents of the log file of a slot.
+struct Log {
+    namespace: Namespace,
+    /// Length of the valid records in the file.
+    len: usize,
+    /// Whether the file ends with a partially written record.
+    truncated: bool,
+}
+
+/// Returns the maximum number of bytes that the namespace of a single slot
+/// can take up.
+pub fn quota() -> usize {
+    option_env!("PERSISTENT_STORAGE_QUOTA")
+        .and_then(|quota| quota.parse().ok())
+        .unwrap_or(DEFAULT_PERSISTENT_STORAGE_QUOTA)
+}
+
+/// Stores the value under the key in the namespace of the slot. Fails if the
+/// namespace would exceed the quota.
+pub fn store(slot: usize, key: u32, value: &[u8]) -> Result<(), String> {
+    if value.len() > MAX_PERSISTENT_VALUE_SIZE {
+        Err(format!(
+            "Value of {} bytes is too large, the limit is {} bytes",
+            value.len(),
+            MAX_PERSISTENT_VALUE_SIZE
+        ))?;
+    }
+    let _lock = PERSISTENT_STORAGE_LOCK.lock();
+    let mut log = load(slot)?;
+    let record = encode_record(key, Some(value));
+    log.namespace.insert(key, value.to_vec());
+    write_record(slot, &log, &record)
+}
+
+/// Returns the value stored under the key in the namespace of the slot.
+pub fn fetch(slot: usize, key: u32) -> Result<Option<Vec<u8>>, String> {
+    let _lock = PERSISTENT_STORAGE_LOCK.lock();
+    Ok(load(slot)?.namespace.remove(&key))
+}
+
+/// Removes the key from the namespace of the slot, returns whether it was
+/// present.
+pub fn delete(slot: usize, key: u32) -> Result<bool, String> {
+    let _lock = PERSISTENT_STORAGE_LOCK.lock();
+    let mut log = load(slot)?;
+    if log.namespace.remove(&key).is_none() {
+        return Ok(false);
+    }
+    write_record(slot, &log, &encode_record(key, None))?;
+    Ok(true)
+}
+
+/// Returns all entries in the namespace of the slot.
+pub fn entries(slot: usize) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let _lock = PERSISTENT_STORAGE_LOCK.lock();
+    Ok(load(slot)?.namespace.into_iter().collect())
+}
+
+/// Removes all entries from the namespace of the slot.
+pub fn clear(slot: usize) -> Result<(), String> {
+    let _lock = PERSISTENT_STORAGE_LOCK.lock();
+    let name = file_name(slot)?;
+    let result = unsafe { persistent_storage_remove(name.as_ptr() as *const c_char) };
+    if result < 0 {
+        Err(format!("Failed to clear the persistent storage of slot {}: {}", slot, result))?;
+    }
+    debug!("Cleared the persistent storage of slot {}", slot);
+    Ok(())
+}
+
+fn file_name(slot: usize) -> Result<String, String> {
+    if slot >= SUIT_STORAGE_SLOTS {
+        Err(format!("Invalid SUIT slot: {}", slot))?;
+    }
+    Ok(format!("bpf-slot-{}.kv\0", slot))
+}
+
+fn encode_record(key: u32, value: Option<&[u8]>) -> Vec<u8> {
+    let mut record = Vec::with_capacity(ENTRY_HEADER_SIZE + value.map_or(0, |v| v.len()));
+    record.extend_from_slice(&key.to_le_bytes());
+    match value {
+        Some(value) => {
+            record.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            record.extend_from_slice(value);
+        }
+        None => record.extend_from_slice(&TOMBSTONE.to_le_bytes()),
+    }
+    record
+}
+
+fn load(slot: usize) -> Result<Log, String> {
+    let name = file_name(slot)?;
+    let mut buf = vec![0u8; quota()];
+    let len = unsafe {
+        persistent_storage_read(name.as_ptr() as *const c_char, buf.as_mut_ptr(), buf.len())
+    };
+    if len < 0 {
+        Err(format!("Failed to read the persistent storage of slot {}: {}", slot, len))?;
+    }
+
+    let mut namespace = Namespace::new();
+    let mut bytes = &buf[..len as usize];
+    while bytes.len() >= ENTRY_HEADER_SIZE {
+        let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let value_len = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if value_len == TOMBSTONE {
+            namespace.remove(&key);
+            bytes = &bytes[ENTRY_HEADER_SIZE..];
+            continue;
+        }
+        let value_len = value_len as usize;
+        if value_len > MAX_PERSISTENT_VALUE_SIZE {
+            Err(format!("Persistent storage of slot {} is corrupted", slot))?;
+        }
+        let Some(value) = bytes.get(ENTRY_HEADER_SIZE..ENTRY_HEADER_SIZE + value_len) else {
+            break;
+        };
+        namespace.insert(key, value.to_vec());
+        bytes = &bytes[ENTRY_HEADER_SIZE + value_len..];
+    }
+    if !bytes.is_empty() {
+        debug!(
+            "Dropping a truncated record at the end of the persistent storage of slot {}",
+            slot
+        );
+    }
+    Ok(Log {
+        namespace,
+        len: len as usize - bytes.len(),
+        truncated: !bytes.is_empty(),
+    })
+}
+
+/// Appends the record to the log of the slot, whose namespace already reflects
+/// the record. If the log wouldn't fit into the quota or it ends with a
+/// truncated record, it is compacted instead.
+fn write_record(slot: usize, log: &Log, record: &[u8]) -> Result<(), String> {
+    let name = file_name(slot)?;
+    if !log.truncated && log.len + record.len() <= quota() {
+        let result = unsafe {
+            persistent_storage_append(name.as_ptr() as *const c_char, record.as_ptr(), record.len())
+        };
+        if result < 0 {
+            Err(format!("Failed to write the persistent storage of slot {}: {}", slot, result))?;
+        }
+        return Ok(());
+    }
+
+    let mut buf = Vec::new();
+    for (key, value) in &log.namespace {
+        buf.extend_from_slice(&encode_record(*key, Some(value.as_slice())));
+    }
+    if buf.len() > quota() {
+        Err(format!(
+            "Persistent storage quota of slot {} exceeded ({} > {} bytes)",
+            slot,
+            buf.len(),
+            quota()
+        ))?;
+    }
+
+    debug!("Compacting the persistent storage of slot {}", slot);
+    let result = unsafe {
+        persistent_storage_write(name.as_ptr() as *const c_char, buf.as_ptr(), buf.len())
+    };
+    if result < 0 {
+        Err(format!("Failed to write the persistent storage of slot {}: {}", slot, result))?;
+    }
+    Ok(())
+}
@@ -9,7 +9,7 @@ use macros::set_env_or_default;
 use micro_bpf_common::BinaryFileLayout;
 use riot_wrappers::{mutex::Mutex, thread};
 
-use crate::infra::local_storage;
+use crate::infra::{local_storage, persistent_storage};
 
 /// Size of each slot in the SUIT storage where the programs get loaded.
 /// It is important that this value is consistent with what is specified in
@@ -36,6 +36,45 @@ pub enum SuitStorageSlotStatus {
     Running,
 }
 
+/// Number of program calls (see [`crate::vm::call_program_in_slot`]) that are
+/// currently executing the program loaded into each slot. A slot cannot be
+/// overwritten or erased while it is being called into.
+static SLOT_CALLERS: Mutex<[usize; SUIT_STORAGE_SLOTS]> = Mutex::new([0; SUIT_STORAGE_SLOTS]);
+
+/// Keeps the program in a SUIT slot from being overwritten or erased until it
+/// is dropped.
+pub struct SlotPin(usize);
+
+impl Drop for SlotPin {
+    fn drop(&mut self) {
+        let mut callers = SLOT_CALLERS.lock();
+        callers[self.0] -= 1;
+    }
+}
+
+/// Checks that the slot contains a program and pins it so that the program
+/// stays in the slot for as long as the returned [`SlotPin`] is alive. The
+/// check and pinning happen under the lock of the slot states, so the
+/// program cannot be replaced in between.
+pub fn pin_slot(slot: usize) -> Result<SlotPin, String> {
+    if slot >= SUIT_STORAGE_SLOTS {
+        Err(format!("SUIT storage slot {} does not exist", slot))?;
+    }
+    let slots = SUIT_STORAGE_STATE.lock();
+    if slots[slot] == SuitStorageSlotStatus::Free {
+        Err(format!("Tried to call a program in an empty SUIT slot {}", slot))?;
+    }
+    let mut callers = SLOT_CALLERS.lock();
+    callers[slot] += 1;
+    Ok(SlotPin(slot))
+}
+
+/// Returns true if some program is currently calling into the given slot.
+/// Needs to be called with the lock of the slot states held.
+fn slot_pinned(slot: usize) -> bool {
+    SLOT_CALLERS.lock()[slot] > 0
+}
+
 // Currently, the interactions with SUIT storage are handled by functions written
 // in native C, ideally they could be reimplemented using unsafe rust bindings from
 // riot_sys.
@@ -99,12 +138,20 @@ pub fn suit_fetch(
         Err("Tried to overwrite a slot that belongs to a currently running program".to_string())?;
     }
 
+    if slot_pinned(slot) {
+        Err("Tried to overwrite a slot that another program is currently calling into".to_string())?;
+    }
+
     let pid = thread::get_pid().into();
     debug!("Thread {} initiating SUIT fetch...", pid);
 
     debug!("Deregistering the local storage associated with the exising slot");
     local_storage::deregister_suit_slot(slot);
 
+    // The persistent storage belongs to the program in the slot, the new
+    // image must not see the data of the previous one.
+    persistent_storage::clear(slot)?;
+
     unsafe {
         initiate_suit_fetch(ip_addr.as_ptr(), netif, suit_manifest.as_ptr(), pid);
 
@@ -155,7 +202,12 @@ pub fn suit_erase(slot: usize) -> Result<(), String> {
         Err("Requested to erase an empty SUIT slot".to_string())?;
     }
 
+    if slot_pinned(slot) {
+        Err("Tried to erase a slot that another program is currently calling into".to_string())?;
+    }
+
     debug!("Erasing SUIT storage slot {}.", slot);
+    local_storage::deregister_suit_slot(slot);
     unsafe {
         let location_ptr = location.as_ptr();
         handle_suit_storage_erase(location_ptr);
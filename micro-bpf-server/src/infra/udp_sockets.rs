@@ -0,0 +1,166 @@
+//! This module allows eBPF programs to exchange raw UDP datagrams, e.g. to
+//! speak simple protocols like syslog or a custom telemetry format. It uses
+//! the GNRC sock API (see `ffi/udp_sockets.c`).
+//!
+//! Each job binds one socket per local port that it uses. The socket is
+//! created by the first operation on the port and stays bound until the job
+//! terminates (see [`release_sockets`]), so datagrams arriving while the
+//! program isn't waiting in [`receive`] are queued by the socket instead of
+//! being dropped.
+//!
+//! Programs can only use the local ports and remote endpoints that were granted
+//! in their execution request, this module doesn't perform any access control.
+
+use core::ffi::c_void;
+
+use alloc::{format, string::String, vec::Vec};
+use embedded_nal::{IpAddr, Ipv6Addr, SocketAddr};
+use log::debug;
+use riot_wrappers::mutex::Mutex;
+
+use crate::util::interruptible_wait;
+
+/// Upper bound on the time that a program can wait for a datagram.
+pub const MAX_UDP_RECEIVE_TIMEOUT_MS: u32 = 5000;
+
+struct JobSocket {
+    job_id: u32,
+    local_port: u16,
+    /// Opaque pointer to the heap-allocated `sock_udp_t`.
+    sock: *mut c_void,
+}
+
+// The socket is only ever used by the thread executing the job that owns it.
+unsafe impl Send for JobSocket {}
+
+static SOCKETS: Mutex<Vec<JobSocket>> = Mutex::new(Vec::new());
+
+extern "C" {
+    fn udp_socket_open(port: u16) -> *mut c_void;
+    fn udp_socket_close(sock: *mut c_void);
+    fn udp_socket_send(
+        sock: *mut c_void,
+        addr: *const u8,
+        port: u16,
+        data: *const u8,
+        len: usize,
+    ) -> i32;
+    fn udp_socket_recv(
+        sock: *mut c_void,
+        buf: *mut u8,
+        len: usize,
+        timeout_ms: u32,
+        remote_addr: *mut u8,
+        remote_port: *mut u16,
+    ) -> isize;
+}
+
+/// Returns the socket of the job bound to the local port, binding it if the
+/// job doesn't have one yet.
+fn socket(job_id: u32, local_port: u16) -> Result<*mut c_void, String> {
+    let mut sockets = SOCKETS.lock();
+    if let Some(socket) = sockets
+        .iter()
+        .find(|socket| socket.job_id == job_id && socket.local_port == local_port)
+    {
+        return Ok(socket.sock);
+    }
+    let sock = unsafe { udp_socket_open(local_port) };
+    if sock.is_null() {
+        Err(format!("Failed to bind UDP port {}", local_port))?;
+    }
+    debug!("Job {} bound UDP port {}", job_id, local_port);
+    sockets.push(JobSocket {
+        job_id,
+        local_port,
+        sock,
+    });
+    Ok(sock)
+}
+
+/// Binds the socket of the job to the local port unless it is already bound.
+pub fn open(job_id: u32, local_port: u16) -> Result<(), String> {
+    socket(job_id, local_port).map(|_| ())
+}
+
+/// Sends the datagram from the local port to the remote endpoint.
+pub fn send(job_id: u32, local_port: u16, remote: SocketAddr, data: &[u8]) -> Result<(), String> {
+    let SocketAddr::V6(remote_v6) = remote else {
+        Err(format!("Only IPv6 remote endpoints are supported: {}", remote))?
+    };
+    let sock = socket(job_id, local_port)?;
+    let result = unsafe {
+        udp_socket_send(
+            sock,
+            remote_v6.ip().octets().as_ptr(),
+            remote.port(),
+            data.as_ptr(),
+            data.len(),
+        )
+    };
+    if result < 0 {
+        Err(format!("Failed to send the datagram to {}: {}", remote, result))?;
+    }
+    Ok(())
+}
+
+/// Waits for at most `timeout_ms` milliseconds (capped at
+/// [`MAX_UDP_RECEIVE_TIMEOUT_MS`]) for a datagram from an accepted remote
+/// endpoint to arrive at the local port and copies it into the buffer.
+/// Datagrams from other endpoints are dropped. The wait is interrupted once
+/// `should_stop` returns true (see [`interruptible_wait::wait`]). Returns the
+/// length of the datagram (truncated to the size of the buffer) and its sender.
+pub fn receive(
+    job_id: u32,
+    local_port: u16,
+    accept: impl Fn(&SocketAddr) -> bool,
+    buffer: &mut [u8],
+    timeout_ms: u32,
+    should_stop: impl Fn() -> bool,
+) -> Result<(usize, SocketAddr), String> {
+    let sock = socket(job_id, local_port)?;
+    let timeout_ms = core::cmp::min(timeout_ms, MAX_UDP_RECEIVE_TIMEOUT_MS);
+    // A dropped datagram ends the current step early, the time spent waiting
+    // for it is still counted as a whole step, so that a flood of datagrams
+    // from other endpoints can't extend the wait.
+    let received = interruptible_wait::wait(timeout_ms, should_stop, |step| {
+        let mut remote_addr = [0u8; 16];
+        let mut remote_port = 0u16;
+        let len = unsafe {
+            udp_socket_recv(
+                sock,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                step,
+                remote_addr.as_mut_ptr(),
+                &mut remote_port,
+            )
+        };
+        if len == -(riot_sys::ETIMEDOUT as isize) {
+            return Ok(None);
+        }
+        if len < 0 {
+            Err(format!("Failed to receive a datagram: {}", len))?;
+        }
+        let remote = SocketAddr::new(IpAddr::V6(Ipv6Addr::from(remote_addr)), remote_port);
+        if !accept(&remote) {
+            debug!("Dropping a datagram from {} on port {}", remote, local_port);
+            return Ok(None);
+        }
+        Ok(Some((len as usize, remote)))
+    })?;
+    received.ok_or_else(|| format!("No datagram received on port {} in time", local_port))
+}
+
+/// Closes all sockets of the job, called once the job terminates.
+pub fn release_sockets(job_id: u32) {
+    let mut sockets = SOCKETS.lock();
+    sockets.retain(|socket| {
+        if socket.job_id != job_id {
+            return true;
+        }
+        unsafe { udp_socket_close(socket.sock) };
+        debug!("Job {} released UDP port {}", job_id, socket.local_port);
+        false
+    });
+}
@@ -37,6 +37,8 @@ riot_main!(main);
 
 fn main(token: thread::StartToken) -> ((), thread::EndToken) {
     util::logger::initialise_logger();
+    vm::middleware::helper_profiles::load_build_profiles();
+    infra::message_queues::load_build_access();
 
     extern "C" {
         fn sound_sensor_saul_register();
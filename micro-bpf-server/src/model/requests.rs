@@ -32,6 +32,15 @@ impl From<msg_t> for VMExecutionRequestIPC {
                 request: Box::new(VMExecutionRequest {
                     configuration: (*req_ptr).configuration,
                     allowed_helpers: (*req_ptr).allowed_helpers.clone(),
+                    helper_profile: (*req_ptr).helper_profile.clone(),
+                    message_queues: (*req_ptr).message_queues.clone(),
+                    saul_device_classes: (*req_ptr).saul_device_classes.clone(),
+                    saul_device_indices: (*req_ptr).saul_device_indices.clone(),
+                    gpio_pins: (*req_ptr).gpio_pins.clone(),
+                    maps: (*req_ptr).maps.clone(),
+                    coap_destinations: (*req_ptr).coap_destinations.clone(),
+                    udp_ports: (*req_ptr).udp_ports.clone(),
+                    time_budget_ms: (*req_ptr).time_budget_ms,
                 }),
             };
         }
@@ -1,4 +1,4 @@
-use alloc::ffi::CString;
+use alloc::{ffi::CString, string::String};
 
 /// HD44780 LCD display wrapper for rust.
 /// This module delegates all calls to the C bindings via ffi.
@@ -8,13 +8,22 @@ pub struct HD44780LCD {
     dev: *mut hd44780_t,
 }
 
+// The device struct is a global defined in the C driver wrapper, the pointer
+// to it stays valid for the whole lifetime of the server.
+unsafe impl Send for HD44780LCD {}
+
 #[allow(dead_code)]
 impl HD44780LCD {
-    pub fn new() -> Self {
-        unsafe {
-            let dev = hd44780_init_default() as *mut hd44780_t;
-            HD44780LCD { dev }
+    /// Initializes the display connected to the board, see
+    /// [`hd44780_init_default`].
+    pub fn new() -> Result<Self, String> {
+        let dev = unsafe { hd44780_init_default() };
+        if dev == -1 {
+            Err("Failed to initialize the HD44780 LCD display")?;
         }
+        Ok(HD44780LCD {
+            dev: dev as *mut hd44780_t,
+        })
     }
 
     pub fn init_from(dev: &mut hd44780_t, params: &hd44780_params_t) -> Self {
@@ -24,6 +33,13 @@ impl HD44780LCD {
         }
     }
 
+    pub fn cols(&self) -> u8 {
+        unsafe { (*self.dev).p.cols }
+    }
+    pub fn rows(&self) -> u8 {
+        unsafe { (*self.dev).p.rows }
+    }
+
     pub fn clear(&self) {
         unsafe { hd44780_clear(self.dev as *const hd44780_t) }
     }
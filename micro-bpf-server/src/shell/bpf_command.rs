@@ -1,12 +1,15 @@
 use crate::{
     model::requests::VMExecutionRequestIPC,
-    vm::{middleware::ALL_HELPERS, VM_EXEC_REQUEST},
+    vm::{
+        middleware::{job_context::GPIO_WILDCARD, ALL_HELPERS},
+        DEFAULT_VM_STACK_SIZE, VM_EXEC_REQUEST,
+    },
 };
-use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use alloc::{boxed::Box, string::String, sync::Arc, vec, vec::Vec};
 use core::{fmt::Write, str::FromStr};
 use micro_bpf_common::{
-    BinaryFileLayout, HelperAccessListSource, HelperAccessVerification, TargetVM, VMConfiguration,
-    VMExecutionRequest,
+    BinaryFileLayout, GpioDirection, GpioPinGrant, HelperAccessListSource,
+    HelperAccessVerification, TargetVM, VMConfiguration, VMExecutionRequest,
 };
 use riot_wrappers::{msg::v2::SendPort, mutex::Mutex};
 
@@ -29,7 +32,7 @@ impl VMExecutionShellCommandHandler {
         let mut usage = || {
             writeln!(
                 stdio,
-                "usage: {} [rBPF | FemtoContainer] <suit-storage-slot (int)> <bytecode-layout-option>",
+                "usage: {} [rBPF | FemtoContainer] <suit-storage-slot (int)> <bytecode-layout-option> [helper-profile]",
                 &args[0]
             )
             .unwrap();
@@ -38,6 +41,11 @@ impl VMExecutionShellCommandHandler {
                 "Available bytecode layout options: OnlyTextSection, FemtoContainersHeader, FunctionRelocationMetadata, RawObjectFile",
             )
             .unwrap();
+            writeln!(
+                stdio,
+                "If no helper profile (e.g. sensors-ro, display, coap-responder, gpio-rw) is given, the program can call all helpers",
+            )
+            .unwrap();
         };
 
         if args.len() < 3 {
@@ -65,13 +73,49 @@ impl VMExecutionShellCommandHandler {
             HelperAccessListSource::ExecuteRequest,
             false,
             false,
+            DEFAULT_VM_STACK_SIZE,
         );
 
-        let allowed_helpers = Vec::from(ALL_HELPERS).into_iter().map(|f| f.id).collect();
+        // The program is only restricted to the helpers of a profile if one
+        // was specified, otherwise it is allowed to call all of them and to
+        // access all GPIO pins.
+        let helper_profile = if args.len() > 4 {
+            Some(String::from(&args[4]))
+        } else {
+            None
+        };
+        let allowed_helpers = match helper_profile {
+            Some(_) => Vec::new(),
+            None => ALL_HELPERS.iter().map(|f| f.id).collect(),
+        };
+        let gpio_pins = match helper_profile {
+            Some(_) => Vec::new(),
+            None => vec![
+                GpioPinGrant {
+                    port: GPIO_WILDCARD,
+                    pin: GPIO_WILDCARD,
+                    direction: GpioDirection::Input,
+                },
+                GpioPinGrant {
+                    port: GPIO_WILDCARD,
+                    pin: GPIO_WILDCARD,
+                    direction: GpioDirection::Output,
+                },
+            ],
+        };
 
         let request = VMExecutionRequest {
             configuration: vm_configuration,
             allowed_helpers,
+            helper_profile,
+            message_queues: Vec::new(),
+            saul_device_classes: Vec::new(),
+            saul_device_indices: Vec::new(),
+            gpio_pins,
+            maps: Vec::new(),
+            coap_destinations: Vec::new(),
+            udp_ports: Vec::new(),
+            time_budget_ms: 0,
         };
 
         let message = VMExecutionRequestIPC {
@@ -0,0 +1,35 @@
+//! Privileged endpoints, i.e. the ones that modify the state shared by all
+//! programs (the helper profile overrides, the message queue access, the
+//! contents of the BPF maps or the records of the ring buffers) or control
+//! running programs (stop requests), require the requests to start with the
+//! admin token set at build time using the `MICRO_BPF_ADMIN_TOKEN` environment
+//! variable. If the token isn't set (or is empty), all requests to those
+//! endpoints are rejected.
+
+use alloc::string::String;
+
+const ADMIN_TOKEN: Option<&str> = option_env!("MICRO_BPF_ADMIN_TOKEN");
+
+/// Checks the token of a request of the form `<token>;<rest>` and returns
+/// the rest of the request.
+pub fn authenticate(request: &str) -> Result<&str, String> {
+    let Some(admin_token) = ADMIN_TOKEN.filter(|token| !token.is_empty()) else {
+        Err("Admin requests are disabled in this build")?
+    };
+    let Some((token, rest)) = request.split_once(';') else {
+        Err("Malformed admin request, it needs to start with the admin token")?
+    };
+    if !tokens_equal(token.as_bytes(), admin_token.as_bytes()) {
+        Err("Invalid admin token")?;
+    }
+    Ok(rest)
+}
+
+/// Compares the tokens in constant time so that the comparison doesn't leak
+/// how many leading characters of the presented token were correct.
+fn tokens_equal(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
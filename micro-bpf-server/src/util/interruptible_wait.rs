@@ -0,0 +1,38 @@
+//! Blocking operations performed on behalf of eBPF programs (locking a named
+//! mutex, waiting for a message or a datagram) wait in short steps so that
+//! they notice when the job that they are waiting for has been asked to stop.
+
+use alloc::string::String;
+
+/// Longest time that a blocking operation waits before checking whether the
+/// job has been asked to stop.
+pub const CANCELLATION_CHECK_INTERVAL_MS: u32 = 100;
+
+/// Calls `attempt` with the number of milliseconds that it can block for (at
+/// most [`CANCELLATION_CHECK_INTERVAL_MS`]) until it produces a value or a
+/// total of `timeout_ms` milliseconds has been spent in it. A timeout of 0
+/// results in a single non-blocking attempt. `should_stop` is checked before
+/// each attempt, once it returns true the wait fails.
+///
+/// Returns `Ok(None)` if the timeout expired without `attempt` producing a
+/// value, errors returned by `attempt` are passed through.
+pub fn wait<T>(
+    timeout_ms: u32,
+    should_stop: impl Fn() -> bool,
+    mut attempt: impl FnMut(u32) -> Result<Option<T>, String>,
+) -> Result<Option<T>, String> {
+    let mut remaining = timeout_ms;
+    loop {
+        if should_stop() {
+            Err(String::from("The job has been asked to stop"))?;
+        }
+        let step = core::cmp::min(remaining, CANCELLATION_CHECK_INTERVAL_MS);
+        if let Some(value) = attempt(step)? {
+            return Ok(Some(value));
+        }
+        remaining -= step;
+        if remaining == 0 {
+            return Ok(None);
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod logger;
 pub mod macros;
 pub mod hacks;
+pub mod admin_token;
+pub mod interruptible_wait;
@@ -1,47 +1,114 @@
 use core::ffi::c_void;
 
-use alloc::{boxed::Box, format, string::String};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
 use log::debug;
+use micro_bpf_common::{
+    BinaryFileLayout, HelperAccessListSource, HelperFunctionID, VMConfiguration,
+};
 use riot_wrappers::gcoap::PacketBuffer;
 
-use crate::{infra::suit_storage, vm::VirtualMachine};
+use crate::{
+    infra::suit_storage,
+    vm::{
+        middleware::helpers::resolve_helper_access_list, rbpf_vm::map_interpreter, VirtualMachine,
+    },
+};
 
 pub struct FemtoContainerVm<'a> {
     program: Option<&'a [u8]>,
     suit_slot: usize,
+    layout: BinaryFileLayout,
+    allowed_helpers: Vec<HelperFunctionID>,
+    helper_access_list_source: HelperAccessListSource,
+    /// Indices of the helpers that the program is allowed to call, resolved
+    /// from the configured helper access list source when initialising the VM.
+    allowed_helper_idxs: Vec<u32>,
+    /// Opaque pointer to the `fc_vm_t` struct of this VM instance holding its
+    /// `f12r_t` context and memory regions. It is allocated on the C side for
+    /// each instance so that multiple instances of the Femto-Container VM can
+    /// execute concurrently on different workers.
+    context: *mut c_void,
+    /// The eBPF program stack, it is allocated by us and passed into the VM
+    /// because for some reason the static stack allocation in the c file
+    /// doesn't work.
+    stack: Vec<u8>,
 }
 
 impl<'a> FemtoContainerVm<'a> {
-    pub fn new(suit_slot: usize) -> Self {
-        Self {
-            program: None,
-            suit_slot,
+    pub fn new(
+        config: VMConfiguration,
+        allowed_helpers: Vec<HelperFunctionID>,
+    ) -> Result<Self, String> {
+        let context = unsafe { create_fc_vm() };
+        if context.is_null() {
+            Err("Failed to allocate the FemtoContainer VM context")?;
         }
+        Ok(Self {
+            program: None,
+            suit_slot: config.suit_slot,
+            layout: config.binary_layout,
+            allowed_helpers,
+            helper_access_list_source: config.helper_access_list_source,
+            allowed_helper_idxs: Vec::new(),
+            context,
+            stack: alloc::vec![0; config.stack_size],
+        })
+    }
+}
+
+impl<'a> Drop for FemtoContainerVm<'a> {
+    fn drop(&mut self) {
+        unsafe { free_fc_vm(self.context) };
     }
 }
 
 impl<'a> VirtualMachine for FemtoContainerVm<'a> {
-    fn verify(&self) -> Result<(), String> {
+    fn verify(&mut self) -> Result<(), String> {
         let Some(program) = self.program else {
             Err("VM not initialised")?
         };
-        let return_code = unsafe { verify_fc_program(program.as_ptr(), program.len()) };
+        let return_code = unsafe {
+            verify_fc_program(self.context, program.as_ptr(), program.len(), self.stack.len())
+        };
 
         if return_code != 0 {
             return Err(format!(
                 "FemtoContainer VM program verification failed with code {}",
                 return_code as i32,
             ));
-        } else {
-            return Ok(());
         }
+
+        Ok(())
     }
 
     fn initialize_vm(&mut self) -> Result<(), String> {
         let program = suit_storage::load_program_static(self.suit_slot);
+        self.allowed_helper_idxs = resolve_helper_access_list(
+            self.helper_access_list_source,
+            self.layout,
+            &self.allowed_helpers,
+            program,
+        )?
+        .0
+        .iter()
+        .map(|helper| helper.id as u32)
+        .collect();
+        // The Femto-Container VM exposes all of its helpers to every program,
+        // so the access restrictions are enforced by checking all helper call
+        // instructions in the bytecode before the program is executed. This is
+        // done regardless of the helper access verification mode because a
+        // load-time check only happens for programs loaded through the SUIT
+        // pull endpoint.
+        rbpf::check_helpers(program, &self.allowed_helper_idxs, map_interpreter(self.layout))
+            .map_err(|e| format!("Error when checking helper function access: {:?}", e))?;
         self.program = Some(program);
         unsafe {
-            initialize_fc_vm(program.as_ptr() as *const u8, program.len());
+            initialize_fc_vm(
+                self.context,
+                program.as_ptr() as *const u8,
+                program.len(),
+                self.stack.len(),
+            );
         }
         Ok(())
     }
@@ -54,12 +121,9 @@ impl<'a> VirtualMachine for FemtoContainerVm<'a> {
             Err("VM not initialised")?
         };
         let mut result: i64 = 0;
-        // We need to define the stack here and pass it into the VM.
-        // For some reason the static stack allocation in the c file doesn't work.
-        let mut stack: [u8; 512] = [0; 512];
 
         unsafe {
-            execute_fc_vm(&mut stack as *mut u8, &mut result as *mut i64);
+            execute_fc_vm(self.context, self.stack.as_mut_ptr(), &mut result as *mut i64);
         }
         Ok(result as u64)
     }
@@ -73,11 +137,9 @@ impl<'a> VirtualMachine for FemtoContainerVm<'a> {
         let mut pkt_box = Box::new(pkt);
         unsafe {
             let mut result: i64 = 0;
-            // We need to define the stack here and pass it into the VM.
-            // For some reason the static stack allocation in the c file doesn't work.
-            let mut stack: [u8; 512] = [0; 512];
             execute_fc_vm_on_coap_pkt(
-                &mut stack as *mut u8,
+                self.context,
+                self.stack.as_mut_ptr(),
                 pkt_box.as_mut() as *mut PacketBuffer as *mut c_void,
                 &mut result as *mut i64,
             );
@@ -94,14 +156,29 @@ extern "C" {
     /// Executes a femtocontainer VM where the eBPF program has access
     /// to the pointer to the CoAP packet.
     fn execute_fc_vm_on_coap_pkt(
+        context: *mut c_void,
         stack: *mut u8,
         pkt: *mut c_void, // PacketBuffer isn't ffi-safe so we need to pass *c_void
         result: *mut i64,
     ) -> u32;
 
-    fn initialize_fc_vm(program: *const u8, program_len: usize) -> u32;
-    fn execute_fc_vm(stack: *mut u8, result: *mut i64) -> u32;
-    fn verify_fc_program(program: *const u8, program_len: usize) -> u32;
+    /// Allocates a new VM instance whose `f12r_t` context is initialised with
+    /// the default configuration. Returns a null pointer if the allocation fails.
+    fn create_fc_vm() -> *mut c_void;
+    fn free_fc_vm(context: *mut c_void);
+    fn initialize_fc_vm(
+        context: *mut c_void,
+        program: *const u8,
+        program_len: usize,
+        stack_size: usize,
+    );
+    fn execute_fc_vm(context: *mut c_void, stack: *mut u8, result: *mut i64) -> u32;
+    fn verify_fc_program(
+        context: *mut c_void,
+        program: *const u8,
+        program_len: usize,
+        stack_size: usize,
+    ) -> u32;
     #[allow(dead_code)]
     fn sensor_processing_from_storage() -> u32;
     #[allow(dead_code)]
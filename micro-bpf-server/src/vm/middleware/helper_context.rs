@@ -0,0 +1,364 @@
+//! Helper functions only receive the five argument registers of the eBPF
+//! program that calls them, so they have no way of telling which program is
+//! calling them and what that program is allowed to do. This module keeps
+//! track of that information for each thread that is currently executing eBPF
+//! programs.
+//!
+//! Every VM pushes a new context frame right before it starts executing its
+//! program and pops it once the execution finishes. Because programs can call
+//! into programs loaded into other SUIT slots (see [`super::bpf_call_slot`]),
+//! each thread maintains a stack of those frames, the top of which corresponds
+//! to the program that is currently running. The height of the stack is
+//! therefore the current call depth.
+//!
+//! The context also contains the memory regions that the program is allowed
+//! to access. Helpers that accept pointers from the program need to check them
+//! against those regions, otherwise a program could read or write arbitrary
+//! host memory by passing it to a helper.
+
+use core::ffi::CStr;
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use micro_bpf_common::{HelperFunctionID, VMConfiguration};
+use riot_wrappers::{mutex::Mutex, thread};
+
+use crate::infra::{bpf_maps, named_mutexes};
+
+/// Maximum number of programs that can be executing at the same time on a
+/// single thread, i.e. the top-level program and the programs that it has
+/// (transitively) called into. Each nested call runs a new VM instance on
+/// the stack of the same worker thread, so this needs to stay small.
+pub const MAX_CALL_DEPTH: usize = 3;
+
+/// Maximum length of a C string (excluding the terminating null byte) that
+/// can be passed into a helper function.
+pub const MAX_HELPER_STRING_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+/// A contiguous region of memory that the program is allowed to access, e.g.
+/// its stack, the program buffer containing .data and .rodata sections or the
+/// memory buffers passed into it.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub len: u64,
+    pub writable: bool,
+}
+
+impl MemoryRegion {
+    pub fn read_only(start: *const u8, len: usize) -> Self {
+        MemoryRegion {
+            start: start as u64,
+            len: len as u64,
+            writable: false,
+        }
+    }
+
+    pub fn read_write(start: *const u8, len: usize) -> Self {
+        MemoryRegion {
+            start: start as u64,
+            len: len as u64,
+            writable: true,
+        }
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.len
+    }
+
+    fn allows(&self, start: u64, end: u64, access: MemoryAccess) -> bool {
+        self.start <= start && end <= self.end() && (self.writable || access == MemoryAccess::Read)
+    }
+}
+
+/// Maps small integer handles given out to the program onto host objects
+/// (e.g. SAUL devices), so that the program never sees the raw pointers and
+/// cannot forge them. Handles are the indices into the table offset by one so
+/// that 0 can be used to signal that an object wasn't found.
+#[derive(Clone, Default)]
+pub struct HandleTable {
+    entries: Vec<usize>,
+}
+
+impl HandleTable {
+    /// Maximum number of objects that a single program can have handles to.
+    pub const MAX_HANDLES: usize = 8;
+
+    /// Returns the handle for the object with a given address, adding it to
+    /// the table if it isn't there yet.
+    pub fn insert(&mut self, address: usize) -> Option<u32> {
+        if let Some(i) = self.entries.iter().position(|a| *a == address) {
+            return Some(i as u32 + 1);
+        }
+        if self.entries.len() >= Self::MAX_HANDLES {
+            return None;
+        }
+        self.entries.push(address);
+        Some(self.entries.len() as u32)
+    }
+
+    /// Returns the address of the object corresponding to the handle.
+    pub fn get(&self, handle: u32) -> Option<usize> {
+        let index = (handle as usize).checked_sub(1)?;
+        self.entries.get(index).copied()
+    }
+}
+
+/// Location of the CoAP packet that the program is processing. The helpers
+/// use this copy instead of the context struct passed into the program, as
+/// the program could overwrite the pointers stored in it.
+#[derive(Debug, Clone, Copy)]
+pub struct CoapPacket {
+    pub pkt: usize,
+    pub buf: usize,
+    pub len: usize,
+}
+
+/// Space reserved in a ring buffer map by the program, the record is read
+/// from the program's buffer when the reservation is submitted.
+#[derive(Debug, Clone, Copy)]
+pub struct RingBufferReservation {
+    pub map_id: u32,
+    pub buffer: u64,
+    pub len: usize,
+}
+
+/// Timer created by the program, the program waits for it to expire using
+/// `bpf_timer_wait`.
+#[derive(Debug, Clone, Copy)]
+pub struct Timer {
+    /// Time (in ms of `ZTIMER_MSEC`) at which the timer expires next.
+    pub next_expiry_ms: u32,
+    /// Period of the timer, `None` for one-shot timers.
+    pub period_ms: Option<u32>,
+}
+
+/// Information about the program that is being executed by the VM on a given
+/// thread that the helper functions need to access.
+#[derive(Clone)]
+pub struct HelperContext {
+    /// Configuration that the VM executing the program was constructed with.
+    pub configuration: VMConfiguration,
+    /// Helpers that the program is actually allowed to call, irrespective of
+    /// whether they were specified in the request or in the binary metadata.
+    pub allowed_helpers: Vec<HelperFunctionID>,
+    /// Memory regions that the program can access through the helpers.
+    pub regions: Vec<MemoryRegion>,
+    /// SAUL devices that the program has opened.
+    pub saul_devices: HandleTable,
+    /// The CoAP packet that the program is processing if it has been given one.
+    pub coap_packet: Option<CoapPacket>,
+    /// Ring buffer reservations that haven't been submitted or discarded yet,
+    /// the handles given out to the program are the indices offset by one.
+    pub ring_buffer_reservations: Vec<Option<RingBufferReservation>>,
+    /// Indices of the named mutexes that the program currently holds.
+    pub held_mutexes: Vec<usize>,
+    /// Payload of the response to the last CoAP request sent by the program.
+    pub coap_client_response: Vec<u8>,
+    /// Local ports of the UDP endpoints that the program has opened, the
+    /// handles given out to the program are the indices offset by one.
+    pub udp_endpoints: Vec<u16>,
+    /// Timers that the program has created, the handles given out to the
+    /// program are the indices offset by one.
+    pub timers: Vec<Option<Timer>>,
+}
+
+impl HelperContext {
+    pub fn new(
+        configuration: VMConfiguration,
+        allowed_helpers: Vec<HelperFunctionID>,
+        regions: Vec<MemoryRegion>,
+    ) -> Self {
+        HelperContext {
+            configuration,
+            allowed_helpers,
+            regions,
+            saul_devices: HandleTable::default(),
+            coap_packet: None,
+            ring_buffer_reservations: Vec::new(),
+            held_mutexes: Vec::new(),
+            coap_client_response: Vec::new(),
+            udp_endpoints: Vec::new(),
+            timers: Vec::new(),
+        }
+    }
+
+    pub fn with_coap_packet(mut self, packet: CoapPacket) -> Self {
+        self.coap_packet = Some(packet);
+        self
+    }
+}
+
+static THREAD_TO_HELPER_CONTEXTS: Mutex<BTreeMap<riot_sys::kernel_pid_t, Vec<HelperContext>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Returned by [`enter_context`], the context frame is popped off the stack of
+/// the current thread once the guard goes out of scope (RAII). This ensures
+/// that the frame gets removed regardless of how the execution terminated.
+/// Any ring buffer space that the program reserved but didn't submit and the
+/// named mutexes that it didn't unlock are released at that point.
+pub struct HelperContextGuard {
+    pid: riot_sys::kernel_pid_t,
+}
+
+impl Drop for HelperContextGuard {
+    fn drop(&mut self) {
+        let mut map = THREAD_TO_HELPER_CONTEXTS.lock();
+        let Some(frames) = map.get_mut(&self.pid) else {
+            return;
+        };
+        let frame = frames.pop();
+        if frames.is_empty() {
+            map.remove(&self.pid);
+        }
+        drop(map);
+
+        let Some(frame) = frame else {
+            return;
+        };
+        for reservation in frame.ring_buffer_reservations.into_iter().flatten() {
+            bpf_maps::ring_buffer_discard(reservation.map_id, reservation.len);
+        }
+        for index in frame.held_mutexes {
+            named_mutexes::unlock(index);
+        }
+    }
+}
+
+/// Pushes a new context frame for the program that is about to be executed
+/// on the current thread.
+pub fn enter_context(context: HelperContext) -> HelperContextGuard {
+    let pid = thread::get_pid().into();
+    let mut map = THREAD_TO_HELPER_CONTEXTS.lock();
+    map.entry(pid).or_insert_with(Vec::new).push(context);
+    HelperContextGuard { pid }
+}
+
+/// Returns a copy of the context of the program currently executing on this
+/// thread, or `None` if the current thread isn't executing any program.
+pub fn current_context() -> Option<HelperContext> {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_HELPER_CONTEXTS.lock();
+    map.get(&pid).and_then(|frames| frames.last().cloned())
+}
+
+/// Gives the closure mutable access to the context of the program currently
+/// executing on this thread. Returns `None` if no program is executing.
+pub fn with_current_context<T>(f: impl FnOnce(&mut HelperContext) -> T) -> Option<T> {
+    let pid = thread::get_pid().into();
+    let mut map = THREAD_TO_HELPER_CONTEXTS.lock();
+    map.get_mut(&pid).and_then(|frames| frames.last_mut()).map(f)
+}
+
+/// Returns the number of programs that are currently executing on this thread.
+pub fn call_depth() -> usize {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_HELPER_CONTEXTS.lock();
+    map.get(&pid).map_or(0, |frames| frames.len())
+}
+
+/// Checks that the `len` bytes starting at `ptr` passed into a helper by the
+/// program currently executing on this thread lie within one of its memory
+/// regions that can be accessed in a given way.
+pub fn check_region(ptr: u64, len: u64, access: MemoryAccess) -> Result<(), String> {
+    if len == 0 {
+        return Ok(());
+    }
+    let Some(end) = ptr.checked_add(len) else {
+        Err(format!("Memory region at {:#x} of length {} overflows", ptr, len))?
+    };
+
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_HELPER_CONTEXTS.lock();
+    let Some(context) = map.get(&pid).and_then(|frames| frames.last()) else {
+        Err("No program is executing on the current thread")?
+    };
+
+    if !context
+        .regions
+        .iter()
+        .any(|region| region.allows(ptr, end, access))
+    {
+        Err(format!(
+            "{:?} access to [{:#x}, {:#x}) is outside of the memory regions of the program",
+            access, ptr, end
+        ))?;
+    }
+    Ok(())
+}
+
+/// Reads a null-terminated string passed into a helper by the program
+/// currently executing on this thread. The string needs to be terminated
+/// within the readable memory region it starts in and it can be at most
+/// [`MAX_HELPER_STRING_LENGTH`] bytes long.
+pub fn read_c_string<'a>(ptr: u64) -> Result<&'a CStr, String> {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_HELPER_CONTEXTS.lock();
+    let Some(context) = map.get(&pid).and_then(|frames| frames.last()) else {
+        Err("No program is executing on the current thread")?
+    };
+
+    let Some(region) = context
+        .regions
+        .iter()
+        .find(|region| region.allows(ptr, ptr.saturating_add(1), MemoryAccess::Read))
+    else {
+        Err(format!(
+            "String at {:#x} is outside of the memory regions of the program",
+            ptr
+        ))?
+    };
+
+    let max_len = core::cmp::min(region.end() - ptr, MAX_HELPER_STRING_LENGTH as u64 + 1);
+    let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, max_len as usize) };
+    CStr::from_bytes_until_nul(bytes).map_err(|_| {
+        format!(
+            "String at {:#x} is not null-terminated within {} bytes",
+            ptr, max_len
+        )
+    })
+}
+
+/// Reads at most `max_len` bytes of a string passed into a helper by the
+/// program currently executing on this thread, stopping at the null byte if
+/// there is one. Contrary to [`read_c_string`], the string doesn't need to be
+/// null-terminated if it is at least `max_len` bytes long, but the bytes that
+/// are read need to lie within the readable memory region it starts in. The
+/// returned bytes don't include the null byte.
+pub fn read_bounded_string<'a>(ptr: u64, max_len: usize) -> Result<&'a [u8], String> {
+    if max_len == 0 {
+        return Ok(&[]);
+    }
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_HELPER_CONTEXTS.lock();
+    let Some(context) = map.get(&pid).and_then(|frames| frames.last()) else {
+        Err("No program is executing on the current thread")?
+    };
+
+    let Some(region) = context
+        .regions
+        .iter()
+        .find(|region| region.allows(ptr, ptr.saturating_add(1), MemoryAccess::Read))
+    else {
+        Err(format!(
+            "String at {:#x} is outside of the memory regions of the program",
+            ptr
+        ))?
+    };
+
+    let readable = core::cmp::min(region.end() - ptr, max_len as u64) as usize;
+    let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, readable) };
+    match bytes.iter().position(|b| *b == 0) {
+        Some(len) => Ok(&bytes[..len]),
+        None if readable == max_len => Ok(bytes),
+        None => Err(format!(
+            "String at {:#x} is not null-terminated within its memory region",
+            ptr
+        )),
+    }
+}
@@ -0,0 +1,165 @@
+//! Helper permission profiles are named sets of helpers that a request (or
+//! the metadata of a program binary) can reference instead of listing all
+//! allowed helper IDs one by one. When a profile is referenced, it is expanded
+//! and its helpers are added to the explicitly listed ones before the VM
+//! verifies the program.
+//!
+//! Profiles come from three places, in the order of decreasing precedence:
+//! 1. Overrides uploaded at runtime through the authenticated
+//!    `/helpers/profiles` endpoint. They allow operators to tighten the policy
+//!    without rebuilding the firmware.
+//! 2. Profiles defined at build time using the `HELPER_PROFILES` environment
+//!    variable, formatted in the same way as the runtime overrides, e.g.
+//!    `HELPER_PROFILES="logger=0102;counter=0203"`. They are parsed once when
+//!    the server starts (see [`load_build_profiles`]).
+//! 3. The built-in profiles defined in terms of the helper permission classes.
+
+use core::convert::TryFrom;
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use log::error;
+use micro_bpf_common::{HelperFunctionID, VMExecutionRequest};
+use riot_wrappers::mutex::Mutex;
+
+use super::{
+    helpers::{HelperAccessList, PermissionClass as P},
+    ALL_HELPERS,
+};
+use crate::util::admin_token;
+
+/// Maximum number of profiles that can be overridden at runtime.
+pub const MAX_PROFILE_OVERRIDES: usize = 8;
+
+/// Built-in profiles, each one grants all helpers in the listed classes.
+const BUILTIN_PROFILES: &[(&str, &[P])] = &[
+    ("sensors-ro", &[P::Console, P::Memory, P::Time, P::SensorRead]),
+    ("display", &[P::Console, P::Memory, P::Time, P::Display]),
+    ("coap-responder", &[P::Memory, P::Storage, P::Coap]),
+    ("gpio-rw", &[P::Console, P::Memory, P::Time, P::Gpio]),
+];
+
+static PROFILE_OVERRIDES: Mutex<BTreeMap<String, Vec<HelperFunctionID>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Profiles defined using `HELPER_PROFILES`, filled in by [`load_build_profiles`].
+static BUILD_PROFILES: Mutex<BTreeMap<String, Vec<HelperFunctionID>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Parses the profiles defined at build time. It needs to be called once when
+/// the server starts. Malformed definitions are logged and skipped so that
+/// they don't affect the other profiles.
+pub fn load_build_profiles() {
+    let Some(definitions) = option_env!("HELPER_PROFILES") else {
+        return;
+    };
+    let mut profiles = BUILD_PROFILES.lock();
+    for definition in definitions.split(';').filter(|d| !d.trim().is_empty()) {
+        match parse_profile_definition(definition) {
+            Ok((name, helpers)) => {
+                profiles.insert(name, helpers);
+            }
+            Err(e) => error!("Ignoring helper profile defined at build time: {}", e),
+        }
+    }
+}
+
+/// Returns the helpers granted by the profile with a given name.
+pub fn resolve_profile(name: &str) -> Result<Vec<HelperFunctionID>, String> {
+    if let Some(helpers) = PROFILE_OVERRIDES.lock().get(name) {
+        return Ok(helpers.clone());
+    }
+
+    if let Some(helpers) = BUILD_PROFILES.lock().get(name) {
+        return Ok(helpers.clone());
+    }
+
+    let Some((_, classes)) = BUILTIN_PROFILES.iter().find(|(n, _)| *n == name) else {
+        Err(format!("Unknown helper profile: {}", name))?
+    };
+    Ok(ALL_HELPERS
+        .iter()
+        .filter(|helper| classes.contains(&helper.permission))
+        .map(|helper| helper.id)
+        .collect())
+}
+
+/// Returns the helpers that the request allows the program to call, i.e. the
+/// explicitly listed ones and the ones granted by the referenced profile.
+pub fn request_helpers(request: &VMExecutionRequest) -> Result<Vec<HelperFunctionID>, String> {
+    with_profile(request.allowed_helpers.clone(), request.helper_profile.as_deref())
+}
+
+/// Returns the helpers that the metadata appended to the program binary
+/// allows the program to call. Only supported by the `ExtendedHeader` layout.
+pub fn metadata_helpers(program: &[u8]) -> Result<Vec<HelperFunctionID>, String> {
+    let listed = micro_bpf_elf_utils::extract_allowed_helpers(program)
+        .into_iter()
+        .map(|id| {
+            num::FromPrimitive::from_u8(id).ok_or(format!("Unknown helper function ID: {:#x}", id))
+        })
+        .collect::<Result<Vec<HelperFunctionID>, String>>()?;
+    let profile = micro_bpf_elf_utils::extract_helper_profile(program);
+    with_profile(listed, profile.as_deref())
+}
+
+/// Adds the helpers granted by the profile (if any) to the listed ones.
+pub fn with_profile(
+    mut helpers: Vec<HelperFunctionID>,
+    profile: Option<&str>,
+) -> Result<Vec<HelperFunctionID>, String> {
+    if let Some(profile) = profile {
+        for id in resolve_profile(profile)? {
+            if !helpers.contains(&id) {
+                helpers.push(id);
+            }
+        }
+    }
+    Ok(helpers)
+}
+
+/// Handles a profile override request of the form `<token>;<name>=<helper IDs>`
+/// where the helper IDs are encoded in the same way as in the execution
+/// requests (two hex digits per helper). An empty list of IDs is a valid
+/// override that doesn't grant any helpers. A request of the form
+/// `<token>;<name>` removes the override so that the profile reverts to its
+/// build-time definition.
+pub fn override_profile(request: &str) -> Result<(), String> {
+    let definition = admin_token::authenticate(request)?;
+
+    if !definition.contains('=') {
+        PROFILE_OVERRIDES.lock().remove(definition.trim());
+        return Ok(());
+    }
+
+    let (name, helpers) = parse_profile_definition(definition)?;
+    let mut overrides = PROFILE_OVERRIDES.lock();
+    if !overrides.contains_key(&name) && overrides.len() >= MAX_PROFILE_OVERRIDES {
+        Err(format!(
+            "Cannot override profile {}, the limit of {} overrides has been reached",
+            name, MAX_PROFILE_OVERRIDES
+        ))?;
+    }
+    overrides.insert(name, helpers);
+    Ok(())
+}
+
+/// Parses a profile definition of the form `<name>=<helper IDs>`.
+fn parse_profile_definition(definition: &str) -> Result<(String, Vec<HelperFunctionID>), String> {
+    let Some((name, ids)) = definition.trim().split_once('=') else {
+        Err(format!("Malformed helper profile definition: {}", definition))?
+    };
+    if name.is_empty() {
+        Err("Helper profile name cannot be empty")?;
+    }
+    let helpers = HelperAccessList::try_from(ids.to_string())?
+        .0
+        .into_iter()
+        .map(|helper| helper.id)
+        .collect();
+    Ok((name.to_string(), helpers))
+}
@@ -1,9 +1,93 @@
-use core::num::ParseIntError;
+use core::convert::TryFrom;
 
-use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
 
-use super::ALL_HELPERS;
-use micro_bpf_common::HelperFunctionID;
+use super::{helper_profiles::metadata_helpers, ALL_HELPERS};
+use micro_bpf_common::{BinaryFileLayout, HelperAccessListSource, HelperFunctionID};
+
+/// Kind of the value that a helper expects in a given argument register. It
+/// is exposed through the `/helpers` endpoint so that the tools can check
+/// programs against the helpers that a particular firmware build offers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgKind {
+    /// A plain integer value.
+    Scalar,
+    /// Pointer to memory that the helper reads from. Unless the helper expects
+    /// a null-terminated string or a fixed-size struct, the length of the
+    /// memory is passed in the following argument.
+    InPtr,
+    /// Pointer to memory that the helper writes into. The length follows the
+    /// same convention as for [`ArgKind::InPtr`].
+    OutPtr,
+    /// An opaque handle previously given out by another helper.
+    Handle,
+}
+
+impl ArgKind {
+    /// Single-character code used in the compact helper descriptions.
+    pub fn code(&self) -> char {
+        match self {
+            ArgKind::Scalar => 's',
+            ArgKind::InPtr => 'i',
+            ArgKind::OutPtr => 'o',
+            ArgKind::Handle => 'h',
+        }
+    }
+}
+
+/// Groups helpers according to the kind of resource that they give the program
+/// access to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionClass {
+    /// Printing to the RIOT console.
+    Console,
+    /// Operations on the memory of the program, e.g. copying and formatting.
+    Memory,
+    /// The local and global key-value storage.
+    Storage,
+    /// Reading timers and sleeping.
+    Time,
+    /// Reading sensors and input devices.
+    SensorRead,
+    /// Writing to actuators through SAUL.
+    ActuatorWrite,
+    /// Inspecting the CoAP request, building the response and sending
+    /// requests to other devices.
+    Coap,
+    /// Reading and writing GPIO pins.
+    Gpio,
+    /// Displays attached to the device.
+    Display,
+    /// Communication with other programs.
+    Ipc,
+    /// Raw network access, e.g. UDP sockets.
+    Network,
+    /// Cryptographic hashes. The random bytes helper isn't part of it as it
+    /// isn't backed by a cryptographically secure generator.
+    Crypto,
+    /// Information about the execution of the program itself, e.g. its slot.
+    Introspection,
+}
+
+impl PermissionClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PermissionClass::Console => "console",
+            PermissionClass::Memory => "memory",
+            PermissionClass::Storage => "storage",
+            PermissionClass::Time => "time",
+            PermissionClass::SensorRead => "sensor-read",
+            PermissionClass::ActuatorWrite => "actuator-write",
+            PermissionClass::Coap => "coap",
+            PermissionClass::Gpio => "gpio",
+            PermissionClass::Display => "display",
+            PermissionClass::Ipc => "ipc",
+            PermissionClass::Network => "network",
+            PermissionClass::Crypto => "crypto",
+            PermissionClass::Introspection => "introspection",
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct HelperFunction {
@@ -11,6 +95,13 @@ pub struct HelperFunction {
     /// It should be consistent with the one defined in the C header file with
     /// all the helpers that is used to compile the eBPF programs
     pub id: HelperFunctionID,
+    /// Name of the helper as declared in the C header file.
+    pub name: &'static str,
+    /// Kinds of the arguments that the helper accepts, their number is the
+    /// arity of the helper.
+    pub args: &'static [ArgKind],
+    /// Kind of resource that the helper gives the program access to.
+    pub permission: PermissionClass,
     /// The actual implementation of the helper function, it always accepts 5
     /// arguments and the eBPF calling convention works by putting all arguments
     /// to the function into registers r1 - r5. One thing is that the helper functions
@@ -20,50 +111,130 @@ pub struct HelperFunction {
 }
 
 impl HelperFunction {
-    pub const fn new(id: HelperFunctionID, function: fn(u64, u64, u64, u64, u64) -> u64) -> Self {
-        HelperFunction { id, function }
+    pub const fn new(
+        id: HelperFunctionID,
+        name: &'static str,
+        args: &'static [ArgKind],
+        permission: PermissionClass,
+        function: fn(u64, u64, u64, u64, u64) -> u64,
+    ) -> Self {
+        HelperFunction {
+            id,
+            name,
+            args,
+            permission,
+            function,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Compact description of the helper in the form
+    /// `<id in hex>,<name>,<arity>,<argument kind codes>,<permission class>`,
+    /// e.g. `02,bpf_store_global,2,ss,storage`.
+    pub fn describe(&self) -> String {
+        let arg_codes: String = self.args.iter().map(|arg| arg.code()).collect();
+        format!(
+            "{:02x},{},{},{},{}",
+            self.id as u8,
+            self.name,
+            self.arity(),
+            arg_codes,
+            self.permission.name()
+        )
     }
 }
 
+/// Returns the helper with a given ID if it is offered by this firmware build.
+pub fn lookup_helper(id: HelperFunctionID) -> Option<&'static HelperFunction> {
+    ALL_HELPERS.iter().find(|h| h.id == id)
+}
+
 pub struct HelperAccessList(pub Vec<HelperFunction>);
 
-impl From<String> for HelperAccessList {
-    fn from(value: String) -> Self {
-        let allowed_helpers_ids = (0..value.len())
-            .step_by(2)
-            .map(|i| u8::from_str_radix(&value[i..i + 2], 16))
-            .collect::<Result<Vec<u8>, ParseIntError>>()
-            .map_err(|e| format!("Unable to parse: {}", e))
-            .unwrap();
+impl TryFrom<String> for HelperAccessList {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.len() % 2 != 0 {
+            Err(format!("Invalid helper access list: {}", value))?;
+        }
+        // The string is split into bytes rather than characters so that
+        // non-ASCII input is rejected instead of being sliced in the middle
+        // of a character.
+        let allowed_helpers_ids = value
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                core::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or(format!("Unable to parse helper access list: {}", value))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
 
-        HelperAccessList::from(allowed_helpers_ids)
+        HelperAccessList::try_from(allowed_helpers_ids)
     }
 }
 
-impl From<Vec<u8>> for HelperAccessList {
-    fn from(value: Vec<u8>) -> Self {
-        let allowed_helpers: Vec<HelperFunctionID> = value
+impl TryFrom<Vec<u8>> for HelperAccessList {
+    type Error = String;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        let allowed_helpers = value
             .into_iter()
-            .filter_map(|id| num::FromPrimitive::from_u8(id))
-            .collect();
-        HelperAccessList::from(allowed_helpers)
+            .map(|id| {
+                num::FromPrimitive::from_u8(id)
+                    .ok_or(format!("Unknown helper function ID: {:#x}", id))
+            })
+            .collect::<Result<Vec<HelperFunctionID>, String>>()?;
+        HelperAccessList::try_from(allowed_helpers)
     }
 }
 
 /// We need to implement this so that it is possible to map from a list of
-/// helper function IDs to the actual list of function pointers.
-impl From<Vec<HelperFunctionID>> for HelperAccessList {
-    fn from(value: Vec<HelperFunctionID>) -> Self {
-        let helper_map = ALL_HELPERS
-            .iter()
-            .map(|h| (h.id, h.clone()))
-            .collect::<BTreeMap<HelperFunctionID, HelperFunction>>();
+/// helper function IDs to the actual list of function pointers. It fails if
+/// any of the helpers isn't available in this firmware build.
+impl TryFrom<Vec<HelperFunctionID>> for HelperAccessList {
+    type Error = String;
 
+    fn try_from(value: Vec<HelperFunctionID>) -> Result<Self, Self::Error> {
         let helpers = value
             .iter()
-            .map(|v| helper_map.get(v).unwrap().clone())
-            .collect::<Vec<HelperFunction>>();
-        HelperAccessList(helpers)
+            .map(|id| {
+                lookup_helper(*id).cloned().ok_or(format!(
+                    "Helper function {:?} ({:#x}) is not available on this device",
+                    id, *id as u8
+                ))
+            })
+            .collect::<Result<Vec<HelperFunction>, String>>()?;
+        Ok(HelperAccessList(helpers))
+    }
+}
+
+/// Resolves the helper functions that a program is allowed to call. Depending
+/// on the configured source of the helper access list, those are either the
+/// helpers sent in the execution request or the ones listed in the metadata
+/// appended to the program binary. The latter is only supported by the
+/// `ExtendedHeader` binary layout.
+pub fn resolve_helper_access_list(
+    list_source: HelperAccessListSource,
+    layout: BinaryFileLayout,
+    request_helpers: &[HelperFunctionID],
+    program: &[u8],
+) -> Result<HelperAccessList, String> {
+    match list_source {
+        HelperAccessListSource::ExecuteRequest => {
+            HelperAccessList::try_from(request_helpers.to_vec())
+        }
+        HelperAccessListSource::BinaryMetadata => {
+            if layout != BinaryFileLayout::ExtendedHeader {
+                Err("Tried to extract allowed helper function indices from an incompatible binary file")?
+            }
+            HelperAccessList::try_from(metadata_helpers(program)?)
+        }
     }
 }
 
@@ -112,7 +283,7 @@ impl AcceptingHelpers for rbpf::EbpfVmMbuff<'_> {
 /// functions and are currently unused.
 #[allow(dead_code)]
 pub fn register_all(vm: &mut impl AcceptingHelpers) {
-    for helper in ALL_HELPERS {
+    for helper in ALL_HELPERS.iter().copied() {
         vm.register_helper(helper);
     }
 }
@@ -0,0 +1,356 @@
+//! Apart from the helpers that a program is allowed to call, an execution
+//! request can grant the program access to resources shared with other
+//! programs (e.g. message queues) or restrict which devices it can use. This
+//! module stores those grants for each thread that is currently executing a
+//! job so that the helper functions can check them.
+//!
+//! Contrary to the [`super::helper_context`], the job context is set up once
+//! by the thread that accepts the execution request and is shared by all
+//! programs that get called while the job is executing.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use micro_bpf_common::{
+    BinaryFileLayout, CoapDestinationGrant, GpioDirection, GpioPinGrant, HelperAccessListSource,
+    MapDefinition, MessageQueueGrant, UdpPortGrant, VMExecutionRequest,
+};
+use riot_wrappers::{mutex::Mutex, thread};
+
+use crate::{
+    infra::{bpf_maps, gpio_ownership, lcd_display, message_queues, suit_storage, udp_sockets},
+    vm::vm_manager,
+};
+
+/// How the program executed by the job was started. The values are exposed
+/// to the programs through the `bpf_get_execution_model` helper.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ExecutionModel {
+    /// Executed directly in the CoAP request handler, without access to the
+    /// request packet.
+    #[default]
+    ShortLived = 0,
+    /// Executed by one of the VM worker threads.
+    LongRunning = 1,
+    /// Executed in the CoAP request handler on the request packet.
+    CoapPacket = 2,
+}
+
+/// A GPIO pin grant with the port or the pin set to this value covers all ports
+/// or all pins of the port respectively, e.g. `(GPIO_WILDCARD, GPIO_WILDCARD,
+/// Output)` grants output access to all pins.
+pub const GPIO_WILDCARD: u32 = u32::MAX;
+
+/// Resources that the execution request has granted to the job.
+#[derive(Clone, Default)]
+pub struct JobContext {
+    pub message_queues: Vec<MessageQueueGrant>,
+    /// SAUL device classes that the job can open, empty means all classes.
+    pub saul_device_classes: Vec<u8>,
+    /// Positions in the SAUL registry of the devices that the job can open,
+    /// empty means all devices.
+    pub saul_device_indices: Vec<u32>,
+    /// GPIO pins that the job can access and in which direction.
+    pub gpio_pins: Vec<GpioPinGrant>,
+    /// IDs of the maps that the job has declared.
+    pub maps: Vec<u32>,
+    /// CoAP peers that the job can send requests to, the programs refer to
+    /// them using their index in this list.
+    pub coap_destinations: Vec<CoapDestinationGrant>,
+    /// Local UDP ports that the job can use together with the remote ports
+    /// that it can exchange datagrams with.
+    pub udp_ports: Vec<UdpPortGrant>,
+    /// Time (in ms of `ZTIMER_MSEC`) by which the job needs to finish, `None`
+    /// if the request didn't specify a time budget.
+    pub deadline: Option<u32>,
+    /// Identifies the job in the logs, assigned sequentially when the job
+    /// is entered.
+    pub job_id: u32,
+    /// SUIT slot containing the program executed by the job.
+    pub suit_slot: usize,
+    /// Set when a stop request for the job has been received. Programs can't
+    /// be preempted, they learn about it at the next cancellation point, i.e.
+    /// a blocking helper call, which returns early with an error.
+    pub stop_requested: bool,
+    /// How the job was started.
+    pub execution_model: ExecutionModel,
+    /// Index of the VM worker thread executing the job, `None` if the job
+    /// doesn't execute on a worker.
+    pub worker: Option<usize>,
+}
+
+static NEXT_JOB_ID: Mutex<u32> = Mutex::new(1);
+
+static THREAD_TO_JOB_CONTEXT: Mutex<BTreeMap<riot_sys::kernel_pid_t, JobContext>> =
+    Mutex::new(BTreeMap::new());
+
+/// Returned by [`enter_job`], releases all resources held by the job once it
+/// goes out of scope.
+pub struct JobContextGuard {
+    pid: riot_sys::kernel_pid_t,
+    job_id: u32,
+}
+
+impl Drop for JobContextGuard {
+    fn drop(&mut self) {
+        let context = THREAD_TO_JOB_CONTEXT.lock().remove(&self.pid);
+        if let Some(context) = context {
+            for grant in context.message_queues {
+                message_queues::close_queue(grant.name);
+            }
+        }
+        gpio_ownership::release_output_pins(self.job_id);
+        udp_sockets::release_sockets(self.job_id);
+        lcd_display::release_handles(self.job_id);
+    }
+}
+
+/// Sets up the context of the job specified by the request on the current
+/// thread. All message queues declared in the request are created (or attached
+/// to if they already exist) and the GPIO output pins are claimed at this point.
+/// The queue grants need to be allowed by the access configured by the
+/// operator (see [`message_queues::check_access`]).
+/// The maps declared in the request and in the `.maps` section of the program
+/// are created unless they already exist. It fails if any of the output pins
+/// is owned by another running job.
+pub fn enter_job(
+    request: &VMExecutionRequest,
+    execution_model: ExecutionModel,
+) -> Result<JobContextGuard, String> {
+    let pid = thread::get_pid().into();
+    let job_id = next_job_id();
+
+    // Maps outlive the jobs (they are released when the program is unloaded),
+    // so they don't need to be cleaned up on failure.
+    let maps = requested_maps(request);
+    for definition in &maps {
+        bpf_maps::create_map(definition, request.configuration.suit_slot as usize)?;
+    }
+
+    let gpio_pins = requested_gpio_pins(request)?;
+    // Wildcard grants don't claim any pins, the pins owned by other jobs
+    // still can't be used by the job.
+    let output_pins: Vec<(u32, u32)> = gpio_pins
+        .iter()
+        .filter(|grant| grant.direction == GpioDirection::Output)
+        .filter(|grant| grant.port != GPIO_WILDCARD && grant.pin != GPIO_WILDCARD)
+        .map(|grant| (grant.port, grant.pin))
+        .collect();
+    gpio_ownership::claim_output_pins(&output_pins, job_id)?;
+
+    for (i, grant) in request.message_queues.iter().enumerate() {
+        let opened = message_queues::check_access(
+            grant.name,
+            request.configuration.suit_slot as usize,
+            grant.can_send,
+            grant.can_receive,
+        )
+        .and_then(|()| message_queues::open_queue(grant.name, grant.capacity as usize));
+        if let Err(e) = opened {
+            for opened in &request.message_queues[..i] {
+                message_queues::close_queue(opened.name);
+            }
+            gpio_ownership::release_output_pins(job_id);
+            return Err(e);
+        }
+    }
+
+    let context = JobContext {
+        message_queues: request.message_queues.clone(),
+        saul_device_classes: request.saul_device_classes.clone(),
+        saul_device_indices: request.saul_device_indices.clone(),
+        gpio_pins,
+        maps: maps.iter().map(|definition| definition.id).collect(),
+        coap_destinations: request.coap_destinations.clone(),
+        udp_ports: request.udp_ports.clone(),
+        deadline: (request.time_budget_ms > 0)
+            .then(|| now_ms().wrapping_add(request.time_budget_ms)),
+        job_id,
+        suit_slot: request.configuration.suit_slot as usize,
+        stop_requested: false,
+        execution_model,
+        worker: vm_manager::worker_index(pid),
+    };
+    THREAD_TO_JOB_CONTEXT.lock().insert(pid, context);
+    Ok(JobContextGuard { pid, job_id })
+}
+
+fn next_job_id() -> u32 {
+    let mut next = NEXT_JOB_ID.lock();
+    let id = *next;
+    *next = next.wrapping_add(1);
+    id
+}
+
+/// The GPIO pins are declared in the same place as the list of allowed
+/// helpers, i.e. either in the request or in the metadata of the program binary.
+fn requested_gpio_pins(request: &VMExecutionRequest) -> Result<Vec<GpioPinGrant>, String> {
+    let config = request.configuration;
+    match config.helper_access_list_source {
+        HelperAccessListSource::ExecuteRequest => Ok(request.gpio_pins.clone()),
+        HelperAccessListSource::BinaryMetadata => {
+            if config.binary_layout != BinaryFileLayout::ExtendedHeader {
+                Err("Tried to extract allowed GPIO pins from an incompatible binary file")?;
+            }
+            let program = suit_storage::load_program_static(config.suit_slot);
+            Ok(micro_bpf_elf_utils::extract_gpio_pins(program))
+        }
+    }
+}
+
+/// Maps can be declared both in the request and in the `.maps` section of
+/// the program binary.
+fn requested_maps(request: &VMExecutionRequest) -> Vec<MapDefinition> {
+    let program = suit_storage::load_program_static(request.configuration.suit_slot);
+    let mut maps = request.maps.clone();
+    for definition in micro_bpf_elf_utils::extract_map_definitions(program) {
+        if !maps.iter().any(|m| m.id == definition.id) {
+            maps.push(definition);
+        }
+    }
+    maps
+}
+
+/// Returns the access that the job executing on the current thread has to
+/// the message queue with a given name, or `None` if it wasn't granted any.
+pub fn message_queue_grant(name: u32) -> Option<MessageQueueGrant> {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    map.get(&pid)?
+        .message_queues
+        .iter()
+        .find(|grant| grant.name == name)
+        .cloned()
+}
+
+/// Checks whether the job executing on the current thread can open the SAUL
+/// device of a given class at a given position in the registry. Code that
+/// isn't executing a job can't open any.
+pub fn saul_device_allowed(class: u8, index: u32) -> bool {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    let Some(context) = map.get(&pid) else {
+        return false;
+    };
+    (context.saul_device_classes.is_empty() || context.saul_device_classes.contains(&class))
+        && (context.saul_device_indices.is_empty() || context.saul_device_indices.contains(&index))
+}
+
+/// Checks whether the job executing on the current thread can access the GPIO
+/// pin in a given direction. Jobs can only access the pins that they declared,
+/// unrestricted access needs to be requested explicitly using a wildcard grant
+/// (see [`GPIO_WILDCARD`]). Code that isn't executing a job can't access any.
+pub fn gpio_pin_allowed(port: u32, pin: u32, direction: GpioDirection) -> bool {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    let Some(context) = map.get(&pid) else {
+        return false;
+    };
+    context.gpio_pins.iter().any(|grant| {
+        (grant.port == port || grant.port == GPIO_WILDCARD)
+            && (grant.pin == pin || grant.pin == GPIO_WILDCARD)
+            && grant.direction == direction
+    })
+}
+
+/// Checks whether the GPIO pin is an output pin owned by a running job other
+/// than the one executing on the current thread, such pins can't be
+/// reconfigured by the current job.
+pub fn gpio_pin_owned_by_other_job(port: u32, pin: u32) -> bool {
+    gpio_ownership::owned_by_other_job(port, pin, job_id())
+}
+
+/// Checks whether the job executing on the current thread has declared the map.
+pub fn map_allowed(id: u32) -> bool {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    map.get(&pid).map_or(false, |context| context.maps.contains(&id))
+}
+
+/// Returns the CoAP peer at a given position in the list of destinations
+/// that the job executing on the current thread was granted.
+pub fn coap_destination(index: usize) -> Option<CoapDestinationGrant> {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    map.get(&pid)?.coap_destinations.get(index).cloned()
+}
+
+/// Returns the remote ports that the job executing on the current thread can
+/// exchange datagrams with using a given local UDP port, or `None` if it wasn't
+/// granted the local port.
+pub fn udp_port_grant(local_port: u16) -> Option<UdpPortGrant> {
+    let pid = thread::get_pid().into();
+    let map = THREAD_TO_JOB_CONTEXT.lock();
+    map.get(&pid)?
+        .udp_ports
+        .iter()
+        .find(|grant| grant.local_port == local_port)
+        .cloned()
+}
+
+/// Returns the ID of the job executing on the current thread.
+pub fn job_id() -> Option<u32> {
+    let pid = thread::get_pid().into();
+    THREAD_TO_JOB_CONTEXT.lock().get(&pid).map(|context| context.job_id)
+}
+
+/// Returns the execution model of the job executing on the current thread.
+pub fn execution_model() -> Option<ExecutionModel> {
+    let pid = thread::get_pid().into();
+    THREAD_TO_JOB_CONTEXT.lock().get(&pid).map(|context| context.execution_model)
+}
+
+/// Checks whether the job executing on the current thread can block in a
+/// helper. Only long-running jobs have a worker thread of their own, the other
+/// ones execute on the thread of the CoAP server, which would be stalled for
+/// the whole duration of the wait.
+pub fn can_block() -> bool {
+    execution_model() == Some(ExecutionModel::LongRunning)
+}
+
+/// Returns the index of the VM worker thread executing the job on the current
+/// thread, `None` if the job doesn't execute on a worker.
+pub fn worker() -> Option<usize> {
+    let pid = thread::get_pid().into();
+    THREAD_TO_JOB_CONTEXT.lock().get(&pid)?.worker
+}
+
+/// Returns the number of milliseconds left until the deadline of the job
+/// executing on the current thread, `None` if the job doesn't have a time
+/// budget. Blocking helpers must not wait for longer than that.
+pub fn remaining_budget_ms() -> Option<u32> {
+    let pid = thread::get_pid().into();
+    let deadline = THREAD_TO_JOB_CONTEXT.lock().get(&pid)?.deadline?;
+    // The difference is interpreted as signed so that the clock wrapping
+    // around is handled correctly.
+    let remaining = deadline.wrapping_sub(now_ms()) as i32;
+    Some(core::cmp::max(remaining, 0) as u32)
+}
+
+/// Requests all jobs executing the program loaded into the SUIT slot to stop.
+/// Returns the number of jobs that were asked to stop.
+pub fn request_stop(suit_slot: usize) -> usize {
+    let mut map = THREAD_TO_JOB_CONTEXT.lock();
+    let mut stopped = 0;
+    for context in map.values_mut().filter(|context| context.suit_slot == suit_slot) {
+        context.stop_requested = true;
+        stopped += 1;
+    }
+    stopped
+}
+
+/// Checks whether the job executing on the current thread should stop, either
+/// because a stop request has been received or because its time budget is
+/// exhausted. Blocking helpers call it while waiting and return early if it
+/// does.
+pub fn should_stop() -> bool {
+    let pid = thread::get_pid().into();
+    let stop_requested = THREAD_TO_JOB_CONTEXT
+        .lock()
+        .get(&pid)
+        .map_or(false, |context| context.stop_requested);
+    stop_requested || remaining_budget_ms() == Some(0)
+}
+
+fn now_ms() -> u32 {
+    let clock = unsafe { riot_sys::ZTIMER_MSEC as *mut riot_sys::inline::ztimer_clock_t };
+    unsafe { riot_sys::inline::ztimer_now(clock) }
+}
@@ -1,4 +1,7 @@
 pub mod riot_middleware;
 pub mod helpers;
+pub mod helper_context;
+pub mod helper_profiles;
+pub mod job_context;
 
 pub use riot_middleware::*;
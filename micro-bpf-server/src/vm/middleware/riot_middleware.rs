@@ -6,77 +6,652 @@
 // `u64` as a return value. Hence some helpers have unused arguments, or return a 0 value in all
 // cases, in order to respect this convention.
 
-use core::ffi::{c_char, CStr};
+use alloc::{format, string::String};
+use core::convert::{TryFrom, TryInto};
+use core::ffi::{c_void, CStr};
+use core::slice::{from_raw_parts, from_raw_parts_mut};
 
-use log::debug;
+use log::{debug, error};
 use riot_wrappers::gpio;
+use riot_wrappers::thread;
+use riot_wrappers::ztimer;
 use riot_wrappers::stdio::println;
 
 use crate::{
-    infra::local_storage::{self},
-    peripherals::{hd44780_lcd::{hd44780_t, HD44780LCD}, keypad_shield_buttons::KeypadShieldButtons},
+    infra::{
+        bpf_maps, checksums, coap_client, lcd_display, local_storage::{self}, message_queues,
+        named_mutexes, persistent_storage, udp_sockets,
+    },
+    vm::call_program_in_slot,
+    peripherals::keypad_shield_buttons::KeypadShieldButtons,
 };
 
-use super::helpers::HelperFunction;
-use micro_bpf_common::HelperFunctionID as ID;
+use super::{
+    helper_context::{self, MemoryAccess},
+    helpers::{
+        ArgKind::{Handle, InPtr, OutPtr, Scalar},
+        HelperFunction, PermissionClass as P,
+    },
+    job_context,
+};
+use micro_bpf_common::{GpioDirection, HelperFunctionID as ID, MapDefinition, UdpPortGrant};
+use micro_bpf_server_core::{fixed_point, formatting};
 
-// Alias the type to make the table below more concise
+// Alias the types to make the table below more concise
 type HF = HelperFunction;
 
-/// List of all helpers together with their corresponding numbers (used
-/// directly as function pointers in the compiled eBPF bytecode).
-pub const ALL_HELPERS: [HelperFunction; 30] = [
-    HF::new(ID::BPF_DEBUG_PRINT_IDX, bpf_print_debug),
-    HF::new(ID::BPF_PRINTF_IDX, bpf_printf),
-    HF::new(ID::BPF_STORE_LOCAL_IDX, bpf_store_local),
-    HF::new(ID::BPF_STORE_GLOBAL_IDX, bpf_store_global),
-    HF::new(ID::BPF_FETCH_LOCAL_IDX, bpf_fetch_local),
-    HF::new(ID::BPF_FETCH_GLOBAL_IDX, bpf_fetch_global),
-    HF::new(ID::BPF_MEMCPY_IDX, bpf_memcpy),
-    HF::new(ID::BPF_NOW_MS_IDX, bpf_now_ms),
-    HF::new(ID::BPF_ZTIMER_NOW_IDX, bpf_ztimer_now),
-    HF::new(ID::BPF_PERIODIC_WAKEUP_IDX, bpf_periodic_wakeup),
-    HF::new(ID::BPF_SAUL_REG_FIND_NTH_IDX, bpf_saul_reg_find_nth),
-    HF::new(ID::BPF_SAUL_REG_FIND_TYPE_IDX, bpf_saul_reg_find_type),
-    HF::new(ID::BPF_SAUL_REG_WRITE_IDX, bpf_saul_reg_write),
-    HF::new(ID::BPF_SAUL_REG_READ_IDX, bpf_saul_reg_read),
-    HF::new(ID::BPF_SAUL_REG_READ_TEMP, bpf_saul_read_temp),
-    HF::new(ID::BPF_GCOAP_RESP_INIT_IDX, bpf_gcoap_resp_init),
-    HF::new(ID::BPF_COAP_OPT_FINISH_IDX, bpf_coap_opt_finish),
-    HF::new(ID::BPF_COAP_ADD_FORMAT_IDX, bpf_coap_add_format),
-    HF::new(ID::BPF_COAP_GET_PDU_IDX, bpf_coap_get_pdu),
-    HF::new(ID::BPF_STRLEN_IDX, bpf_strlen),
-    HF::new(ID::BPF_FMT_S16_DFP_IDX, bpf_fmt_s16_dfp),
-    HF::new(ID::BPF_FMT_U32_DEC_IDX, bpf_fmt_u32_dec),
-    HF::new(ID::BPF_GPIO_READ_INPUT, bpf_gpio_read_input),
-    HF::new(ID::BPF_GPIO_READ_RAW, bpf_gpio_read_raw),
-    HF::new(ID::BPF_GPIO_WRITE, bpf_gpio_write),
-    HF::new(ID::BPF_HD44780_INIT, bpf_hd44780_init),
-    HF::new(ID::BPF_HD44780_CLEAR, bpf_hd44780_clear),
-    HF::new(ID::BPF_HD44780_PRINT, bpf_hd44780_print),
-    HF::new(ID::BPF_HD44780_SET_CURSOR, bpf_hd44780_set_cursor),
-    HF::new(ID::BPF_KEYPAD_GET_INPUT, bpf_keypad_get_input),
+/// Registry of all helpers offered by this firmware build together with their
+/// corresponding numbers (used directly as function pointers in the compiled
+/// eBPF bytecode) and the metadata exposed through the `/helpers` endpoint.
+/// New helpers only need to be added to this table.
+pub const ALL_HELPERS: &[HelperFunction] = &[
+    HF::new(
+        ID::BPF_LOG_IDX,
+        "bpf_log",
+        &[Scalar, InPtr, Scalar, Scalar, Scalar],
+        P::Console,
+        bpf_log,
+    ),
+    HF::new(ID::BPF_DEBUG_PRINT_IDX, "bpf_print_debug", &[Scalar], P::Console, bpf_print_debug),
+    HF::new(
+        ID::BPF_PRINTF_IDX,
+        "bpf_printf",
+        &[InPtr, Scalar, Scalar, Scalar, Scalar],
+        P::Console,
+        bpf_printf,
+    ),
+    HF::new(
+        ID::BPF_STORE_LOCAL_IDX,
+        "bpf_store_local",
+        &[Scalar, Scalar],
+        P::Storage,
+        bpf_store_local,
+    ),
+    HF::new(
+        ID::BPF_STORE_GLOBAL_IDX,
+        "bpf_store_global",
+        &[Scalar, Scalar],
+        P::Storage,
+        bpf_store_global,
+    ),
+    HF::new(
+        ID::BPF_FETCH_LOCAL_IDX,
+        "bpf_fetch_local",
+        &[Scalar, OutPtr],
+        P::Storage,
+        bpf_fetch_local,
+    ),
+    HF::new(
+        ID::BPF_FETCH_GLOBAL_IDX,
+        "bpf_fetch_global",
+        &[Scalar, OutPtr],
+        P::Storage,
+        bpf_fetch_global,
+    ),
+    HF::new(
+        ID::BPF_STORE_GLOBAL_CAS_IDX,
+        "bpf_store_global_cas",
+        &[Scalar, Scalar, Scalar],
+        P::Storage,
+        bpf_store_global_cas,
+    ),
+    HF::new(
+        ID::BPF_STORE_GLOBAL_FETCH_ADD_IDX,
+        "bpf_store_global_fetch_add",
+        &[Scalar, Scalar, OutPtr],
+        P::Storage,
+        bpf_store_global_fetch_add,
+    ),
+    HF::new(
+        ID::BPF_PERSIST_STORE_IDX,
+        "bpf_persist_store",
+        &[Scalar, InPtr, Scalar],
+        P::Storage,
+        bpf_persist_store,
+    ),
+    HF::new(
+        ID::BPF_PERSIST_FETCH_IDX,
+        "bpf_persist_fetch",
+        &[Scalar, OutPtr, Scalar],
+        P::Storage,
+        bpf_persist_fetch,
+    ),
+    HF::new(
+        ID::BPF_PERSIST_DELETE_IDX,
+        "bpf_persist_delete",
+        &[Scalar],
+        P::Storage,
+        bpf_persist_delete,
+    ),
+    HF::new(ID::BPF_MUTEX_LOCK_IDX, "bpf_mutex_lock", &[InPtr, Scalar], P::Ipc, bpf_mutex_lock),
+    HF::new(ID::BPF_MUTEX_UNLOCK_IDX, "bpf_mutex_unlock", &[InPtr], P::Ipc, bpf_mutex_unlock),
+    HF::new(ID::BPF_MEMCPY_IDX, "bpf_memcpy", &[OutPtr, InPtr, Scalar], P::Memory, bpf_memcpy),
+    HF::new(ID::BPF_MEMSET_IDX, "bpf_memset", &[OutPtr, Scalar, Scalar], P::Memory, bpf_memset),
+    HF::new(ID::BPF_MEMCMP_IDX, "bpf_memcmp", &[InPtr, InPtr, Scalar], P::Memory, bpf_memcmp),
+    HF::new(
+        ID::BPF_STRNCPY_IDX,
+        "bpf_strncpy",
+        &[OutPtr, InPtr, Scalar],
+        P::Memory,
+        bpf_strncpy,
+    ),
+    HF::new(
+        ID::BPF_STRNCMP_IDX,
+        "bpf_strncmp",
+        &[InPtr, InPtr, Scalar],
+        P::Memory,
+        bpf_strncmp,
+    ),
+    HF::new(ID::BPF_NOW_MS_IDX, "bpf_now_ms", &[], P::Time, bpf_now_ms),
+    HF::new(ID::BPF_ZTIMER_NOW_IDX, "bpf_ztimer_now", &[], P::Time, bpf_ztimer_now),
+    HF::new(
+        ID::BPF_PERIODIC_WAKEUP_IDX,
+        "bpf_ztimer_periodic_wakeup",
+        &[OutPtr, Scalar],
+        P::Time,
+        bpf_periodic_wakeup,
+    ),
+    HF::new(ID::BPF_SLEEP_MS_IDX, "bpf_sleep_ms", &[Scalar], P::Time, bpf_sleep_ms),
+    HF::new(ID::BPF_SLEEP_US_IDX, "bpf_sleep_us", &[Scalar], P::Time, bpf_sleep_us),
+    HF::new(
+        ID::BPF_TIMER_CREATE_IDX,
+        "bpf_timer_create",
+        &[Scalar, Scalar],
+        P::Time,
+        bpf_timer_create,
+    ),
+    HF::new(ID::BPF_TIMER_WAIT_IDX, "bpf_timer_wait", &[Handle], P::Time, bpf_timer_wait),
+    HF::new(ID::BPF_TIMER_DELETE_IDX, "bpf_timer_delete", &[Handle], P::Time, bpf_timer_delete),
+    HF::new(
+        ID::BPF_SAUL_REG_FIND_NTH_IDX,
+        "bpf_saul_reg_find_nth",
+        &[Scalar],
+        P::SensorRead,
+        bpf_saul_reg_find_nth,
+    ),
+    HF::new(
+        ID::BPF_SAUL_REG_FIND_TYPE_IDX,
+        "bpf_saul_reg_find_type",
+        &[Scalar],
+        P::SensorRead,
+        bpf_saul_reg_find_type,
+    ),
+    HF::new(
+        ID::BPF_SAUL_REG_WRITE_IDX,
+        "bpf_saul_reg_write",
+        &[Handle, InPtr],
+        P::ActuatorWrite,
+        bpf_saul_reg_write,
+    ),
+    HF::new(
+        ID::BPF_SAUL_REG_READ_IDX,
+        "bpf_saul_reg_read",
+        &[Handle, OutPtr],
+        P::SensorRead,
+        bpf_saul_reg_read,
+    ),
+    HF::new(
+        ID::BPF_SAUL_REG_READ_TEMP,
+        "bpf_saul_read_temp",
+        &[Handle, OutPtr],
+        P::SensorRead,
+        bpf_saul_read_temp,
+    ),
+    HF::new(
+        ID::BPF_GCOAP_RESP_INIT_IDX,
+        "bpf_gcoap_resp_init",
+        &[InPtr, Scalar],
+        P::Coap,
+        bpf_gcoap_resp_init,
+    ),
+    HF::new(
+        ID::BPF_COAP_OPT_FINISH_IDX,
+        "bpf_coap_opt_finish",
+        &[InPtr, Scalar],
+        P::Coap,
+        bpf_coap_opt_finish,
+    ),
+    HF::new(
+        ID::BPF_COAP_ADD_FORMAT_IDX,
+        "bpf_coap_add_format",
+        &[InPtr, Scalar],
+        P::Coap,
+        bpf_coap_add_format,
+    ),
+    HF::new(ID::BPF_COAP_GET_PDU_IDX, "bpf_coap_get_pdu", &[InPtr], P::Coap, bpf_coap_get_pdu),
+    HF::new(
+        ID::BPF_COAP_GET_METHOD_IDX,
+        "bpf_coap_get_method",
+        &[InPtr],
+        P::Coap,
+        bpf_coap_get_method,
+    ),
+    HF::new(
+        ID::BPF_COAP_GET_OPTION_IDX,
+        "bpf_coap_get_option",
+        &[InPtr, Scalar, Scalar, OutPtr, Scalar],
+        P::Coap,
+        bpf_coap_get_option,
+    ),
+    HF::new(
+        ID::BPF_COAP_GET_URI_PATH_IDX,
+        "bpf_coap_get_uri_path",
+        &[InPtr, OutPtr, Scalar],
+        P::Coap,
+        bpf_coap_get_uri_path,
+    ),
+    HF::new(
+        ID::BPF_COAP_GET_URI_QUERY_IDX,
+        "bpf_coap_get_uri_query",
+        &[InPtr, Scalar, OutPtr, Scalar],
+        P::Coap,
+        bpf_coap_get_uri_query,
+    ),
+    HF::new(
+        ID::BPF_COAP_GET_CONTENT_FORMAT_IDX,
+        "bpf_coap_get_content_format",
+        &[InPtr],
+        P::Coap,
+        bpf_coap_get_content_format,
+    ),
+    HF::new(
+        ID::BPF_COAP_GET_PAYLOAD_IDX,
+        "bpf_coap_get_payload",
+        &[InPtr, OutPtr, Scalar],
+        P::Coap,
+        bpf_coap_get_payload,
+    ),
+    HF::new(
+        ID::BPF_COAP_ADD_OPTION_IDX,
+        "bpf_coap_add_option",
+        &[InPtr, Scalar, InPtr, Scalar],
+        P::Coap,
+        bpf_coap_add_option,
+    ),
+    HF::new(
+        ID::BPF_COAP_ADD_UINT_OPTION_IDX,
+        "bpf_coap_add_uint_option",
+        &[InPtr, Scalar, Scalar],
+        P::Coap,
+        bpf_coap_add_uint_option,
+    ),
+    HF::new(
+        ID::BPF_COAP_ADD_PAYLOAD_IDX,
+        "bpf_coap_add_payload",
+        &[InPtr, InPtr, Scalar],
+        P::Coap,
+        bpf_coap_add_payload,
+    ),
+    HF::new(
+        ID::BPF_COAP_CLIENT_SEND_IDX,
+        "bpf_coap_client_send",
+        &[Scalar, Scalar, InPtr, InPtr, Scalar],
+        P::Coap,
+        bpf_coap_client_send,
+    ),
+    HF::new(
+        ID::BPF_COAP_CLIENT_READ_RESPONSE_IDX,
+        "bpf_coap_client_read_response",
+        &[OutPtr, Scalar],
+        P::Coap,
+        bpf_coap_client_read_response,
+    ),
+    HF::new(ID::BPF_UDP_OPEN_IDX, "bpf_udp_open", &[Scalar], P::Network, bpf_udp_open),
+    HF::new(
+        ID::BPF_UDP_SEND_IDX,
+        "bpf_udp_send",
+        &[Handle, InPtr, InPtr, Scalar],
+        P::Network,
+        bpf_udp_send,
+    ),
+    HF::new(
+        ID::BPF_UDP_RECV_IDX,
+        "bpf_udp_recv",
+        &[Handle, OutPtr, Scalar, Scalar],
+        P::Network,
+        bpf_udp_recv,
+    ),
+    HF::new(ID::BPF_STRLEN_IDX, "bpf_strlen", &[InPtr], P::Memory, bpf_strlen),
+    HF::new(
+        ID::BPF_SNPRINTF_IDX,
+        "bpf_snprintf",
+        &[OutPtr, Scalar, InPtr, InPtr, Scalar],
+        P::Memory,
+        bpf_snprintf,
+    ),
+    HF::new(
+        ID::BPF_FMT_S16_DFP_IDX,
+        "bpf_fmt_s16_dfp",
+        &[OutPtr, Scalar, Scalar],
+        P::Memory,
+        bpf_fmt_s16_dfp,
+    ),
+    HF::new(
+        ID::BPF_FMT_U32_DEC_IDX,
+        "bpf_fmt_u32_dec",
+        &[OutPtr, Scalar],
+        P::Memory,
+        bpf_fmt_u32_dec,
+    ),
+    HF::new(
+        ID::BPF_GPIO_READ_INPUT,
+        "bpf_gpio_read_input",
+        &[Scalar, Scalar],
+        P::Gpio,
+        bpf_gpio_read_input,
+    ),
+    HF::new(
+        ID::BPF_GPIO_READ_RAW,
+        "bpf_gpio_read_raw",
+        &[Scalar, Scalar],
+        P::Gpio,
+        bpf_gpio_read_raw,
+    ),
+    HF::new(
+        ID::BPF_GPIO_WRITE,
+        "bpf_gpio_write",
+        &[Scalar, Scalar, Scalar],
+        P::Gpio,
+        bpf_gpio_write,
+    ),
+    HF::new(ID::BPF_HD44780_INIT, "bpf_hd44780_init", &[], P::Display, bpf_hd44780_init),
+    HF::new(
+        ID::BPF_HD44780_OPEN_ROWS,
+        "bpf_hd44780_open_rows",
+        &[Scalar, Scalar],
+        P::Display,
+        bpf_hd44780_open_rows,
+    ),
+    HF::new(ID::BPF_HD44780_CLEAR, "bpf_hd44780_clear", &[Handle], P::Display, bpf_hd44780_clear),
+    HF::new(
+        ID::BPF_HD44780_PRINT,
+        "bpf_hd44780_print",
+        &[Handle, InPtr],
+        P::Display,
+        bpf_hd44780_print,
+    ),
+    HF::new(
+        ID::BPF_HD44780_SET_CURSOR,
+        "bpf_hd44780_set_cursor",
+        &[Handle, Scalar, Scalar],
+        P::Display,
+        bpf_hd44780_set_cursor,
+    ),
+    HF::new(
+        ID::BPF_KEYPAD_GET_INPUT,
+        "bpf_keypad_get_input",
+        &[Scalar],
+        P::SensorRead,
+        bpf_keypad_get_input,
+    ),
+    HF::new(
+        ID::BPF_CALL_SLOT_IDX,
+        "bpf_call_slot",
+        &[Scalar, OutPtr, Scalar, OutPtr],
+        P::Ipc,
+        bpf_call_slot,
+    ),
+    HF::new(ID::BPF_QUEUE_SEND_IDX, "bpf_queue_send", &[Scalar, Scalar], P::Ipc, bpf_queue_send),
+    HF::new(
+        ID::BPF_QUEUE_RECEIVE_IDX,
+        "bpf_queue_receive",
+        &[Scalar, OutPtr, Scalar],
+        P::Ipc,
+        bpf_queue_receive,
+    ),
+    HF::new(
+        ID::BPF_MAP_LOOKUP_ELEM_IDX,
+        "bpf_map_lookup_elem",
+        &[Scalar, InPtr, OutPtr],
+        P::Storage,
+        bpf_map_lookup_elem,
+    ),
+    HF::new(
+        ID::BPF_MAP_UPDATE_ELEM_IDX,
+        "bpf_map_update_elem",
+        &[Scalar, InPtr, InPtr, Scalar],
+        P::Storage,
+        bpf_map_update_elem,
+    ),
+    HF::new(
+        ID::BPF_MAP_DELETE_ELEM_IDX,
+        "bpf_map_delete_elem",
+        &[Scalar, InPtr],
+        P::Storage,
+        bpf_map_delete_elem,
+    ),
+    HF::new(
+        ID::BPF_RINGBUF_RESERVE_IDX,
+        "bpf_ringbuf_reserve",
+        &[Scalar, OutPtr, Scalar],
+        P::Storage,
+        bpf_ringbuf_reserve,
+    ),
+    HF::new(
+        ID::BPF_RINGBUF_SUBMIT_IDX,
+        "bpf_ringbuf_submit",
+        &[Handle, Scalar],
+        P::Storage,
+        bpf_ringbuf_submit,
+    ),
+    HF::new(
+        ID::BPF_RINGBUF_DISCARD_IDX,
+        "bpf_ringbuf_discard",
+        &[Handle],
+        P::Storage,
+        bpf_ringbuf_discard,
+    ),
+    HF::new(
+        ID::BPF_RINGBUF_OUTPUT_IDX,
+        "bpf_ringbuf_output",
+        &[Scalar, InPtr, Scalar, Scalar],
+        P::Storage,
+        bpf_ringbuf_output,
+    ),
+    HF::new(
+        ID::BPF_MAP_CAS_ELEM_IDX,
+        "bpf_map_cas_elem",
+        &[Scalar, InPtr, Scalar, Scalar],
+        P::Storage,
+        bpf_map_cas_elem,
+    ),
+    HF::new(
+        ID::BPF_MAP_FETCH_ADD_ELEM_IDX,
+        "bpf_map_fetch_add_elem",
+        &[Scalar, InPtr, Scalar, OutPtr],
+        P::Storage,
+        bpf_map_fetch_add_elem,
+    ),
+    HF::new(ID::BPF_CRC16_IDX, "bpf_crc16", &[InPtr, Scalar], P::Memory, bpf_crc16),
+    HF::new(ID::BPF_CRC32_IDX, "bpf_crc32", &[InPtr, Scalar], P::Memory, bpf_crc32),
+    HF::new(
+        ID::BPF_FLETCHER16_IDX,
+        "bpf_fletcher16",
+        &[InPtr, Scalar],
+        P::Memory,
+        bpf_fletcher16,
+    ),
+    HF::new(
+        ID::BPF_FLETCHER32_IDX,
+        "bpf_fletcher32",
+        &[InPtr, Scalar],
+        P::Memory,
+        bpf_fletcher32,
+    ),
+    HF::new(ID::BPF_SHA256_IDX, "bpf_sha256", &[InPtr, Scalar, OutPtr], P::Crypto, bpf_sha256),
+    HF::new(
+        ID::BPF_HMAC_SHA256_IDX,
+        "bpf_hmac_sha256",
+        &[InPtr, Scalar, InPtr, Scalar, OutPtr],
+        P::Crypto,
+        bpf_hmac_sha256,
+    ),
+    HF::new(
+        ID::BPF_RANDOM_BYTES_IDX,
+        "bpf_random_bytes",
+        &[OutPtr, Scalar],
+        P::Memory,
+        bpf_random_bytes,
+    ),
+    HF::new(ID::BPF_SAT_MUL_IDX, "bpf_sat_mul", &[Scalar, Scalar], P::Memory, bpf_sat_mul),
+    HF::new(ID::BPF_SAT_DIV_IDX, "bpf_sat_div", &[Scalar, Scalar], P::Memory, bpf_sat_div),
+    HF::new(ID::BPF_ISQRT_IDX, "bpf_isqrt", &[Scalar], P::Memory, bpf_isqrt),
+    HF::new(
+        ID::BPF_Q_MUL_IDX,
+        "bpf_q_mul",
+        &[Scalar, Scalar, Scalar, OutPtr],
+        P::Memory,
+        bpf_q_mul,
+    ),
+    HF::new(
+        ID::BPF_Q_DIV_IDX,
+        "bpf_q_div",
+        &[Scalar, Scalar, Scalar, OutPtr],
+        P::Memory,
+        bpf_q_div,
+    ),
+    HF::new(ID::BPF_LOG2_Q_IDX, "bpf_log2_q", &[Scalar, Scalar], P::Memory, bpf_log2_q),
+    HF::new(ID::BPF_LOG10_Q_IDX, "bpf_log10_q", &[Scalar, Scalar], P::Memory, bpf_log10_q),
+    HF::new(
+        ID::BPF_EMA_UPDATE_IDX,
+        "bpf_ema_update",
+        &[Scalar, InPtr, Scalar, Scalar, OutPtr],
+        P::Storage,
+        bpf_ema_update,
+    ),
+    HF::new(ID::BPF_GET_SLOT_IDX, "bpf_get_slot", &[], P::Introspection, bpf_get_slot),
+    HF::new(ID::BPF_GET_JOB_ID_IDX, "bpf_get_job_id", &[], P::Introspection, bpf_get_job_id),
+    HF::new(
+        ID::BPF_GET_EXECUTION_MODEL_IDX,
+        "bpf_get_execution_model",
+        &[],
+        P::Introspection,
+        bpf_get_execution_model,
+    ),
+    HF::new(
+        ID::BPF_GET_REMAINING_BUDGET_MS_IDX,
+        "bpf_get_remaining_budget_ms",
+        &[],
+        P::Introspection,
+        bpf_get_remaining_budget_ms,
+    ),
+    HF::new(ID::BPF_GET_WORKER_IDX, "bpf_get_worker", &[], P::Introspection, bpf_get_worker),
 ];
 
+/* Helper argument validation */
+
+/// Checks that the memory pointed to by a helper argument can be accessed by
+/// the calling program, logging the reason if it can't.
+fn valid_region(ptr: u64, len: u64, access: MemoryAccess) -> bool {
+    match helper_context::check_region(ptr, len, access) {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Rejected helper argument: {}", e);
+            false
+        }
+    }
+}
+
+/// Reads a bounded C string passed into a helper by the calling program.
+fn valid_c_string<'a>(ptr: u64) -> Option<&'a CStr> {
+    match helper_context::read_c_string(ptr) {
+        Ok(string) => Some(string),
+        Err(e) => {
+            error!("Rejected helper argument: {}", e);
+            None
+        }
+    }
+}
+
 /* Print/debug helper functions - implementation */
 
 /// Allows for printing arbitrary text to the RIOT shell console output.
+///
+/// The text is formatted in Rust (see [`formatting`]) instead of passing the
+/// format string and the arguments to the C `printf`, which would read as many
+/// variadic arguments as the conversions ask for. `%s` arguments need to point
+/// to strings that the program can access and `%n` is rejected. Returns 0 on
+/// success and -1 if the format string is invalid.
 pub fn bpf_printf(fmt: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> u64 {
-    // We need to take in the format string dynamically, so format! or println!
-    // won't work here. We need to call into C.
     extern "C" {
-        fn printf(fmt: *const c_char, ...) -> i32;
+        fn stdio_write(buffer: *const c_void, len: usize) -> isize;
     }
-    unsafe {
-        printf(
-            CStr::from_ptr(fmt as *const i8).as_ptr() as *const c_char,
-            a1 as u32,
-            a2 as u32,
-            a3 as u32,
-            a4 as u32,
-        );
+    let Some(fmt) = valid_c_string(fmt) else {
+        return -1i64 as u64;
+    };
+    let read_string = |ptr| valid_c_string(ptr).map(|string| string.to_bytes().to_vec());
+    let output = match formatting::printf_format(fmt.to_bytes(), &[a1, a2, a3, a4], read_string) {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Rejected bpf_printf format string {:?}: {}", fmt, e);
+            return -1i64 as u64;
+        }
+    };
+    unsafe { stdio_write(output.as_ptr() as *const c_void, output.len()) };
+    0
+}
+
+/// Maximum length of a message logged by `bpf_log`, longer messages are
+/// truncated.
+const MAX_LOG_MESSAGE_LENGTH: usize = 128;
+
+/// Converts the level passed to `bpf_log`, which uses the numbering of the
+/// RIOT `LOG_*` levels, into the level of the `log` crate.
+fn log_level(level: u64) -> Option<log::Level> {
+    match level {
+        1 => Some(log::Level::Error),
+        2 => Some(log::Level::Warn),
+        3 => Some(log::Level::Info),
+        4 => Some(log::Level::Debug),
+        5 => Some(log::Level::Trace),
+        _ => None,
     }
-    return 0;
+}
+
+/// Logs a message at the given level through the logger of the server, so
+/// that it is filtered according to the log level and printed in the same
+/// format as the logs of the server itself. Each record is tagged with the
+/// SUIT slot of the program and the ID of the job that executes it, so that
+/// the output of programs running concurrently can be told apart.
+///
+/// The message is formatted in Rust (see [`formatting`]), `%s` arguments need
+/// to point to strings that the program can access and `%n` is rejected.
+/// Returns 0 on success and -1 if the level or the format string is invalid.
+pub fn bpf_log(level: u64, fmt: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+    let Some(level) = log_level(level) else {
+        error!("Invalid log level: {}", level);
+        return -1i64 as u64;
+    };
+    let Some(fmt) = valid_c_string(fmt) else {
+        return -1i64 as u64;
+    };
+    // Skip formatting the message if it would be discarded anyway.
+    if level > log::max_level() {
+        return 0;
+    }
+    let read_string = |ptr| valid_c_string(ptr).map(|string| string.to_bytes().to_vec());
+    let formatted = formatting::printf_format(fmt.to_bytes(), &[a1, a2, a3], read_string);
+    let mut message = match formatted {
+        Ok(message) => message,
+        Err(e) => {
+            error!("Rejected bpf_log format string {:?}: {}", fmt, e);
+            return -1i64 as u64;
+        }
+    };
+    message.truncate(MAX_LOG_MESSAGE_LENGTH);
+    let message = String::from_utf8_lossy(&message);
+
+    let slot = helper_context::with_current_context(|ctx| ctx.configuration.suit_slot);
+    let job_id = job_context::job_id();
+    log::log!(
+        target: "bpf",
+        level,
+        "[slot {} job {}] {}",
+        slot.map_or(String::from("-"), |slot| format!("{}", slot)),
+        job_id.map_or(String::from("-"), |id| format!("{}", id)),
+        message.trim_end_matches('\n')
+    );
+    0
 }
 
 /// Responsible for printing debug information. Prints a single value.
@@ -93,6 +668,32 @@ pub fn bpf_print_debug(a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
 extern "C" {
     fn bpf_store_update_global(key: u32, value: u32) -> i64;
     fn bpf_store_fetch_global(key: u32, value: *mut u32) -> i64;
+    /// Unwrapped versions of the two functions above, they can only be
+    /// called while holding the lock of the global storage.
+    fn __real_bpf_store_update_global(key: u32, value: u32) -> i32;
+    fn __real_bpf_store_fetch_global(key: u32, value: *mut u32) -> i32;
+    fn global_store_lock();
+    fn global_store_unlock();
+}
+
+/// Holds the lock that serialises all accesses to the global storage (see
+/// `ffi/bpf_storage.c`), so that the atomic helpers can perform their
+/// read-modify-write sequences without any other store interleaving. The
+/// lock is taken by both VMs, as the Femto-Container VM accesses the storage
+/// through the same wrapped C functions.
+struct GlobalStoreGuard;
+
+impl GlobalStoreGuard {
+    fn lock() -> Self {
+        unsafe { global_store_lock() };
+        GlobalStoreGuard
+    }
+}
+
+impl Drop for GlobalStoreGuard {
+    fn drop(&mut self) {
+        unsafe { global_store_unlock() };
+    }
 }
 
 /// Local storage for the eBPF programs is managed on a per SUIT slot basis.
@@ -111,6 +712,9 @@ pub fn bpf_store_local(key: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u6
     local_storage::local_storage_store(key as usize, value as i32) as u64
 }
 pub fn bpf_fetch_local(key: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(value, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
     let value = value as *mut i32;
     unsafe {
         *value = local_storage::local_storage_fetch(key as usize).unwrap_or(0);
@@ -126,8 +730,175 @@ pub fn bpf_store_global(key: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u
     unsafe { bpf_store_update_global(key as u32, value as u32) as u64 }
 }
 
+/// Applies the operation to the value stored under the key in the global
+/// storage while holding the [`GlobalStoreGuard`]. Missing keys read as 0.
+/// Returns the previous value.
+fn global_store_atomic_update(key: u32, operation: impl FnOnce(u32) -> Option<u32>) -> u32 {
+    let _lock = GlobalStoreGuard::lock();
+    let mut previous: u32 = 0;
+    unsafe { __real_bpf_store_fetch_global(key, &mut previous as *mut u32) };
+    if let Some(new) = operation(previous) {
+        unsafe { __real_bpf_store_update_global(key, new) };
+    }
+    previous
+}
+
+/// Atomically replaces the value stored under the key in the global storage
+/// with `desired` if it is equal to `expected`. Returns 1 if the value was
+/// replaced and 0 otherwise.
+pub fn bpf_store_global_cas(key: u64, expected: u64, desired: u64, _a4: u64, _a5: u64) -> u64 {
+    let (expected, desired) = (expected as u32, desired as u32);
+    let previous = global_store_atomic_update(key as u32, |value| {
+        (value == expected).then_some(desired)
+    });
+    (previous == expected) as u64
+}
+
+/// Atomically adds `delta` to the value stored under the key in the global
+/// storage (wrapping around on overflow). The previous value is written into
+/// `previous_p` unless it is null. Returns 0 on success and -1 on failure.
+pub fn bpf_store_global_fetch_add(
+    key: u64,
+    delta: u64,
+    previous_p: u64,
+    _a4: u64,
+    _a5: u64,
+) -> u64 {
+    if previous_p != 0 && !valid_region(previous_p, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let previous = global_store_atomic_update(key as u32, |value| {
+        Some(value.wrapping_add(delta as u32))
+    });
+    if previous_p != 0 {
+        unsafe { *(previous_p as *mut u32) = previous };
+    }
+    0
+}
+
+/* Persistent storage */
+
+/// Stores `len` bytes of the value under the key in the persistent storage of
+/// the program's SUIT slot. Returns 0 on success and -1 if the value is too
+/// large or the quota of the slot would be exceeded.
+pub fn bpf_persist_store(key: u64, value_p: u64, len: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(value_p, len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    let value = unsafe { from_raw_parts(value_p as *const u8, len as usize) };
+    match persistent_storage::store(slot, key as u32, value) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Copies the value stored under the key in the persistent storage into the
+/// buffer, truncating it if the buffer is too small. Returns the length of the
+/// stored value or -1 if the key isn't present.
+pub fn bpf_persist_fetch(key: u64, buf_p: u64, len: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(buf_p, len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    match persistent_storage::fetch(slot, key as u32) {
+        Ok(Some(value)) => {
+            let copied = core::cmp::min(value.len(), len as usize);
+            let buf = unsafe { from_raw_parts_mut(buf_p as *mut u8, copied) };
+            buf.copy_from_slice(&value[..copied]);
+            value.len() as u64
+        }
+        Ok(None) => -1i64 as u64,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Removes the key from the persistent storage. Returns 0 on success and -1
+/// if the key isn't present.
+pub fn bpf_persist_delete(key: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    match persistent_storage::delete(slot, key as u32) {
+        Ok(true) => 0,
+        Ok(false) => -1i64 as u64,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* Named mutexes */
+
+/// Locks the mutex with a given name, waiting for at most `timeout_ms`
+/// milliseconds (see [`named_mutexes::MAX_MUTEX_TIMEOUT_MS`]). A timeout of 0
+/// only tries to lock the mutex without blocking, it is the only timeout that
+/// programs which aren't long-running can use. The mutex is released
+/// automatically when the program terminates. Returns 0 on success and -1 if
+/// the mutex couldn't be locked in time or the job was asked to stop.
+pub fn bpf_mutex_lock(name_p: u64, timeout_ms: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if timeout_ms != 0 && !job_context::can_block() {
+        error!("Only long-running programs can wait for a mutex");
+        return -1i64 as u64;
+    }
+    let Some(name) = valid_c_string(name_p).and_then(|name| name.to_str().ok()) else {
+        return -1i64 as u64;
+    };
+    let pid = thread::get_pid().into();
+    let index = match named_mutexes::lock(name, timeout_ms as u32, pid, job_context::should_stop) {
+        Ok(index) => index,
+        Err(e) => {
+            debug!("{}", e);
+            return -1i64 as u64;
+        }
+    };
+    let recorded = helper_context::with_current_context(|ctx| ctx.held_mutexes.push(index));
+    if recorded.is_none() {
+        named_mutexes::unlock(index);
+        return -1i64 as u64;
+    }
+    0
+}
+
+/// Unlocks the mutex with a given name. Returns 0 on success and -1 if the
+/// program doesn't hold the mutex.
+pub fn bpf_mutex_unlock(name_p: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(name) = valid_c_string(name_p).and_then(|name| name.to_str().ok()) else {
+        return -1i64 as u64;
+    };
+    let Some(index) = named_mutexes::find(name) else {
+        error!("Mutex {} does not exist", name);
+        return -1i64 as u64;
+    };
+    let held = helper_context::with_current_context(|ctx| {
+        let position = ctx.held_mutexes.iter().position(|held| *held == index);
+        position.map(|i| ctx.held_mutexes.remove(i))
+    })
+    .flatten();
+    if held.is_none() {
+        error!("Program does not hold mutex {}", name);
+        return -1i64 as u64;
+    }
+    named_mutexes::unlock(index);
+    0
+}
+
 pub fn bpf_fetch_global(key: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
     debug!("Fetching key: {:#x}, value: {:#x}", key, value);
+    if !valid_region(value, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
     unsafe {
         debug!(
             "Actual value in memory: {} ({:#x})",
@@ -142,6 +913,11 @@ pub fn bpf_fetch_global(key: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u
 /* Standard library functions */
 
 pub fn bpf_memcpy(dest_p: u64, src_p: u64, size: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(dest_p, size, MemoryAccess::Write)
+        || !valid_region(src_p, size, MemoryAccess::Read)
+    {
+        return 0;
+    }
     let dest: *mut riot_sys::libc::c_void = dest_p as *mut riot_sys::libc::c_void;
     let src: *const riot_sys::libc::c_void = src_p as *const riot_sys::libc::c_void;
     let size = size as u32;
@@ -151,36 +927,169 @@ pub fn bpf_memcpy(dest_p: u64, src_p: u64, size: u64, _a4: u64, _a5: u64) -> u64
     }
 }
 
+/// Returned by `bpf_memcmp` and `bpf_strncmp` if any of the arguments is
+/// invalid, the comparison results are always -1, 0 or 1.
+const INVALID_COMPARISON: u64 = -2i64 as u64;
+
+/// Sets `size` bytes starting at `dest_p` to the (truncated to 8 bits) value.
+/// Returns the destination pointer or 0 if the region can't be written.
+pub fn bpf_memset(dest_p: u64, value: u64, size: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(dest_p, size, MemoryAccess::Write) {
+        return 0;
+    }
+    let dest = unsafe { from_raw_parts_mut(dest_p as *mut u8, size as usize) };
+    dest.fill(value as u8);
+    dest_p
+}
+
+fn ordering_result(ordering: core::cmp::Ordering) -> u64 {
+    ordering as i64 as u64
+}
+
+/// Compares `size` bytes of the two regions. Returns -1, 0 or 1 if the first
+/// region is respectively smaller than, equal to or greater than the second
+/// one, or -2 if any of the regions can't be read.
+pub fn bpf_memcmp(a_p: u64, b_p: u64, size: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(a_p, size, MemoryAccess::Read) || !valid_region(b_p, size, MemoryAccess::Read)
+    {
+        return INVALID_COMPARISON;
+    }
+    let a = unsafe { from_raw_parts(a_p as *const u8, size as usize) };
+    let b = unsafe { from_raw_parts(b_p as *const u8, size as usize) };
+    ordering_result(a.cmp(b))
+}
+
+/// Reads at most `max_len` bytes of a string, logging the reason if it can't.
+fn valid_bounded_string<'a>(ptr: u64, max_len: u64) -> Option<&'a [u8]> {
+    match helper_context::read_bounded_string(ptr, max_len as usize) {
+        Ok(string) => Some(string),
+        Err(e) => {
+            error!("Rejected helper argument: {}", e);
+            None
+        }
+    }
+}
+
+/// Copies the string into the destination buffer of `size` bytes. Contrary to
+/// the C `strncpy`, at most `size - 1` bytes are copied and the destination is
+/// always null-terminated. Returns the number of bytes copied, excluding the
+/// null byte, or -1 if any of the arguments is invalid.
+pub fn bpf_strncpy(dest_p: u64, src_p: u64, size: u64, _a4: u64, _a5: u64) -> u64 {
+    if size == 0 || !valid_region(dest_p, size, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let Some(src) = valid_bounded_string(src_p, size - 1) else {
+        return -1i64 as u64;
+    };
+    // The regions may overlap, so the string is copied via a temporary buffer.
+    let src = src.to_vec();
+    let dest = unsafe { from_raw_parts_mut(dest_p as *mut u8, size as usize) };
+    dest[..src.len()].copy_from_slice(&src);
+    dest[src.len()] = 0;
+    src.len() as u64
+}
+
+/// Compares at most `size` bytes of the two strings, stopping at the first
+/// null byte. Returns -1, 0 or 1 if the first string is respectively smaller
+/// than, equal to or greater than the second one, or -2 if any of the strings
+/// can't be read.
+pub fn bpf_strncmp(a_p: u64, b_p: u64, size: u64, _a4: u64, _a5: u64) -> u64 {
+    let (Some(a), Some(b)) = (valid_bounded_string(a_p, size), valid_bounded_string(b_p, size))
+    else {
+        return INVALID_COMPARISON;
+    };
+    ordering_result(a.cmp(b))
+}
+
 /* Saul functions - implementation */
 
-/// Find a SAUL device by its position in the registry. It returns a pointer to
-/// the device which can then be used with reg_read / reg_write helpers to
-/// manipulate the device.
+/// Checks whether the job executing on the current thread was granted access
+/// to the SAUL device. The index of the device is its position in the registry.
+fn saul_device_allowed(dev: *mut riot_sys::saul_reg_t, index: u32) -> bool {
+    let class = unsafe { (*(*dev).driver).type_ };
+    job_context::saul_device_allowed(class, index)
+}
+
+/// Adds the device to the handle table of the calling program and returns the
+/// handle or 0 if it cannot be opened.
+fn open_saul_device(dev: *mut riot_sys::saul_reg_t, index: u32) -> u64 {
+    if dev.is_null() {
+        return 0;
+    }
+    if !saul_device_allowed(dev, index) {
+        error!("Program not allowed to access SAUL device {}", index);
+        return 0;
+    }
+    helper_context::with_current_context(|ctx| ctx.saul_devices.insert(dev as usize))
+        .flatten()
+        .map_or(0, |handle| handle as u64)
+}
+
+/// Resolves the SAUL device handle given to the program back into the device.
+fn lookup_saul_device(handle: u64) -> Option<*mut riot_sys::saul_reg_t> {
+    let dev = helper_context::with_current_context(|ctx| ctx.saul_devices.get(handle as u32))
+        .flatten()
+        .map(|address| address as *mut riot_sys::saul_reg_t);
+    if dev.is_none() {
+        error!("Invalid SAUL device handle: {}", handle);
+    }
+    dev
+}
+
+/// Find a SAUL device by its position in the registry. It returns an opaque
+/// handle to the device which can then be used with reg_read / reg_write
+/// helpers to manipulate the device. If the device doesn't exist or the
+/// program isn't allowed to access it, 0 is returned.
 pub fn bpf_saul_reg_find_nth(saul_dev_index: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    unsafe { return riot_sys::saul_reg_find_nth(saul_dev_index as i32) as u64 }
+    let dev = unsafe { riot_sys::saul_reg_find_nth(saul_dev_index as i32) };
+    open_saul_device(dev, saul_dev_index as u32)
 }
 
 /// Find the first device of the given type. The saul_dev_type needs to match
 /// the list of all device classes is available here:
 /// https://api.riot-os.org/group__drivers__saul.html#:~:text=category%20ID.%20More...-,enum,-%7B%0A%C2%A0%C2%A0SAUL_ACT_ANY
+/// Similarly to `bpf_saul_reg_find_nth`, it returns an opaque handle to the device.
 pub fn bpf_saul_reg_find_type(saul_dev_type: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    unsafe { return riot_sys::saul_reg_find_type(saul_dev_type as u8) as u64 }
+    let dev = unsafe { riot_sys::saul_reg_find_type(saul_dev_type as u8) };
+    if dev.is_null() {
+        return 0;
+    }
+    // The device index is needed to check the access restrictions, so we
+    // need to find the position of the device in the registry.
+    let mut index = 0;
+    loop {
+        let nth = unsafe { riot_sys::saul_reg_find_nth(index as i32) };
+        if nth.is_null() || nth == dev {
+            break;
+        }
+        index += 1;
+    }
+    open_saul_device(dev, index)
 }
 
-/// Given a pointer to the SAUL device struct, it reads from the device into the
+/// Given a handle to the SAUL device, it reads from the device into the
 /// provided phydat_t struct.
-pub fn bpf_saul_reg_read(dev_ptr: u64, data_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev: *mut riot_sys::saul_reg_t = dev_ptr as *mut riot_sys::saul_reg_t;
+pub fn bpf_saul_reg_read(dev_handle: u64, data_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    const PHYDAT_SIZE: u64 = core::mem::size_of::<riot_sys::phydat_t>() as u64;
+    if !valid_region(data_ptr, PHYDAT_SIZE, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let Some(dev) = lookup_saul_device(dev_handle) else {
+        return -1i64 as u64;
+    };
     let data: *mut riot_sys::phydat_t = data_ptr as *mut riot_sys::phydat_t;
-    //debug!("Reading from saul device at: {:x}", dev_ptr);
-    //debug!("Reading into phydat at: {:x}", data_ptr);
     unsafe { riot_sys::saul_reg_read(dev, data) as u64 }
 }
 
-/// Given a pointer to the SAUL device struct, it reads the value 0 from the device into the
+/// Given a handle to the SAUL device, it reads the value 0 from the device into the
 /// provided integer.
-pub fn bpf_saul_read_temp(dev_ptr: u64, value_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev: *mut riot_sys::saul_reg_t = dev_ptr as *mut riot_sys::saul_reg_t;
+pub fn bpf_saul_read_temp(dev_handle: u64, value_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(value_ptr, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let Some(dev) = lookup_saul_device(dev_handle) else {
+        return -1i64 as u64;
+    };
     let mut reading: riot_sys::phydat_t = Default::default();
     unsafe {
         let result = riot_sys::saul_reg_read(dev, &mut reading as *mut riot_sys::phydat_t);
@@ -189,10 +1098,16 @@ pub fn bpf_saul_read_temp(dev_ptr: u64, value_ptr: u64, _a3: u64, _a4: u64, _a5:
     }
 }
 
-/// Given a pointer to the SAUL device struct, it writes the provided phydat_t
+/// Given a handle to the SAUL device, it writes the provided phydat_t
 /// struct (pointed to by data_ptr) into the device.
-pub fn bpf_saul_reg_write(dev_ptr: u64, data_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev: *mut riot_sys::saul_reg_t = dev_ptr as *mut riot_sys::saul_reg_t;
+pub fn bpf_saul_reg_write(dev_handle: u64, data_ptr: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    const PHYDAT_SIZE: u64 = core::mem::size_of::<riot_sys::phydat_t>() as u64;
+    if !valid_region(data_ptr, PHYDAT_SIZE, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    let Some(dev) = lookup_saul_device(dev_handle) else {
+        return -1i64 as u64;
+    };
     let data: *const riot_sys::phydat_t = data_ptr as *const riot_sys::phydat_t;
     unsafe { riot_sys::saul_reg_write(dev, data) as u64 }
 }
@@ -204,24 +1119,73 @@ pub struct CoapContext {
     pub len: usize,
 }
 
+const COAP_CONTEXT_SIZE: u64 = core::mem::size_of::<CoapContext>() as u64;
+
+extern "C" {
+    fn coap_helper_get_pdu(pkt: *mut riot_sys::coap_pkt_t) -> *mut u8;
+    fn coap_helper_get_method(pkt: *mut riot_sys::coap_pkt_t) -> u32;
+    fn coap_helper_get_content_format(pkt: *mut riot_sys::coap_pkt_t) -> u32;
+    fn coap_helper_get_option(
+        pkt: *mut riot_sys::coap_pkt_t,
+        optnum: u32,
+        index: u32,
+        buf: *mut u8,
+        buf_len: usize,
+    ) -> isize;
+    fn coap_helper_get_uri_path(pkt: *mut riot_sys::coap_pkt_t, buf: *mut u8, buf_len: usize) -> isize;
+    fn coap_helper_get_payload(pkt: *mut riot_sys::coap_pkt_t, buf: *mut u8, buf_len: usize) -> isize;
+    fn coap_helper_add_option(
+        pkt: *mut riot_sys::coap_pkt_t,
+        optnum: u32,
+        value: *const u8,
+        len: usize,
+    ) -> isize;
+    fn coap_helper_add_uint_option(pkt: *mut riot_sys::coap_pkt_t, optnum: u32, value: u32) -> isize;
+    fn coap_helper_add_payload(pkt: *mut riot_sys::coap_pkt_t, data: *const u8, len: usize) -> isize;
+}
+
+/// The CoAP option number of the URI query.
+const COAP_OPT_URI_QUERY: u32 = 15;
+
+/// Returns the CoAP context of the packet that the calling program is
+/// processing. The context pointer passed in by the program only needs to
+/// point to its context struct, the actual packet pointers are taken from
+/// the helper context as the program could have overwritten them.
+fn coap_context(coap_ctx_p: u64) -> Option<CoapContext> {
+    if !valid_region(coap_ctx_p, COAP_CONTEXT_SIZE, MemoryAccess::Read) {
+        return None;
+    }
+    let packet = helper_context::with_current_context(|ctx| ctx.coap_packet).flatten();
+    if packet.is_none() {
+        error!("Program is not processing a CoAP packet");
+    }
+    packet.map(|packet| CoapContext {
+        pkt: packet.pkt as *mut riot_sys::coap_pkt_t,
+        buf: packet.buf as *mut u8,
+        len: packet.len,
+    })
+}
+
 /* (g)coap functions */
 /// Initializes a CoAP response packet on a buffer.
 /// Initializes payload location within the buffer based on packet setup.
+/// Note that this overwrites the options of the request, so all request
+/// inspection helpers need to be called before this one.
 pub fn bpf_gcoap_resp_init(coap_ctx_p: u64, resp_code: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let coap_ctx: *const CoapContext = coap_ctx_p as *const CoapContext;
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
 
     let resp_code = resp_code as u32;
 
     unsafe {
-        debug!("coap_ctx: {:?}", *coap_ctx);
-        debug!("coap pkt: {:?}", (*coap_ctx).pkt);
-        debug!("buf_len: {:?}", (*coap_ctx).len);
-        debug!("packet payload len: {:?}", (*(*coap_ctx).pkt).payload_len);
+        debug!("coap_ctx: {:?}", coap_ctx);
+        debug!("packet payload len: {:?}", (*coap_ctx.pkt).payload_len);
         debug!("resp code: {:?}", resp_code);
         let res = riot_sys::gcoap_resp_init(
-            (*coap_ctx).pkt,
-            (*coap_ctx).buf,
-            (*coap_ctx).len as u32,
+            coap_ctx.pkt,
+            coap_ctx.buf,
+            coap_ctx.len as u32,
             resp_code,
         ) as u64;
         return res;
@@ -229,34 +1193,260 @@ pub fn bpf_gcoap_resp_init(coap_ctx_p: u64, resp_code: u64, _a3: u64, _a4: u64,
 }
 
 pub fn bpf_coap_opt_finish(coap_ctx_p: u64, flags_u: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let coap_ctx: *const CoapContext = coap_ctx_p as *const CoapContext;
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
     unsafe {
-        debug!("coap_ctx: {:?}", *coap_ctx);
-        debug!("buf_len: {:?}", (*coap_ctx).len);
-        debug!("packet payload len: {:?}", (*(*coap_ctx).pkt).payload_len);
-        return riot_sys::coap_opt_finish((*coap_ctx).pkt, flags_u as u16) as u64;
+        debug!("coap_ctx: {:?}", coap_ctx);
+        debug!("packet payload len: {:?}", (*coap_ctx.pkt).payload_len);
+        return riot_sys::coap_opt_finish(coap_ctx.pkt, flags_u as u16) as u64;
     }
 }
 
 /// Append a Content-Format option to the pkt buffer.
 pub fn bpf_coap_add_format(coap_ctx_p: u64, format: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let coap_ctx: *const CoapContext = coap_ctx_p as *const CoapContext;
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
     unsafe {
-        debug!("coap_ctx: {:?}", *coap_ctx);
-        debug!("buf_len: {:?}", (*coap_ctx).len);
-        debug!("packet payload len: {:?}", (*(*coap_ctx).pkt).payload_len);
+        debug!("coap_ctx: {:?}", coap_ctx);
+        debug!("packet payload len: {:?}", (*coap_ctx.pkt).payload_len);
         // Again the type cast hacking is needed because we are using the function
         // from the inline module.
         return riot_sys::inline::coap_opt_add_format(
-            (*coap_ctx).pkt as *mut riot_sys::inline::coap_pkt_t,
+            coap_ctx.pkt as *mut riot_sys::inline::coap_pkt_t,
             format as u16,
         ) as u64;
     }
 }
 
-/// TODO: implement this helper
-pub fn bpf_coap_get_pdu(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    return 0;
+/// Returns a pointer to the start of the PDU (the CoAP header) in the packet buffer.
+pub fn bpf_coap_get_pdu(coap_ctx_p: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return 0;
+    };
+    unsafe { coap_helper_get_pdu(coap_ctx.pkt) as u64 }
+}
+
+/// Returns the method code of the request (1 = GET, 2 = POST, 3 = PUT, 4 = DELETE, ...).
+pub fn bpf_coap_get_method(coap_ctx_p: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return 0;
+    };
+    unsafe { coap_helper_get_method(coap_ctx.pkt) as u64 }
+}
+
+/// Returns the Content-Format of the request or 65535 if it isn't specified.
+pub fn bpf_coap_get_content_format(
+    coap_ctx_p: u64,
+    _a2: u64,
+    _a3: u64,
+    _a4: u64,
+    _a5: u64,
+) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return u16::MAX as u64;
+    };
+    unsafe { coap_helper_get_content_format(coap_ctx.pkt) as u64 }
+}
+
+/// Copies the value of the `index`-th occurrence of the option with a given
+/// number into the buffer. The value is truncated if it doesn't fit.
+/// Returns the full length of the option value or -1 if it isn't present.
+pub fn bpf_coap_get_option(
+    coap_ctx_p: u64,
+    optnum: u64,
+    index: u64,
+    buf_p: u64,
+    buf_len: u64,
+) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(buf_p, buf_len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    unsafe {
+        coap_helper_get_option(
+            coap_ctx.pkt,
+            optnum as u32,
+            index as u32,
+            buf_p as *mut u8,
+            buf_len as usize,
+        ) as u64
+    }
+}
+
+/// Writes the URI path of the request (e.g. "/temperature") as a null-terminated
+/// string into the buffer. Returns the length of the string including the null
+/// byte or a negative value if it doesn't fit.
+pub fn bpf_coap_get_uri_path(coap_ctx_p: u64, buf_p: u64, buf_len: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(buf_p, buf_len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    unsafe { coap_helper_get_uri_path(coap_ctx.pkt, buf_p as *mut u8, buf_len as usize) as u64 }
+}
+
+/// Copies the `index`-th URI query parameter (e.g. "unit=celsius") into the
+/// buffer. It allows for iterating over the query by incrementing the index
+/// until -1 is returned. Returns the full length of the parameter.
+pub fn bpf_coap_get_uri_query(
+    coap_ctx_p: u64,
+    index: u64,
+    buf_p: u64,
+    buf_len: u64,
+    _a5: u64,
+) -> u64 {
+    bpf_coap_get_option(coap_ctx_p, COAP_OPT_URI_QUERY as u64, index, buf_p, buf_len)
+}
+
+/// Copies the payload of the request into the buffer. The payload is truncated
+/// if it doesn't fit. Returns the full length of the payload, so calling it
+/// with an empty buffer allows for checking the payload length.
+pub fn bpf_coap_get_payload(coap_ctx_p: u64, buf_p: u64, buf_len: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(buf_p, buf_len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    unsafe { coap_helper_get_payload(coap_ctx.pkt, buf_p as *mut u8, buf_len as usize) as u64 }
+}
+
+/// Appends an option with an opaque value to the response. Options need to
+/// be added in the order of their option numbers.
+pub fn bpf_coap_add_option(
+    coap_ctx_p: u64,
+    optnum: u64,
+    value_p: u64,
+    len: u64,
+    _a5: u64,
+) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(value_p, len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    unsafe {
+        coap_helper_add_option(coap_ctx.pkt, optnum as u32, value_p as *const u8, len as usize)
+            as u64
+    }
+}
+
+/// Appends an option with an unsigned integer value to the response.
+pub fn bpf_coap_add_uint_option(
+    coap_ctx_p: u64,
+    optnum: u64,
+    value: u64,
+    _a4: u64,
+    _a5: u64,
+) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    unsafe { coap_helper_add_uint_option(coap_ctx.pkt, optnum as u32, value as u32) as u64 }
+}
+
+/// Copies the data into the payload of the response, it needs to be called
+/// after `bpf_coap_opt_finish` with COAP_OPT_FINISH_PAYLOAD. Returns the
+/// number of bytes written (which needs to be added to the length returned by
+/// the program) or -1 if the data doesn't fit.
+pub fn bpf_coap_add_payload(coap_ctx_p: u64, data_p: u64, len: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(coap_ctx) = coap_context(coap_ctx_p) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(data_p, len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    unsafe { coap_helper_add_payload(coap_ctx.pkt, data_p as *const u8, len as usize) as u64 }
+}
+
+/* CoAP client functions */
+
+/// Sends a CoAP request (method 1 - GET, 2 - POST, 3 - PUT) with the payload
+/// to the URI of the peer at a given position in the list of destinations
+/// declared in the execution request. The URI needs to be one of the URIs
+/// declared for that peer. It blocks until the response arrives, for at most
+/// the remaining time budget of the job. Returns the raw response code (e.g.
+/// 0x45 for 2.05 Content) or -1 on failure. The response payload can then be
+/// read using `bpf_coap_client_read_response`. Only long-running jobs can send
+/// requests, the other ones execute on the gcoap thread, which would have to
+/// process the response that they are waiting for.
+pub fn bpf_coap_client_send(
+    destination: u64,
+    method: u64,
+    uri_p: u64,
+    payload_p: u64,
+    payload_len: u64,
+) -> u64 {
+    if job_context::execution_model() != Some(job_context::ExecutionModel::LongRunning) {
+        error!("CoAP requests can only be sent by long-running programs");
+        return -1i64 as u64;
+    }
+    let Some(grant) = job_context::coap_destination(destination as usize) else {
+        error!("Program not allowed to send CoAP requests to destination {}", destination);
+        return -1i64 as u64;
+    };
+    let Some(uri) = valid_c_string(uri_p).and_then(|uri| uri.to_str().ok()) else {
+        return -1i64 as u64;
+    };
+    if !grant.uris.iter().any(|allowed| allowed == uri) {
+        error!("Program not allowed to send CoAP requests to {}{}", grant.peer, uri);
+        return -1i64 as u64;
+    }
+    if !valid_region(payload_p, payload_len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    let method = match coap_client::CoapMethod::try_from(method) {
+        Ok(method) => method,
+        Err(e) => {
+            error!("{}", e);
+            return -1i64 as u64;
+        }
+    };
+    let timeout_ms = match job_context::remaining_budget_ms() {
+        Some(0) => {
+            error!("Time budget of the job exhausted, not sending the CoAP request");
+            return -1i64 as u64;
+        }
+        Some(remaining) => remaining,
+        None => coap_client::MAX_COAP_CLIENT_TIMEOUT_MS,
+    };
+
+    let payload = unsafe { from_raw_parts(payload_p as *const u8, payload_len as usize) };
+    match coap_client::send(&grant.peer, method, uri, payload, timeout_ms) {
+        Ok(response) => {
+            helper_context::with_current_context(|ctx| {
+                ctx.coap_client_response = response.payload;
+            });
+            response.code as u64
+        }
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Copies the payload of the response to the last request sent using
+/// `bpf_coap_client_send` into the buffer (truncating it if the buffer is too
+/// small). Returns the length of the payload.
+pub fn bpf_coap_client_read_response(buf_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(buf_p, len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    helper_context::with_current_context(|ctx| {
+        let payload = &ctx.coap_client_response;
+        let copied = core::cmp::min(payload.len(), len as usize);
+        let buf = unsafe { from_raw_parts_mut(buf_p as *mut u8, copied) };
+        buf.copy_from_slice(&payload[..copied]);
+        payload.len() as u64
+    })
+    .unwrap_or(-1i64 as u64)
 }
 
 /// Returns the current time in milliseconds as measured by RIOT's ZTIMER.
@@ -277,23 +1467,244 @@ pub fn bpf_ztimer_now(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
     now as u64
 }
 
-/// Suspend the calling thread until the time (last_wakeup + period)
+/// Suspend the calling thread until the time (last_wakeup + period). The whole
+/// milliseconds of the wait are spent in [`interruptible_sleep_ms`], so -1 is
+/// returned if the job is asked to stop or exhausts its time budget meanwhile.
 pub fn bpf_periodic_wakeup(last_wakeup: u64, period: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(last_wakeup, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
     let last_wakeup: *mut u32 = last_wakeup as *mut u32;
     let period: u32 = period as u32;
+    let now = bpf_ztimer_now(0, 0, 0, 0, 0) as u32;
+    // The difference is interpreted as signed so that a wakeup time that has
+    // already passed doesn't result in a sleep.
+    let remaining_us = unsafe { last_wakeup.read_unaligned() }
+        .wrapping_add(period)
+        .wrapping_sub(now) as i32;
+    if !interruptible_sleep_ms(core::cmp::max(remaining_us, 0) as u32 / 1000) {
+        return -1i64 as u64;
+    }
     unsafe { riot_sys::ztimer_periodic_wakeup(riot_sys::ZTIMER_USEC, last_wakeup, period) }
 
     return 0;
 }
 
+/* Sleeping and timers */
+
+/// Interval at which the waiting helpers check whether the job should stop.
+const WAIT_CHECK_INTERVAL_MS: u32 = 10;
+
+/// Maximum number of timers that a single program can create.
+const MAX_TIMERS: usize = 4;
+
+/// Waits for `duration_ms` milliseconds in steps of [`WAIT_CHECK_INTERVAL_MS`],
+/// checking after each of them whether the job has been asked to stop or has
+/// exhausted its time budget. Returns false if the wait was cut short because
+/// of that, in which case the program is expected to exit. Only long-running
+/// jobs can wait, for the other ones false is returned straight away unless
+/// the duration is 0.
+fn interruptible_sleep_ms(duration_ms: u32) -> bool {
+    if duration_ms != 0 && !job_context::can_block() {
+        error!("Only long-running programs can sleep");
+        return false;
+    }
+    let clock = ztimer::Clock::msec();
+    let mut remaining = duration_ms;
+    loop {
+        if job_context::should_stop() {
+            return false;
+        }
+        if remaining == 0 {
+            return true;
+        }
+        let step = match job_context::remaining_budget_ms() {
+            Some(budget) => remaining.min(WAIT_CHECK_INTERVAL_MS).min(budget),
+            None => remaining.min(WAIT_CHECK_INTERVAL_MS),
+        };
+        clock.sleep(ztimer::Ticks(step));
+        remaining -= step;
+    }
+}
+
+/// Suspends the program for `ms` milliseconds. Returns 0 once the time has
+/// elapsed or -1 if the job was stopped or ran out of its time budget while
+/// sleeping, or if it isn't long-running (see [`job_context::can_block`]).
+pub fn bpf_sleep_ms(ms: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let ms = u32::try_from(ms).unwrap_or(u32::MAX);
+    if interruptible_sleep_ms(ms) {
+        0
+    } else {
+        -1i64 as u64
+    }
+}
+
+/// Suspends the program for `us` microseconds. Sleeps shorter than
+/// [`WAIT_CHECK_INTERVAL_MS`] are performed using `ZTIMER_USEC` without
+/// checking for stop requests in between, longer ones behave like `bpf_sleep_ms`.
+/// Like all waits, it is only available to long-running programs.
+pub fn bpf_sleep_us(us: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if job_context::should_stop() {
+        return -1i64 as u64;
+    }
+    if us != 0 && !job_context::can_block() {
+        error!("Only long-running programs can sleep");
+        return -1i64 as u64;
+    }
+    if us < WAIT_CHECK_INTERVAL_MS as u64 * 1000 {
+        unsafe { riot_sys::ztimer_sleep(riot_sys::ZTIMER_USEC, us as u32) };
+        return 0;
+    }
+    bpf_sleep_ms(us / 1000, 0, 0, 0, 0)
+}
+
+/// Creates a timer that expires `timeout_ms` milliseconds from now. If
+/// `periodic` is non-zero, the timer expires again every `timeout_ms`
+/// milliseconds after that. Returns the timer handle or 0 on failure.
+pub fn bpf_timer_create(timeout_ms: u64, periodic: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Ok(timeout_ms) = u32::try_from(timeout_ms) else {
+        return 0;
+    };
+    if timeout_ms == 0 {
+        return 0;
+    }
+    let timer = helper_context::Timer {
+        next_expiry_ms: (bpf_now_ms(0, 0, 0, 0, 0) as u32).wrapping_add(timeout_ms),
+        period_ms: (periodic != 0).then_some(timeout_ms),
+    };
+    helper_context::with_current_context(|ctx| {
+        if let Some(i) = ctx.timers.iter().position(Option::is_none) {
+            ctx.timers[i] = Some(timer);
+            return i as u64 + 1;
+        }
+        if ctx.timers.len() >= MAX_TIMERS {
+            return 0;
+        }
+        ctx.timers.push(Some(timer));
+        ctx.timers.len() as u64
+    })
+    .unwrap_or(0)
+}
+
+fn timer_slot(handle: u64) -> Option<usize> {
+    (handle as usize).checked_sub(1)
+}
+
+/// Waits until the timer expires. Periodic timers are then rearmed for their
+/// next period (periods that have already passed are skipped), one-shot timers
+/// are deleted. Returns 0 once the timer has expired or -1 if the handle is
+/// invalid or the job was stopped or ran out of its time budget while waiting.
+pub fn bpf_timer_wait(handle: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(index) = timer_slot(handle) else {
+        return -1i64 as u64;
+    };
+    let timer = helper_context::with_current_context(|ctx| ctx.timers.get(index).copied())
+        .flatten()
+        .flatten();
+    let Some(timer) = timer else {
+        error!("Invalid timer handle: {}", handle);
+        return -1i64 as u64;
+    };
+
+    let now = bpf_now_ms(0, 0, 0, 0, 0) as u32;
+    // Interpreted as signed so that the clock wrapping around is handled.
+    let remaining = timer.next_expiry_ms.wrapping_sub(now) as i32;
+    if !interruptible_sleep_ms(core::cmp::max(remaining, 0) as u32) {
+        return -1i64 as u64;
+    }
+
+    let next = timer.period_ms.map(|period| {
+        let mut next_expiry = timer.next_expiry_ms.wrapping_add(period);
+        let now = bpf_now_ms(0, 0, 0, 0, 0) as u32;
+        while (next_expiry.wrapping_sub(now) as i32) <= 0 {
+            next_expiry = next_expiry.wrapping_add(period);
+        }
+        helper_context::Timer {
+            next_expiry_ms: next_expiry,
+            period_ms: Some(period),
+        }
+    });
+    helper_context::with_current_context(|ctx| ctx.timers[index] = next);
+    0
+}
+
+/// Deletes the timer. Returns 0 on success or -1 if the handle is invalid.
+pub fn bpf_timer_delete(handle: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(index) = timer_slot(handle) else {
+        return -1i64 as u64;
+    };
+    helper_context::with_current_context(|ctx| match ctx.timers.get_mut(index) {
+        Some(timer @ Some(_)) => {
+            *timer = None;
+            0
+        }
+        _ => -1i64 as u64,
+    })
+    .unwrap_or(-1i64 as u64)
+}
+
 /* Format and string functions - implementation */
 
+/// Returns the length of the string, strings longer than
+/// [`helper_context::MAX_HELPER_STRING_LENGTH`] are rejected and 0 is returned.
 pub fn bpf_strlen(str_ptr: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let str_ptr = str_ptr as *const i8;
-    unsafe {
-        let c_str = CStr::from_ptr(str_ptr);
-        return c_str.to_bytes().len() as u64;
+    match valid_c_string(str_ptr) {
+        Some(c_str) => c_str.to_bytes().len() as u64,
+        None => 0,
+    }
+}
+
+/// Maximum number of arguments that can be passed to `bpf_snprintf`.
+const MAX_SNPRINTF_ARGS: usize = 8;
+
+/// Formats the arguments according to the format string (see [`formatting`]
+/// for the supported conversions) into the buffer of `len` bytes. Because
+/// helpers only take five arguments, the arguments to format are passed as an
+/// array of `args_count` 64 bit values. The output is truncated to fit into the
+/// buffer and always null-terminated. Returns the number of bytes written,
+/// excluding the terminating null byte, or -1 if the format string is invalid.
+pub fn bpf_snprintf(buf_p: u64, len: u64, fmt_p: u64, args_p: u64, args_count: u64) -> u64 {
+    if len == 0 || !valid_region(buf_p, len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let Some(fmt) = valid_c_string(fmt_p) else {
+        return -1i64 as u64;
+    };
+    if args_count as usize > MAX_SNPRINTF_ARGS {
+        error!("bpf_snprintf supports at most {} arguments", MAX_SNPRINTF_ARGS);
+        return -1i64 as u64;
+    }
+    let args_size = args_count * core::mem::size_of::<u64>() as u64;
+    if args_count > 0 && !valid_region(args_p, args_size, MemoryAccess::Read) {
+        return -1i64 as u64;
     }
+    // The program's array doesn't need to be aligned, so it is read bytewise.
+    let mut args = [0u64; MAX_SNPRINTF_ARGS];
+    if args_count > 0 {
+        let bytes = unsafe { from_raw_parts(args_p as *const u8, args_size as usize) };
+        for (arg, chunk) in args.iter_mut().zip(bytes.chunks_exact(8)) {
+            *arg = u64::from_ne_bytes(chunk.try_into().unwrap());
+        }
+    }
+
+    let read_string = |ptr| valid_c_string(ptr).map(|string| string.to_bytes().to_vec());
+    let formatted = formatting::printf_format(
+        fmt.to_bytes(),
+        &args[..args_count as usize],
+        read_string,
+    );
+    let output = match formatted {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Rejected bpf_snprintf format string {:?}: {}", fmt, e);
+            return -1i64 as u64;
+        }
+    };
+    let buf = unsafe { from_raw_parts_mut(buf_p as *mut u8, len as usize) };
+    let written = core::cmp::min(output.len(), buf.len() - 1);
+    buf[..written].copy_from_slice(&output[..written]);
+    buf[written] = 0;
+    written as u64
 }
 
 /// Convert 16-bit fixed point number to a decimal string.
@@ -305,6 +1716,11 @@ pub fn bpf_fmt_s16_dfp(out_p: u64, val: u64, fp_digits: u64, _a4: u64, _a5: u64)
         fn fmt_s16_dfp(out: *mut u8, val: i16, fp_digits: i32) -> usize;
     }
 
+    // Calling the function with a null buffer only computes the length of the output.
+    let len = unsafe { fmt_s16_dfp(core::ptr::null_mut(), val as i16, fp_digits as i32) };
+    if !valid_region(out_p, len as u64, MemoryAccess::Write) {
+        return 0;
+    }
     let out = out_p as *mut u8;
     unsafe {
         return fmt_s16_dfp(out, val as i16, fp_digits as i32) as u64;
@@ -318,6 +1734,10 @@ pub fn bpf_fmt_u32_dec(out_p: u64, val: u64, _a3: u64, _a4: u64, _a5: u64) -> u6
         fn fmt_u32_dec(out: *mut u8, val: u32) -> usize;
     }
 
+    let len = unsafe { fmt_u32_dec(core::ptr::null_mut(), val as u32) };
+    if !valid_region(out_p, len as u64, MemoryAccess::Write) {
+        return 0;
+    }
     let out = out_p as *mut u8;
     unsafe {
         return fmt_u32_dec(out, val as u32) as u64;
@@ -325,30 +1745,76 @@ pub fn bpf_fmt_u32_dec(out_p: u64, val: u64, _a3: u64, _a4: u64, _a5: u64) -> u6
 }
 
 /* GPIO functions - implementation */
+
+/// Checks whether the program can configure the pin in a given direction,
+/// logging the reason if it can't. Output pins owned by other running jobs
+/// can't be reconfigured.
+fn gpio_access_allowed(port: u64, pin_num: u64, direction: GpioDirection) -> bool {
+    if !job_context::gpio_pin_allowed(port as u32, pin_num as u32, direction) {
+        error!(
+            "Program not allowed to access GPIO pin ({}, {}) as {:?}",
+            port, pin_num, direction
+        );
+        return false;
+    }
+    if job_context::gpio_pin_owned_by_other_job(port as u32, pin_num as u32) {
+        error!(
+            "GPIO pin ({}, {}) is owned by another running program",
+            port, pin_num
+        );
+        return false;
+    }
+    true
+}
+
+/// Reads the state of the pin after configuring it as an input. The program
+/// needs to be granted input access to the pin and the pin can't be an output
+/// owned by another running program. Returns -1 if the pin can't be read.
 pub fn bpf_gpio_read_input(port: u64, pin_num: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let pin = gpio::GPIO::from_c(unsafe { riot_sys::macro_GPIO_PIN(port as u32, pin_num as u32) })
-        .unwrap();
+    if !gpio_access_allowed(port, pin_num, GpioDirection::Input) {
+        return -1i64 as u64;
+    }
+    let Some(pin) =
+        gpio::GPIO::from_c(unsafe { riot_sys::macro_GPIO_PIN(port as u32, pin_num as u32) })
+    else {
+        return -1i64 as u64;
+    };
     let result = pin.configure_as_input(gpio::InputMode::In);
     if let Ok(in_pin) = result {
         let pin_state = unsafe { riot_sys::gpio_read(in_pin.to_c()) };
         return pin_state as u64;
     }
-    return 0;
+    return -1i64 as u64;
 }
 
 /// Reads raw state of the pin, can be used to inspect the state of outputs without
 /// changing it. E.g. if we have a pin powering a led and then turn it to input
 /// to read its state, it will return 0 as changing a pin to input changes its
-/// state
+/// state. The program needs to be granted either input or output access to the
+/// pin, otherwise -1 is returned.
 pub fn bpf_gpio_read_raw(port: u64, pin_num: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !job_context::gpio_pin_allowed(port as u32, pin_num as u32, GpioDirection::Output)
+        && !job_context::gpio_pin_allowed(port as u32, pin_num as u32, GpioDirection::Input)
+    {
+        error!("Program not allowed to read GPIO pin ({}, {})", port, pin_num);
+        return -1i64 as u64;
+    }
     let pin_state =
         unsafe { riot_sys::gpio_read(riot_sys::macro_GPIO_PIN(port as u32, pin_num as u32)) };
     return pin_state as u64;
 }
 
+/// Configures the pin as an output and writes the value to it. The program
+/// needs to be granted output access to the pin.
 pub fn bpf_gpio_write(port: u64, pin_num: u64, val: u64, _a4: u64, _a5: u64) -> u64 {
-    let pin = gpio::GPIO::from_c(unsafe { riot_sys::macro_GPIO_PIN(port as u32, pin_num as u32) })
-        .unwrap();
+    if !gpio_access_allowed(port, pin_num, GpioDirection::Output) {
+        return 0;
+    }
+    let Some(pin) =
+        gpio::GPIO::from_c(unsafe { riot_sys::macro_GPIO_PIN(port as u32, pin_num as u32) })
+    else {
+        return 0;
+    };
     let result = pin.configure_as_output(gpio::OutputMode::Out);
     if let Ok(out_pin) = result {
         unsafe { riot_sys::gpio_write(out_pin.to_c(), val as i32) };
@@ -357,30 +1823,80 @@ pub fn bpf_gpio_write(port: u64, pin_num: u64, val: u64, _a4: u64, _a5: u64) ->
     return 0;
 }
 
+/// Opens a handle with exclusive access to the whole LCD display, the display
+/// is initialized when it is first used. Returns 0 if the display is used by
+/// another running program.
 pub fn bpf_hd44780_init(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev = HD44780LCD::new();
-    let dev_ptr: *mut hd44780_t = dev.into();
-    return dev_ptr as u64;
+    bpf_hd44780_open_rows(0, 0, 0, 0, 0)
 }
 
-pub fn bpf_hd44780_clear(dev: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev = HD44780LCD::from(dev as *mut hd44780_t);
-    dev.clear();
-    return 0;
-}
-pub fn bpf_hd44780_print(dev: u64, data: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev = HD44780LCD::from(dev as *mut hd44780_t);
-    let string = unsafe { CStr::from_ptr(data as *const i8) };
-    if let Ok(str) = string.to_str() {
-        dev.print(str);
+/// Opens a handle with write access to `row_count` rows of the LCD display
+/// starting at `first_row` (all rows if `row_count` is 0). Returns 0 if any
+/// of the rows is used by another running program.
+pub fn bpf_hd44780_open_rows(first_row: u64, row_count: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let (Ok(first_row), Ok(row_count)) = (u8::try_from(first_row), u8::try_from(row_count)) else {
         return 0;
+    };
+    let Some(job_id) = job_context::job_id() else {
+        return 0;
+    };
+    match lcd_display::open(first_row, row_count, job_id) {
+        Ok(handle) => handle as u64,
+        Err(e) => {
+            error!("{}", e);
+            0
+        }
     }
-    return 0;
 }
-pub fn bpf_hd44780_set_cursor(dev: u64, row: u64, column: u64, _a4: u64, _a5: u64) -> u64 {
-    let dev = HD44780LCD::from(dev as *mut hd44780_t);
-    dev.set_cursor(row as u8, column as u8);
-    return 0;
+
+/// Clears the rows of the LCD display that the handle gives access to.
+pub fn bpf_hd44780_clear(handle: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(job_id) = job_context::job_id() else {
+        return -1i64 as u64;
+    };
+    match lcd_display::clear(handle as u32, job_id) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Prints the string at the cursor of the handle, the part that doesn't fit
+/// into the current row is cut off. Returns the number of printed characters.
+pub fn bpf_hd44780_print(handle: u64, data: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(string) = valid_c_string(data) else {
+        return -1i64 as u64;
+    };
+    let Some(job_id) = job_context::job_id() else {
+        return -1i64 as u64;
+    };
+    match lcd_display::print(handle as u32, job_id, string.to_bytes()) {
+        Ok(printed) => printed as u64,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Moves the cursor of the handle, the row needs to be one of the rows that
+/// the handle gives access to.
+pub fn bpf_hd44780_set_cursor(handle: u64, col: u64, row: u64, _a4: u64, _a5: u64) -> u64 {
+    let (Ok(col), Ok(row)) = (u8::try_from(col), u8::try_from(row)) else {
+        return -1i64 as u64;
+    };
+    let Some(job_id) = job_context::job_id() else {
+        return -1i64 as u64;
+    };
+    match lcd_display::set_cursor(handle as u32, job_id, col, row) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
 }
 
 pub fn bpf_keypad_get_input(adc_index: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
@@ -388,3 +1904,764 @@ pub fn bpf_keypad_get_input(adc_index: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u
     let direction = dev.read_direction();
     return direction as u64;
 }
+
+/* Program chaining functions - implementation */
+
+/// Executes the program loaded into the given SUIT slot and writes its return
+/// value into the result pointer. The pointer to the argument buffer is passed
+/// into the callee as its only argument and the callee can also write its
+/// results back into it. Returns 0 on success and -1 if the call cannot be
+/// made (e.g. the slot is empty or the maximum call depth has been reached).
+/// The result is returned separately because any value, including -1, can
+/// be returned by the callee.
+pub fn bpf_call_slot(slot: u64, arg_p: u64, arg_len: u64, result_p: u64, _a5: u64) -> u64 {
+    if !valid_region(arg_p, arg_len, MemoryAccess::Write)
+        || !valid_region(result_p, 8, MemoryAccess::Write)
+    {
+        return -1i64 as u64;
+    }
+    let argument: &mut [u8] = if arg_len == 0 {
+        &mut []
+    } else {
+        unsafe { from_raw_parts_mut(arg_p as *mut u8, arg_len as usize) }
+    };
+    match call_program_in_slot(slot as usize, argument) {
+        Ok(result) => {
+            unsafe { *(result_p as *mut u64) = result };
+            0
+        }
+        Err(e) => {
+            error!("Call into the program in SUIT slot {} failed: {}", slot, e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* Message queue functions - implementation */
+
+/// Puts the value into the message queue with a given name without blocking.
+/// Returns 0 on success and -1 if the queue is full or the program wasn't
+/// granted permission to send messages to it.
+pub fn bpf_queue_send(queue: u64, value: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let queue = queue as u32;
+    if !matches!(job_context::message_queue_grant(queue), Some(grant) if grant.can_send) {
+        error!("Program not allowed to send messages to queue {}", queue);
+        return -1i64 as u64;
+    }
+    match message_queues::send(queue, value as u32) {
+        Ok(()) => 0,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Takes the next message out of the queue with a given name and writes it
+/// into the provided value. It blocks for at most `timeout_ms` milliseconds
+/// waiting for a message to arrive, a timeout of 0 makes the call non-blocking.
+/// Only long-running programs can block, the other ones need to use a timeout
+/// of 0. Returns 0 on success and -1 if no message arrived in time, the job was
+/// asked to stop or the program wasn't granted permission to receive messages
+/// from the queue.
+pub fn bpf_queue_receive(queue: u64, value_p: u64, timeout_ms: u64, _a4: u64, _a5: u64) -> u64 {
+    let queue = queue as u32;
+    if timeout_ms != 0 && !job_context::can_block() {
+        error!("Only long-running programs can wait for messages");
+        return -1i64 as u64;
+    }
+    if !matches!(job_context::message_queue_grant(queue), Some(grant) if grant.can_receive) {
+        error!("Program not allowed to receive messages from queue {}", queue);
+        return -1i64 as u64;
+    }
+    if !valid_region(value_p, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    match message_queues::receive(queue, timeout_ms as u32, job_context::should_stop) {
+        Ok(value) => {
+            unsafe { *(value_p as *mut u32) = value };
+            0
+        }
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* BPF maps */
+
+/// Returns the definition of the map if the program can access it, validating
+/// that the key and value (if given) pointers are large enough.
+fn accessible_map(
+    map_id: u32,
+    key_p: u64,
+    value: Option<(u64, MemoryAccess)>,
+) -> Option<MapDefinition> {
+    if !job_context::map_allowed(map_id) {
+        error!("Program has not declared map {}", map_id);
+        return None;
+    }
+    let definition = match bpf_maps::definition(map_id) {
+        Ok(definition) => definition,
+        Err(e) => {
+            error!("{}", e);
+            return None;
+        }
+    };
+    if !valid_region(key_p, definition.key_size as u64, MemoryAccess::Read) {
+        return None;
+    }
+    if let Some((value_p, access)) = value {
+        if !valid_region(value_p, definition.value_size as u64, access) {
+            return None;
+        }
+    }
+    Some(definition)
+}
+
+/// Copies the value corresponding to the key into the value buffer. Contrary to
+/// the eBPF helper, it doesn't return a pointer to the value as the program
+/// cannot access the memory of the map directly. Returns 0 on success and -1
+/// if the key isn't in the map.
+pub fn bpf_map_lookup_elem(map_id: u64, key_p: u64, value_p: u64, _a4: u64, _a5: u64) -> u64 {
+    let map_id = map_id as u32;
+    let Some(definition) = accessible_map(map_id, key_p, Some((value_p, MemoryAccess::Write)))
+    else {
+        return -1i64 as u64;
+    };
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    let value = unsafe { from_raw_parts_mut(value_p as *mut u8, definition.value_size as usize) };
+    match bpf_maps::lookup(map_id, key, value, slot) {
+        Ok(()) => 0,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Sets the value corresponding to the key, `flags` follow the eBPF
+/// convention (0 - any, 1 - only if the key doesn't exist, 2 - only if it does).
+/// Returns 0 on success and -1 on failure.
+pub fn bpf_map_update_elem(
+    map_id: u64,
+    key_p: u64,
+    value_p: u64,
+    flags: u64,
+    _a5: u64,
+) -> u64 {
+    let map_id = map_id as u32;
+    let Some(definition) = accessible_map(map_id, key_p, Some((value_p, MemoryAccess::Read)))
+    else {
+        return -1i64 as u64;
+    };
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    let value = unsafe { from_raw_parts(value_p as *const u8, definition.value_size as usize) };
+    match bpf_maps::update(map_id, key, value, flags, slot) {
+        Ok(()) => 0,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Removes the key from a hash map. Returns 0 on success and -1 on failure.
+pub fn bpf_map_delete_elem(map_id: u64, key_p: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let map_id = map_id as u32;
+    let Some(definition) = accessible_map(map_id, key_p, None) else {
+        return -1i64 as u64;
+    };
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    match bpf_maps::delete(map_id, key) {
+        Ok(()) => 0,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* UDP sockets */
+
+/// Maximum number of UDP endpoints that a single program can open.
+const MAX_UDP_ENDPOINTS: usize = 4;
+
+/// Opens an endpoint on a local UDP port granted in the execution request.
+/// Returns the endpoint handle or 0 if the port wasn't granted.
+pub fn bpf_udp_open(local_port: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Ok(local_port) = u16::try_from(local_port) else {
+        return 0;
+    };
+    if job_context::udp_port_grant(local_port).is_none() {
+        error!("Program not allowed to use UDP port {}", local_port);
+        return 0;
+    }
+    let Some(job_id) = job_context::job_id() else {
+        return 0;
+    };
+    if let Err(e) = udp_sockets::open(job_id, local_port) {
+        error!("{}", e);
+        return 0;
+    }
+    helper_context::with_current_context(|ctx| {
+        if let Some(i) = ctx.udp_endpoints.iter().position(|port| *port == local_port) {
+            return i as u64 + 1;
+        }
+        if ctx.udp_endpoints.len() >= MAX_UDP_ENDPOINTS {
+            return 0;
+        }
+        ctx.udp_endpoints.push(local_port);
+        ctx.udp_endpoints.len() as u64
+    })
+    .unwrap_or(0)
+}
+
+fn udp_endpoint(handle: u64) -> Option<u16> {
+    let index = (handle as usize).checked_sub(1)?;
+    helper_context::with_current_context(|ctx| ctx.udp_endpoints.get(index).copied()).flatten()
+}
+
+/// Checks that both the host and the port of the remote endpoint were granted
+/// together with the local port.
+fn udp_remote_allowed(grant: &UdpPortGrant, remote: &embedded_nal::SocketAddr) -> bool {
+    grant.remote_ports.contains(&remote.port())
+        && grant.remote_hosts.iter().any(|host| {
+            host.parse::<embedded_nal::IpAddr>().is_ok_and(|host| host == remote.ip())
+        })
+}
+
+/// Sends `len` bytes of data from the endpoint to the remote address given as a
+/// string (e.g. `[fe80::1]:514`). The remote host and port need to be among the
+/// ones granted together with the local port. Returns 0 on success and -1 on
+/// failure.
+pub fn bpf_udp_send(handle: u64, remote_p: u64, data_p: u64, len: u64, _a5: u64) -> u64 {
+    let Some(local_port) = udp_endpoint(handle) else {
+        error!("Invalid UDP endpoint handle: {}", handle);
+        return -1i64 as u64;
+    };
+    let Some(job_id) = job_context::job_id() else {
+        return -1i64 as u64;
+    };
+    let Some(remote) = valid_c_string(remote_p).and_then(|remote| remote.to_str().ok()) else {
+        return -1i64 as u64;
+    };
+    let Ok(remote) = remote.parse::<embedded_nal::SocketAddr>() else {
+        error!("Invalid UDP remote address: {}", remote);
+        return -1i64 as u64;
+    };
+    let allowed = job_context::udp_port_grant(local_port)
+        .is_some_and(|grant| udp_remote_allowed(&grant, &remote));
+    if !allowed {
+        error!("Program not allowed to send datagrams to {}", remote);
+        return -1i64 as u64;
+    }
+    if !valid_region(data_p, len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    let data = unsafe { from_raw_parts(data_p as *const u8, len as usize) };
+    match udp_sockets::send(job_id, local_port, remote, data) {
+        Ok(()) => 0,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Waits for at most `timeout_ms` milliseconds (and at most the remaining time
+/// budget of the job) for a datagram from one of the granted remote endpoints
+/// to arrive at the endpoint and copies it into the buffer. Programs that
+/// aren't long-running can only poll for a datagram using a timeout of 0.
+/// Returns the length of the datagram or -1 if none arrived in time or the job
+/// was asked to stop.
+pub fn bpf_udp_recv(handle: u64, buf_p: u64, len: u64, timeout_ms: u64, _a5: u64) -> u64 {
+    if timeout_ms != 0 && !job_context::can_block() {
+        error!("Only long-running programs can wait for datagrams");
+        return -1i64 as u64;
+    }
+    let Some(local_port) = udp_endpoint(handle) else {
+        error!("Invalid UDP endpoint handle: {}", handle);
+        return -1i64 as u64;
+    };
+    let Some(job_id) = job_context::job_id() else {
+        return -1i64 as u64;
+    };
+    let Some(grant) = job_context::udp_port_grant(local_port) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(buf_p, len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let timeout_ms = match job_context::remaining_budget_ms() {
+        Some(remaining) => core::cmp::min(remaining, timeout_ms as u32),
+        None => timeout_ms as u32,
+    };
+    let buffer = unsafe { from_raw_parts_mut(buf_p as *mut u8, len as usize) };
+    match udp_sockets::receive(
+        job_id,
+        local_port,
+        |remote| udp_remote_allowed(&grant, remote),
+        buffer,
+        timeout_ms,
+        job_context::should_stop,
+    ) {
+        Ok((len, _remote)) => len as u64,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* Ring buffers */
+
+/// Flag of `bpf_ringbuf_submit` and `bpf_ringbuf_output` which prevents the
+/// observers of the ring buffer resource from being notified about the record.
+const BPF_RB_NO_WAKEUP: u64 = 1;
+
+/// Maximum number of records that a single program can have reserved at a time.
+const MAX_RING_BUFFER_RESERVATIONS: usize = 4;
+
+extern "C" {
+    /// Schedules a notification of the observers of the ring buffer resource,
+    /// which is sent from the event thread.
+    fn ringbuf_observe_notify();
+}
+
+/// Reserves space for a `len` byte record in the ring buffer. The program
+/// writes the record into the buffer that it passes in and the record is copied
+/// into the ring buffer once it is submitted. Returns a reservation handle or 0
+/// if the ring buffer is full.
+pub fn bpf_ringbuf_reserve(map_id: u64, buf_p: u64, len: u64, _a4: u64, _a5: u64) -> u64 {
+    let map_id = map_id as u32;
+    if !job_context::map_allowed(map_id) {
+        error!("Program has not declared map {}", map_id);
+        return 0;
+    }
+    if !valid_region(buf_p, len, MemoryAccess::Write) {
+        return 0;
+    }
+    if let Err(e) = bpf_maps::ring_buffer_reserve(map_id, len as usize) {
+        debug!("{}", e);
+        return 0;
+    }
+
+    let reservation = helper_context::RingBufferReservation {
+        map_id,
+        buffer: buf_p,
+        len: len as usize,
+    };
+    let handle = helper_context::with_current_context(|ctx| {
+        let reservations = &mut ctx.ring_buffer_reservations;
+        if let Some(i) = reservations.iter().position(|r| r.is_none()) {
+            reservations[i] = Some(reservation);
+            return Some(i as u64 + 1);
+        }
+        if reservations.len() >= MAX_RING_BUFFER_RESERVATIONS {
+            return None;
+        }
+        reservations.push(Some(reservation));
+        Some(reservations.len() as u64)
+    })
+    .flatten();
+
+    handle.unwrap_or_else(|| {
+        error!("Too many outstanding ring buffer reservations");
+        bpf_maps::ring_buffer_discard(map_id, len as usize);
+        0
+    })
+}
+
+/// Removes the reservation corresponding to the handle from the context of
+/// the current program.
+fn take_reservation(handle: u64) -> Option<helper_context::RingBufferReservation> {
+    let index = (handle as usize).checked_sub(1)?;
+    helper_context::with_current_context(|ctx| {
+        ctx.ring_buffer_reservations
+            .get_mut(index)
+            .and_then(|reservation| reservation.take())
+    })
+    .flatten()
+}
+
+fn notify_ring_buffer_observers(flags: u64) {
+    if flags & BPF_RB_NO_WAKEUP != 0 {
+        return;
+    }
+    unsafe { ringbuf_observe_notify() };
+}
+
+/// Copies the record from the buffer passed into `bpf_ringbuf_reserve` into
+/// the ring buffer and notifies the observers unless `BPF_RB_NO_WAKEUP` is set.
+/// Returns 0 on success and -1 if the handle is invalid.
+pub fn bpf_ringbuf_submit(handle: u64, flags: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(reservation) = take_reservation(handle) else {
+        error!("Invalid ring buffer reservation handle: {}", handle);
+        return -1i64 as u64;
+    };
+    let record = unsafe { from_raw_parts(reservation.buffer as *const u8, reservation.len) };
+    if let Err(e) = bpf_maps::ring_buffer_submit(reservation.map_id, record) {
+        debug!("{}", e);
+        return -1i64 as u64;
+    }
+    notify_ring_buffer_observers(flags);
+    0
+}
+
+/// Releases the space reserved for a record without submitting it. Returns 0
+/// on success and -1 if the handle is invalid.
+pub fn bpf_ringbuf_discard(handle: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(reservation) = take_reservation(handle) else {
+        error!("Invalid ring buffer reservation handle: {}", handle);
+        return -1i64 as u64;
+    };
+    bpf_maps::ring_buffer_discard(reservation.map_id, reservation.len);
+    0
+}
+
+/// Copies `len` bytes of data into the ring buffer as a single record. Returns
+/// 0 on success and -1 if the ring buffer is full.
+pub fn bpf_ringbuf_output(map_id: u64, data_p: u64, len: u64, flags: u64, _a5: u64) -> u64 {
+    let map_id = map_id as u32;
+    if !job_context::map_allowed(map_id) {
+        error!("Program has not declared map {}", map_id);
+        return -1i64 as u64;
+    }
+    if !valid_region(data_p, len, MemoryAccess::Read) {
+        return -1i64 as u64;
+    }
+    let record = unsafe { from_raw_parts(data_p as *const u8, len as usize) };
+    if let Err(e) = bpf_maps::ring_buffer_output(map_id, record) {
+        debug!("{}", e);
+        return -1i64 as u64;
+    }
+    notify_ring_buffer_observers(flags);
+    0
+}
+
+/// Atomically replaces the value corresponding to the key with `desired` if
+/// it is equal to `expected`. The values of the map need to be 4 or 8 bytes
+/// long. Returns 1 if the value was replaced, 0 if it wasn't and -1 on failure.
+pub fn bpf_map_cas_elem(map_id: u64, key_p: u64, expected: u64, desired: u64, _a5: u64) -> u64 {
+    let map_id = map_id as u32;
+    let Some(definition) = accessible_map(map_id, key_p, None) else {
+        return -1i64 as u64;
+    };
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    // Only compare the bits that are actually stored in the map.
+    let mask = if definition.value_size == 4 { u32::MAX as u64 } else { u64::MAX };
+    let (expected, desired) = (expected & mask, desired & mask);
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    match bpf_maps::atomic_update(map_id, key, slot, |value| {
+        (value == expected).then_some(desired)
+    }) {
+        Ok(previous) => (previous == expected) as u64,
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Atomically adds `delta` to the value corresponding to the key (wrapping
+/// around on overflow). The previous value is written into `previous_p`
+/// unless it is null, it needs to be as large as the values of the map.
+/// Returns 0 on success and -1 on failure.
+pub fn bpf_map_fetch_add_elem(
+    map_id: u64,
+    key_p: u64,
+    delta: u64,
+    previous_p: u64,
+    _a5: u64,
+) -> u64 {
+    let map_id = map_id as u32;
+    let previous = (previous_p != 0).then_some((previous_p, MemoryAccess::Write));
+    let Some(definition) = accessible_map(map_id, key_p, previous) else {
+        return -1i64 as u64;
+    };
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    let value_size = definition.value_size as usize;
+    match bpf_maps::atomic_update(map_id, key, slot, |value| Some(value.wrapping_add(delta))) {
+        Ok(value) => {
+            if previous_p != 0 {
+                let previous = unsafe { from_raw_parts_mut(previous_p as *mut u8, value_size) };
+                previous.copy_from_slice(&value.to_le_bytes()[..value_size]);
+            }
+            0
+        }
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* Checksums, hashes and random numbers */
+
+/// Returns the readable region of `len` bytes at `data_p`, logging the reason
+/// if the program can't read it.
+fn readable_bytes<'a>(data_p: u64, len: u64) -> Option<&'a [u8]> {
+    if !valid_region(data_p, len, MemoryAccess::Read) {
+        return None;
+    }
+    Some(unsafe { from_raw_parts(data_p as *const u8, len as usize) })
+}
+
+/// Computes the CRC-16/CCITT-FALSE checksum of the data. Returns -1 if the
+/// region can't be read.
+pub fn bpf_crc16(data_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    readable_bytes(data_p, len).map_or(-1i64 as u64, |data| checksums::crc16(data) as u64)
+}
+
+/// Computes the (IEEE 802.3) CRC-32 checksum of the data. Returns -1 if the
+/// region can't be read.
+pub fn bpf_crc32(data_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    readable_bytes(data_p, len).map_or(-1i64 as u64, |data| checksums::crc32(data) as u64)
+}
+
+/// Computes the Fletcher-16 checksum of the data. Returns -1 if the region
+/// can't be read.
+pub fn bpf_fletcher16(data_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    readable_bytes(data_p, len).map_or(-1i64 as u64, |data| checksums::fletcher_16(data) as u64)
+}
+
+/// Computes the Fletcher-32 checksum of the data interpreted as 16 bit words,
+/// an odd trailing byte is padded with zero. Returns -1 if the region can't be
+/// read.
+pub fn bpf_fletcher32(data_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    readable_bytes(data_p, len).map_or(-1i64 as u64, |data| checksums::fletcher_32(data) as u64)
+}
+
+/// Writes the 32 byte SHA-256 digest of the data into the digest buffer.
+/// Returns 0 on success or -1 if any of the regions is invalid.
+pub fn bpf_sha256(data_p: u64, len: u64, digest_p: u64, _a4: u64, _a5: u64) -> u64 {
+    let Some(data) = readable_bytes(data_p, len) else {
+        return -1i64 as u64;
+    };
+    if !valid_region(digest_p, checksums::SHA256_DIGEST_LENGTH as u64, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let digest = checksums::sha_256(data);
+    let out = unsafe { from_raw_parts_mut(digest_p as *mut u8, digest.len()) };
+    out.copy_from_slice(&digest);
+    0
+}
+
+/// Writes the 32 byte HMAC-SHA256 of the data authenticated with the key into
+/// the digest buffer. Returns 0 on success or -1 if any of the regions is
+/// invalid.
+pub fn bpf_hmac_sha256(key_p: u64, key_len: u64, data_p: u64, len: u64, digest_p: u64) -> u64 {
+    let (Some(key), Some(data)) = (readable_bytes(key_p, key_len), readable_bytes(data_p, len))
+    else {
+        return -1i64 as u64;
+    };
+    if !valid_region(digest_p, checksums::SHA256_DIGEST_LENGTH as u64, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let digest = checksums::hmac_sha_256(key, data);
+    let out = unsafe { from_raw_parts_mut(digest_p as *mut u8, digest.len()) };
+    out.copy_from_slice(&digest);
+    0
+}
+
+/// Fills the buffer with bytes from the non-cryptographic RIOT PRNG, they must
+/// not be used as key material or nonces. It is therefore in the memory
+/// permission class rather than the crypto one. Returns 0 on success or -1 if
+/// the buffer can't be written.
+pub fn bpf_random_bytes(buf_p: u64, len: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    if !valid_region(buf_p, len, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    let buf = unsafe { from_raw_parts_mut(buf_p as *mut u8, len as usize) };
+    checksums::fill_random(buf);
+    0
+}
+
+/* Integer and fixed-point math */
+
+/// Sign-extends the 32 bit result so that the program sees it as a negative
+/// number when it reads the return value as a 64 bit integer.
+fn signed_result(value: i32) -> u64 {
+    value as i64 as u64
+}
+
+/// Multiplies two 32 bit signed integers, saturating instead of overflowing.
+pub fn bpf_sat_mul(a: u64, b: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    signed_result(fixed_point::saturating_mul(a as i32, b as i32))
+}
+
+/// Divides two 32 bit signed integers, saturating instead of overflowing. A
+/// division by zero returns the extreme value with the sign of the dividend.
+pub fn bpf_sat_div(a: u64, b: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    signed_result(fixed_point::saturating_div(a as i32, b as i32))
+}
+
+/// Returns the floor of the square root of the 64 bit unsigned value.
+pub fn bpf_isqrt(value: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    fixed_point::isqrt(value) as u64
+}
+
+/// Writes the result of a Q-format operation into the 32 bit buffer at
+/// `result_p`. Returns 0 on success and -1 if the operation failed or the
+/// buffer can't be written.
+fn q_result(result: Result<i32, String>, result_p: u64) -> u64 {
+    if !valid_region(result_p, 4, MemoryAccess::Write) {
+        return -1i64 as u64;
+    }
+    match result {
+        Ok(result) => {
+            unsafe { *(result_p as *mut i32) = result };
+            0
+        }
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Multiplies two Q-format numbers with `fractional_bits` (at most 31)
+/// fractional bits, rounding to nearest and saturating. The product is written
+/// into `result_p`, returns 0 on success and -1 if the number of fractional
+/// bits is invalid.
+pub fn bpf_q_mul(a: u64, b: u64, fractional_bits: u64, result_p: u64, _a5: u64) -> u64 {
+    q_result(fixed_point::q_mul(a as i32, b as i32, fractional_bits as u32), result_p)
+}
+
+/// Divides two Q-format numbers with `fractional_bits` (at most 31) fractional
+/// bits, rounding to nearest and saturating. The quotient is written into
+/// `result_p`, returns 0 on success and -1 if the number of fractional bits is
+/// invalid.
+pub fn bpf_q_div(a: u64, b: u64, fractional_bits: u64, result_p: u64, _a5: u64) -> u64 {
+    q_result(fixed_point::q_div(a as i32, b as i32, fractional_bits as u32), result_p)
+}
+
+/// Returns the base 2 logarithm of the positive 32 bit value in the Q format
+/// with `fractional_bits` (at most 16) fractional bits, with an error below one
+/// least significant bit. Returns -1 if the value is 0.
+pub fn bpf_log2_q(value: u64, fractional_bits: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match fixed_point::log2_q(value as u32, fractional_bits as u32) {
+        Ok(result) => signed_result(result),
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Returns the base 10 logarithm of the positive 32 bit value in the Q format
+/// with `fractional_bits` (at most 16) fractional bits, with an error of at most
+/// one least significant bit. Returns -1 if the value is 0.
+pub fn bpf_log10_q(value: u64, fractional_bits: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match fixed_point::log10_q(value as u32, fractional_bits as u32) {
+        Ok(result) => signed_result(result),
+        Err(e) => {
+            error!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/// Updates the exponential moving average stored in the map under the key
+/// with the sample, using the smoothing factor `alpha` in the Q16 format (at
+/// most 65536). The values of the map need to be 4 or 8 byte signed integers.
+/// The update is atomic with respect to other programs updating the same
+/// average. The new average is written into the buffer at `average_p` unless
+/// it is null. Returns 0 on success and -1 on failure.
+pub fn bpf_ema_update(map_id: u64, key_p: u64, sample: u64, alpha: u64, average_p: u64) -> u64 {
+    let map_id = map_id as u32;
+    let average = (average_p != 0).then_some((average_p, MemoryAccess::Write));
+    let Some(definition) = accessible_map(map_id, key_p, average) else {
+        return -1i64 as u64;
+    };
+    let Some(slot) = local_storage::lookup_slot_number() else {
+        return -1i64 as u64;
+    };
+    let key = unsafe { from_raw_parts(key_p as *const u8, definition.key_size as usize) };
+    let value_size = definition.value_size as usize;
+    let (sample, to_signed): (i64, fn(u64) -> i64) = if value_size == 4 {
+        (sample as i32 as i64, |value| value as u32 as i32 as i64)
+    } else {
+        (sample as i64, |value| value as i64)
+    };
+
+    let mut result = Err(String::from("Map update not performed"));
+    let updated = bpf_maps::atomic_update(map_id, key, slot, |previous| {
+        result = fixed_point::ema_update(to_signed(previous), sample, alpha as u32);
+        result.as_ref().ok().map(|average| *average as u64)
+    });
+    match updated.and(result) {
+        Ok(new_average) => {
+            if average_p != 0 {
+                let average = unsafe { from_raw_parts_mut(average_p as *mut u8, value_size) };
+                average.copy_from_slice(&new_average.to_le_bytes()[..value_size]);
+            }
+            0
+        }
+        Err(e) => {
+            debug!("{}", e);
+            -1i64 as u64
+        }
+    }
+}
+
+/* Execution context introspection */
+
+/// Returns the SUIT slot of the program that is currently executing, -1 if the
+/// thread isn't associated with any slot. When a program is called from
+/// another one, it is the slot of the callee.
+pub fn bpf_get_slot(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match local_storage::lookup_slot_number() {
+        Some(slot) => slot as u64,
+        None => -1i64 as u64,
+    }
+}
+
+/// Returns the ID of the job that the program is executing as part of, it is
+/// the same ID that appears in the messages logged by `bpf_log`.
+pub fn bpf_get_job_id(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match job_context::job_id() {
+        Some(job_id) => job_id as u64,
+        None => -1i64 as u64,
+    }
+}
+
+/// Returns how the job was started, see [`job_context::ExecutionModel`].
+pub fn bpf_get_execution_model(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match job_context::execution_model() {
+        Some(model) => model as u64,
+        None => -1i64 as u64,
+    }
+}
+
+/// Returns the number of milliseconds left until the deadline of the job, -1
+/// if the job doesn't have a time budget.
+pub fn bpf_get_remaining_budget_ms(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match job_context::remaining_budget_ms() {
+        Some(remaining) => remaining as u64,
+        None => -1i64 as u64,
+    }
+}
+
+/// Returns the index of the VM worker thread executing the program, -1 if the
+/// program doesn't execute on a worker (e.g. it was started by a short-lived
+/// execution request).
+pub fn bpf_get_worker(_a1: u64, _a2: u64, _a3: u64, _a4: u64, _a5: u64) -> u64 {
+    match job_context::worker() {
+        Some(worker) => worker as u64,
+        None => -1i64 as u64,
+    }
+}
@@ -5,7 +5,7 @@ pub mod rbpf_jit;
 mod vm_manager;
 mod femtocontainer_vm;
 pub mod middleware;
-pub use vm::{VirtualMachine, construct_vm};
+pub use vm::{VirtualMachine, construct_vm, call_program_in_slot, DEFAULT_VM_STACK_SIZE};
 pub use rbpf_vm::RbpfVm;
 pub use timed_vm::TimedVm;
 pub use femtocontainer_vm::FemtoContainerVm;
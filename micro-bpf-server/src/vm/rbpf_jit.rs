@@ -5,7 +5,7 @@ use alloc::{
     string::String,
     vec::Vec,
 };
-use core::{cell::RefCell, slice::from_raw_parts_mut};
+use core::{cell::RefCell, convert::TryFrom, slice::from_raw_parts_mut};
 use log::debug;
 use micro_bpf_common::{
     BinaryFileLayout, HelperAccessListSource, HelperAccessVerification, HelperFunctionID,
@@ -17,12 +17,13 @@ use riot_wrappers::gcoap::PacketBuffer;
 
 use super::{
     middleware::{
+        helper_context::{self, CoapPacket, HelperContext, MemoryRegion},
         helpers::HelperAccessList,
         CoapContext,
     },
     rbpf_vm::map_interpreter,
 };
-use crate::infra::jit_prog_storage::{self};
+use crate::infra::jit_prog_storage::{self, JittedFunction};
 use crate::infra::suit_storage::{self};
 
 #[allow(dead_code)]
@@ -35,7 +36,13 @@ pub struct RbpfJIT<'a> {
     pub recompile: bool,
     pub jit_prog_slot: usize,
     pub jit_program_length: usize,
-    pub jitted_fn: Option<unsafe fn(*mut u8, usize, *mut u8, usize) -> u32>,
+    pub stack_size: usize,
+    pub configuration: VMConfiguration,
+    pub jitted_fn: Option<JittedFunction>,
+    /// The stack of the program is owned by the adapter and passed into the
+    /// jitted program so that it doesn't share the stack with the thread
+    /// executing it.
+    pub stack: Vec<u8>,
 }
 
 impl<'a> RbpfJIT<'a> {
@@ -49,15 +56,44 @@ impl<'a> RbpfJIT<'a> {
             recompile: config.jit_compile,
             jit_prog_slot: config.suit_slot,
             jit_program_length: 0,
+            stack_size: config.stack_size,
+            configuration: config,
             jitted_fn: None,
+            stack: Vec::new(),
         }
     }
+
+    /// Context that the helper functions called by the program can access.
+    /// Apart from the provided regions, the program can always access its
+    /// stack and read its program buffer and the jit storage slot.
+    fn helper_context(&self, mut regions: Vec<MemoryRegion>) -> HelperContext {
+        regions.push(MemoryRegion::read_write(self.stack.as_ptr(), self.stack.len()));
+        if let Ok((slot_start, slot_size)) = jit_prog_storage::get_slot_region(self.jit_prog_slot) {
+            regions.push(MemoryRegion::read_only(slot_start, slot_size));
+        }
+        if let Some(program) = self.program.as_ref() {
+            let program = program.borrow();
+            regions.push(MemoryRegion::read_only(program.as_ptr(), program.len()));
+        }
+        HelperContext::new(self.configuration, self.allowed_helpers.clone(), regions)
+    }
+
+    /// Calls into the jitted program passing in the end of the program stack
+    /// as the frame pointer, as the stack grows downwards.
+    unsafe fn run(&mut self, mem: *mut u8, mem_len: usize) -> u32 {
+        let stack_end = self.stack.as_mut_ptr().add(self.stack.len());
+        self.jitted_fn.unwrap()(mem, mem_len, 0 as *mut u8, 0, stack_end)
+    }
 }
 
 impl<'a> VirtualMachine for RbpfJIT<'a> {
     fn initialize_vm(&mut self) -> Result<(), String> {
         if !self.recompile {
-            self.jitted_fn = Some(jit_prog_storage::get_program_from_slot(self.jit_prog_slot).unwrap());
+            self.jitted_fn = Some(jit_prog_storage::get_program_from_slot(self.jit_prog_slot)?);
+            // The program was compiled for the stack size of the request
+            // that loaded it, which can differ from the current one.
+            self.stack_size = jit_prog_storage::get_stack_size(self.jit_prog_slot)?;
+            self.stack = alloc::vec![0; self.stack_size];
             return Ok(());
         }
         let program = suit_storage::load_program_static(self.jit_prog_slot);
@@ -70,7 +106,7 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
         // We take the list of helpers from the execute request as this is the
         // only one way supported by the raw elf file binary layout that we use for the JIT.
         let mut helpers_map = BTreeMap::new();
-        let helper_access_list = HelperAccessList::from(self.allowed_helpers.clone());
+        let helper_access_list = HelperAccessList::try_from(self.allowed_helpers.clone())?;
 
         for h in helper_access_list.0 {
             helpers_map.insert(h.id as u32, h.function);
@@ -95,6 +131,7 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
                     true,
                     false,
                     rbpf::InterpreterVariant::RawObjectFile,
+                    self.stack_size,
                 )
                 .unwrap();
                 self.jit_program_length = jit_memory.offset;
@@ -107,10 +144,13 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
             self.program = Some(program_cell);
             slot_guard.1 = text_offset;
         }
+        jit_prog_storage::set_stack_size(jit_slot, self.stack_size)?;
+        jit_prog_storage::set_helpers(jit_slot, self.allowed_helpers.clone())?;
+        self.stack = alloc::vec![0; self.stack_size];
         self.jitted_fn = Some(jit_prog_storage::get_program_from_slot(self.jit_prog_slot).unwrap());
         Ok(())
     }
-    fn verify(&self) -> Result<(), String> {
+    fn verify(&mut self) -> Result<(), String> {
         if !self.recompile {
             return Ok(());
         }
@@ -119,8 +159,11 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
         let interpreter = map_interpreter(self.layout);
 
         // Vefiy the program and abort if failed
-        let Ok(()) = rbpf::EbpfVmMbuff::verify_program(interpreter, prog_ref.as_ref())
-        else {
+        let Ok(()) = rbpf::EbpfVmMbuff::verify_program_with_stack_size(
+            interpreter,
+            prog_ref.as_ref(),
+            self.stack_size,
+        ) else {
             Err("Program verification failed")?
         };
 
@@ -137,11 +180,12 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
     }
 
     fn execute(&mut self) -> Result<u64, String> {
+        let _context = helper_context::enter_context(self.helper_context(Vec::new()));
         let ret: u32;
         unsafe {
             // We don't pass any meaningful arguments here as the program doesn't
             // work on a COAP message packet buffer.
-            ret = self.jitted_fn.unwrap()(0 as *mut u8, 0, 0 as *mut u8, 0);
+            ret = self.run(0 as *mut u8, 0);
         }
         debug!("JIT execution successful: {}", ret);
         Ok(ret as u64)
@@ -156,11 +200,38 @@ impl<'a> VirtualMachine for RbpfJIT<'a> {
             from_raw_parts_mut(ctx as *mut u8, CONTEXT_SIZE)
         };
 
+        let coap_packet = unsafe {
+            let ctx = coap_context.as_ptr() as *const CoapContext;
+            CoapPacket {
+                pkt: (*ctx).pkt as usize,
+                buf: (*ctx).buf as usize,
+                len: (*ctx).len,
+            }
+        };
+        let _context = helper_context::enter_context(
+            self.helper_context(alloc::vec![
+                MemoryRegion::read_write(coap_context.as_ptr(), coap_context.len()),
+                MemoryRegion::read_write(coap_packet.buf as *const u8, coap_packet.len),
+            ])
+            .with_coap_packet(coap_packet),
+        );
         let ret: u32;
         unsafe {
             // We don't pass any meaningful arguments here as the program doesn't
             // work on a COAP message packet buffer.
-            ret = self.jitted_fn.unwrap()(coap_context as *mut _ as *mut u8, 0, 0 as *mut u8, 0);
+            ret = self.run(coap_context as *mut _ as *mut u8, 0);
+        }
+        debug!("JIT execution successful: {}", ret);
+        Ok(ret as u64)
+    }
+
+    fn execute_on_buffer(&mut self, buffer: &mut [u8]) -> Result<u64, String> {
+        let _context = helper_context::enter_context(self.helper_context(alloc::vec![
+            MemoryRegion::read_write(buffer.as_ptr(), buffer.len()),
+        ]));
+        let ret: u32;
+        unsafe {
+            ret = self.run(buffer.as_mut_ptr(), buffer.len());
         }
         debug!("JIT execution successful: {}", ret);
         Ok(ret as u64)
@@ -11,12 +11,12 @@ use micro_bpf_common::{
     BinaryFileLayout, HelperAccessListSource, HelperAccessVerification, HelperFunctionID,
     VMConfiguration,
 };
-use micro_bpf_elf_utils::extract_allowed_helpers;
 
 use riot_wrappers::gcoap::PacketBuffer;
 
 use super::middleware::{
-    helpers::HelperAccessList,
+    helper_context::{self, CoapPacket, HelperContext, MemoryRegion},
+    helpers::resolve_helper_access_list,
     CoapContext,
 };
 
@@ -30,6 +30,12 @@ pub struct RbpfVm<'a> {
     pub helper_access_list_source: HelperAccessListSource,
     pub program_length: usize,
     pub suit_slot: usize,
+    pub stack_size: usize,
+    pub configuration: VMConfiguration,
+    /// The stack of the program is owned by the adapter so that the helper
+    /// functions know where it is located.
+    pub stack: Vec<u8>,
+    pub program_region: Option<MemoryRegion>,
 }
 
 impl<'a> RbpfVm<'a> {
@@ -45,8 +51,21 @@ impl<'a> RbpfVm<'a> {
             helper_access_list_source: config.helper_access_list_source,
             program_length: 0,
             suit_slot: config.suit_slot,
+            stack_size: config.stack_size,
+            configuration: config,
+            stack: Vec::new(),
+            program_region: None,
         })
     }
+
+    /// Context that the helper functions called by the program can access.
+    /// Apart from the provided regions, the program can always access its
+    /// stack and read its own program buffer (containing e.g. .rodata).
+    fn helper_context(&self, mut regions: Vec<MemoryRegion>) -> HelperContext {
+        regions.push(MemoryRegion::read_write(self.stack.as_ptr(), self.stack.len()));
+        regions.extend(self.program_region);
+        HelperContext::new(self.configuration, self.allowed_helpers.clone(), regions)
+    }
 }
 
 pub fn map_interpreter(layout: BinaryFileLayout) -> rbpf::InterpreterVariant {
@@ -62,26 +81,25 @@ impl<'a> VirtualMachine for RbpfVm<'a> {
     fn initialize_vm(&mut self) -> Result<(), String> {
         let program = suit_storage::load_program_static(self.suit_slot);
 
-        // We need to make a decision whether we use the helper list that was
-        // sent in the request or read the allowed helpers from the metadata appended
-        // to the program binary.
-        let helper_access_list = match self.helper_access_list_source {
-            micro_bpf_common::HelperAccessListSource::ExecuteRequest => {
-                HelperAccessList::from(self.allowed_helpers.clone())
-            }
-            micro_bpf_common::HelperAccessListSource::BinaryMetadata => {
-                if self.layout == BinaryFileLayout::ExtendedHeader {
-                    HelperAccessList::from(extract_allowed_helpers(program))
-                } else {
-                    Err("Tried to extract allowed helper function indices from an incompatible binary file")?
-                }
-            }
-        };
+        let helper_access_list = resolve_helper_access_list(
+            self.helper_access_list_source,
+            self.layout,
+            &self.allowed_helpers,
+            program,
+        )?;
         self.vm = Some(
             rbpf::EbpfVmMbuff::new(Some(program), map_interpreter(self.layout))
                 .map_err(|e| format!("Error: {:?}", e))?,
         );
+        // The stack size needs to be set before the verification so that
+        // the verifier can check the stack-relative accesses against it.
+        self.vm.as_mut().unwrap().set_stack_size(self.stack_size);
         self.program_length = program.len();
+        self.program_region = Some(MemoryRegion::read_only(program.as_ptr(), program.len()));
+        self.stack = alloc::vec![0; self.stack_size];
+        // From now on the list of allowed helpers reflects the ones that the
+        // program can actually call, regardless of where they were specified.
+        self.allowed_helpers = helper_access_list.0.iter().map(|h| h.id).collect();
         middleware::helpers::register_helpers(
             self.vm.as_mut().unwrap(),
             helper_access_list.0.clone(),
@@ -89,7 +107,7 @@ impl<'a> VirtualMachine for RbpfVm<'a> {
         Ok(())
     }
 
-    fn verify(&self) -> Result<(), String> {
+    fn verify(&mut self) -> Result<(), String> {
         // The VM runs the verification when the new program is loaded into it.
         if let Some(vm) = self.vm.as_ref() {
             vm.verify_loaded_program()
@@ -113,8 +131,9 @@ impl<'a> VirtualMachine for RbpfVm<'a> {
     }
 
     fn execute(&mut self) -> Result<u64, String> {
+        let _context = helper_context::enter_context(self.helper_context(Vec::new()));
         if let Some(vm) = self.vm.as_mut() {
-            vm.execute_program(&alloc::vec![], &alloc::vec![], alloc::vec![])
+            vm.execute_program_with_stack(&alloc::vec![], &alloc::vec![], &mut self.stack, alloc::vec![])
                 .map_err(|e| format!("Error: {:?}", e))
         } else {
             Err("VM not initialised".to_string())
@@ -144,8 +163,26 @@ impl<'a> VirtualMachine for RbpfVm<'a> {
             ((*ctx).buf as *const u8 as u64, (*ctx).len as u64)
         };
 
+        let coap_packet = unsafe {
+            let ctx = pkt_box.as_mut() as *mut _ as *mut CoapContext;
+            CoapPacket {
+                pkt: (*ctx).pkt as usize,
+                buf: (*ctx).buf as usize,
+                len: (*ctx).len,
+            }
+        };
+
+        let _context = helper_context::enter_context(
+            self.helper_context(alloc::vec![
+                MemoryRegion::read_write(coap_context.as_ptr(), coap_context.len()),
+                MemoryRegion::read_write(mem.as_ptr(), mem.len()),
+                MemoryRegion::read_write(pkt_buffer_region.0 as *const u8, pkt_buffer_region.1 as usize),
+            ])
+            .with_coap_packet(coap_packet),
+        );
         if let Some(vm) = self.vm.as_mut() {
-            let result = vm.execute_program(mem, coap_context, alloc::vec![pkt_buffer_region])
+            let result = vm
+                .execute_program_with_stack(mem, coap_context, &mut self.stack, alloc::vec![pkt_buffer_region])
                 .map_err(|e| format!("Error: {:?}", e));
             debug!("CoAP execution result: {:?}", result.clone().unwrap_or(0));
             return result;
@@ -155,6 +192,20 @@ impl<'a> VirtualMachine for RbpfVm<'a> {
         }
     }
 
+    fn execute_on_buffer(&mut self, buffer: &mut [u8]) -> Result<u64, String> {
+        let _context = helper_context::enter_context(self.helper_context(alloc::vec![
+            MemoryRegion::read_write(buffer.as_ptr(), buffer.len()),
+        ]));
+        if let Some(vm) = self.vm.as_mut() {
+            // When no metadata buffer is given, the VM passes the pointer
+            // to the memory buffer to the program in r1.
+            vm.execute_program_with_stack(buffer, &[], &mut self.stack, alloc::vec![])
+                .map_err(|e| format!("Error: {:?}", e))
+        } else {
+            Err("VM not initialised".to_string())
+        }
+    }
+
     fn get_program_length(&self) -> usize {
         return self.program_length;
     }
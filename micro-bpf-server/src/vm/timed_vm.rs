@@ -33,7 +33,7 @@ impl TimedVm {
 }
 
 impl VirtualMachine for TimedVm {
-    fn verify(&self) -> Result<(), String> {
+    fn verify(&mut self) -> Result<(), String> {
         let start = self.time_now();
         let result = self.vm.verify();
         let end = self.time_now();
@@ -69,6 +69,15 @@ impl VirtualMachine for TimedVm {
         result
     }
 
+    fn execute_on_buffer(&mut self, buffer: &mut [u8]) -> Result<u64, String> {
+        let start = self.time_now();
+        let result = self.vm.execute_on_buffer(buffer);
+        let end = self.time_now();
+
+        self.results.borrow_mut().execution_time = end - start;
+        result
+    }
+
     fn full_run(&mut self) -> Result<u64, String> {
         let start = self.time_now();
         self.initialize_vm()?;
@@ -1,13 +1,35 @@
-use alloc::{boxed::Box, string::String, vec::Vec};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use log::debug;
 use micro_bpf_common::{
-    HelperFunctionID, TargetVM, VMConfiguration,
+    BinaryFileLayout, HelperAccessListSource, HelperFunctionID, TargetVM, VMConfiguration,
 };
 use riot_wrappers::gcoap::PacketBuffer;
 
+use crate::infra::{
+    jit_prog_storage,
+    local_storage,
+    suit_storage,
+};
+
 use super::{
-    rbpf_jit::RbpfJIT, FemtoContainerVm, RbpfVm,
+    middleware::{
+        helper_context::{self, MAX_CALL_DEPTH},
+        helper_profiles,
+    },
+    rbpf_jit::RbpfJIT,
+    vm_manager::VM_WORKER_STACK_SIZE,
+    FemtoContainerVm, RbpfVm,
 };
 
+/// Size of the eBPF program stack in bytes that is used if the execution
+/// request doesn't specify one. It is in line with the eBPF specification.
+pub const DEFAULT_VM_STACK_SIZE: usize = 512;
+
+/// The largest eBPF program stack that a VM can be configured with. The program
+/// stacks are allocated on the heap, we only limit them to half of the worker
+/// thread stack to keep the heap usage of the programs in check.
+pub const MAX_VM_STACK_SIZE: usize = VM_WORKER_STACK_SIZE / 2;
+
 /// Structs implementing this interface should allow for executing eBPF programs
 /// both raw and with access to the incoming CoAP packet.
 pub trait VirtualMachine {
@@ -31,7 +53,7 @@ pub trait VirtualMachine {
     /// to the VM here.
     fn initialize_vm(&mut self) -> Result<(), String>;
     /// Verifies the program bytecode after it has been loaded into the VM.
-    fn verify(&self) -> Result<(), String>;
+    fn verify(&mut self) -> Result<(), String>;
     /// Executes a given program and returns its return value.
     fn execute(&mut self) -> Result<u64, String>;
     /// Executes a given eBPF program giving it access to the provided PacketBuffer
@@ -40,6 +62,20 @@ pub trait VirtualMachine {
     /// the packet PDU + payload. The reason for this is that the handler then
     /// needs to know this length when sending the response back.
     fn execute_on_coap_pkt(&mut self, pkt: PacketBuffer) -> Result<u64, String>;
+    /// Loads, verifies and executes the program giving it access to the
+    /// provided argument buffer.
+    fn full_run_on_buffer(&mut self, buffer: &mut [u8]) -> Result<u64, String> {
+        self.initialize_vm()?;
+        self.verify()?;
+        self.execute_on_buffer(buffer)
+    }
+    /// Executes the program passing in the pointer to the provided buffer as its
+    /// first argument. This is used when a program is called by another
+    /// one, in which case the buffer contains the arguments of the call and
+    /// can also be used by the callee to write back its results.
+    fn execute_on_buffer(&mut self, _buffer: &mut [u8]) -> Result<u64, String> {
+        Err("Executing programs on argument buffers is not supported by this VM")?
+    }
     /// Returns the length of the program that is currently loaded into the VM.
     /// This is used for benchmarking, because when we are using the jit, we
     /// don't know the final program size until we execute it.
@@ -52,9 +88,10 @@ pub trait VirtualMachine {
 /// of the VM is tied to the lifetime of the program buffer (as every VM operates
 /// on only one program).
 pub fn construct_vm<'a>(
-    config: VMConfiguration,
+    mut config: VMConfiguration,
     allowed_helpers: Vec<HelperFunctionID>,
 ) -> Result<Box<dyn VirtualMachine>, String> {
+    config.stack_size = validate_stack_size(config.stack_size)?;
 
     if config.jit {
         return Ok(Box::new(RbpfJIT::new(config, allowed_helpers)));
@@ -65,7 +102,158 @@ pub fn construct_vm<'a>(
             return Ok(Box::new(RbpfVm::new(config, allowed_helpers)?));
         }
         TargetVM::FemtoContainer => {
-            return Ok(Box::new(FemtoContainerVm::new(config.suit_slot)));
+            return Ok(Box::new(FemtoContainerVm::new(config, allowed_helpers)?));
         }
     }
 }
+
+/// Executes the program loaded into the given SUIT slot on behalf of the
+/// program that is currently running on this thread and returns the callee's
+/// return value.
+///
+/// The callee is executed by the same VM as its caller using the caller's
+/// configuration (apart from the SUIT slot). VMs that cannot execute programs
+/// on argument buffers return an error. The callee is only allowed to call
+/// helpers that both its own helper access list and the one of the caller
+/// allow for, so that a program cannot escalate its privileges by calling into
+/// some other program. When the caller reads its helper list from the binary
+/// metadata, the callee's list is read from its metadata, otherwise the callee
+/// simply inherits the caller's list.
+/// A jitted caller can only call into a program that has already been
+/// compiled into the JIT storage slot of the callee with a subset of these
+/// helpers, the callee is never recompiled.
+///
+/// The callee slot is pinned for the whole duration of the call so that the
+/// program cannot be replaced between loading and executing it. Nested calls
+/// are limited to [`MAX_CALL_DEPTH`] and are only made if the thread stack has
+/// enough space left for the callee VM.
+///
+/// # Arguments
+///
+/// * `slot` - The index of the SUIT storage slot containing the callee program.
+/// * `argument` - The buffer that is passed into the callee as its only argument.
+pub fn call_program_in_slot(slot: usize, argument: &mut [u8]) -> Result<u64, String> {
+    let Some(caller) = helper_context::current_context() else {
+        Err("Programs can only be called from within a running VM")?
+    };
+
+    if helper_context::call_depth() >= MAX_CALL_DEPTH {
+        Err(format!(
+            "Maximum program call depth of {} exceeded",
+            MAX_CALL_DEPTH
+        ))?;
+    }
+
+    let mut config = caller.configuration;
+    config.suit_slot = slot;
+    // A JIT-compiled program can only call into the program already compiled
+    // into the JIT storage slot of the callee. Recompiling it would free the
+    // slot while the program in it may still be running on another thread.
+    config.jit_compile = false;
+
+    let Some(remaining_stack) = remaining_thread_stack() else {
+        Err("Cannot determine the remaining stack of the thread, nested calls are disabled")?
+    };
+    if remaining_stack < NESTED_CALL_STACK_RESERVE {
+        Err(format!(
+            "Not enough stack left for calling into slot {}: {} [B] left, {} [B] needed",
+            slot, remaining_stack, NESTED_CALL_STACK_RESERVE
+        ))?;
+    }
+
+    let _pin = suit_storage::pin_slot(slot)?;
+
+    let callee_helpers = match config.helper_access_list_source {
+        HelperAccessListSource::ExecuteRequest => caller.allowed_helpers.clone(),
+        HelperAccessListSource::BinaryMetadata => {
+            if config.binary_layout != BinaryFileLayout::ExtendedHeader {
+                Err("Tried to extract allowed helper function indices from an incompatible binary file")?;
+            }
+            let program = suit_storage::load_program_static(slot);
+            helper_profiles::metadata_helpers(program)?
+                .into_iter()
+                .filter(|id| caller.allowed_helpers.contains(id))
+                .collect()
+        }
+    };
+    // The intersected list has been computed above, so the callee VM needs
+    // to take it from the 'request' instead of re-reading the metadata.
+    config.helper_access_list_source = HelperAccessListSource::ExecuteRequest;
+
+    // The helpers that a jitted program can call are fixed when it is
+    // compiled, so it needs to have been compiled with a subset of them.
+    if config.jit {
+        let compiled_helpers = jit_prog_storage::get_helpers(slot)?;
+        if let Some(id) = compiled_helpers.iter().find(|id| !callee_helpers.contains(id)) {
+            Err(format!(
+                "The program in JIT slot {} was compiled with helper {} which the caller cannot call",
+                slot, *id as u32
+            ))?;
+        }
+    }
+
+    debug!(
+        "Calling program in SUIT slot {} from slot {} (depth: {})",
+        slot,
+        caller.configuration.suit_slot,
+        helper_context::call_depth()
+    );
+
+    let result = construct_vm(config, callee_helpers)
+        .and_then(|mut vm| vm.full_run_on_buffer(argument));
+
+    // Loading the callee program has associated this thread with the local
+    // storage of the callee slot, we need to give the caller its storage back.
+    local_storage::register_suit_slot(caller.configuration.suit_slot);
+
+    result
+}
+
+/// Stack space in bytes that a nested VM needs for its own call frames and
+/// the helper functions called by the program.
+const NESTED_CALL_STACK_RESERVE: usize = 1024;
+
+/// Returns the number of bytes left on the stack of the current thread. RIOT
+/// only tracks the stack bounds when DEVELHELP is enabled, otherwise None is
+/// returned.
+fn remaining_thread_stack() -> Option<usize> {
+    extern "C" {
+        fn get_active_thread_stack(start: *mut *mut u8, size: *mut usize) -> i32;
+    }
+    let mut stack_start: *mut u8 = core::ptr::null_mut();
+    let mut stack_size: usize = 0;
+    if unsafe { get_active_thread_stack(&mut stack_start, &mut stack_size) } != 0 {
+        return None;
+    }
+    // The stack grows downwards, so the space left is between the start of
+    // the stack and the current frame.
+    let marker = 0u8;
+    (&marker as *const u8 as usize).checked_sub(stack_start as usize)
+}
+
+/// Checks that the eBPF program stack size requested in the VM configuration
+/// can be allocated by the thread executing the program. The stack size of 0
+/// means that the request didn't specify it and so the default one is used.
+pub fn validate_stack_size(stack_size: usize) -> Result<usize, String> {
+    if stack_size == 0 {
+        return Ok(DEFAULT_VM_STACK_SIZE);
+    }
+
+    // All eBPF load / store instructions operate on at most 8 bytes at a time,
+    // so we require the stack to be aligned to the largest access size.
+    if stack_size % 8 != 0 {
+        Err(format!(
+            "VM stack size of {} [B] is not a multiple of 8 bytes",
+            stack_size
+        ))?;
+    }
+
+    if stack_size > MAX_VM_STACK_SIZE {
+        Err(format!(
+            "VM stack size of {} [B] exceeds the maximum of {} [B] allowed by the worker thread stack",
+            stack_size, MAX_VM_STACK_SIZE
+        ))?;
+    }
+
+    Ok(stack_size)
+}
@@ -16,18 +16,32 @@ use crate::{
     infra::suit_storage::{self},
     model::requests::{VMExecutionCompleteMsg, VMExecutionRequestIPC},
     spawn_thread,
-    vm::construct_vm,
+    vm::{
+        construct_vm,
+        middleware::{
+            helper_profiles,
+            job_context::{self, ExecutionModel},
+        },
+    },
 };
 
+/// Size of the stack of each VM worker thread. The eBPF program stack of the VM
+/// running on a given worker needs to fit into it (see [`super::vm::MAX_VM_STACK_SIZE`]).
+pub const VM_WORKER_STACK_SIZE: usize = 4096;
+
 // Because of the lifetime rules we need to preallocate the stacks of all of the
 // VM worker threads beforehand as static constants.
-static VM_WORKER_0_STACK: Mutex<[u8; 4096]> = Mutex::new([0; 4096]);
-static VM_WORKER_1_STACK: Mutex<[u8; 4096]> = Mutex::new([0; 4096]);
-static VM_WORKER_2_STACK: Mutex<[u8; 4096]> = Mutex::new([0; 4096]);
-static VM_WORKER_3_STACK: Mutex<[u8; 4096]> = Mutex::new([0; 4096]);
+static VM_WORKER_0_STACK: Mutex<[u8; VM_WORKER_STACK_SIZE]> = Mutex::new([0; VM_WORKER_STACK_SIZE]);
+static VM_WORKER_1_STACK: Mutex<[u8; VM_WORKER_STACK_SIZE]> = Mutex::new([0; VM_WORKER_STACK_SIZE]);
+static VM_WORKER_2_STACK: Mutex<[u8; VM_WORKER_STACK_SIZE]> = Mutex::new([0; VM_WORKER_STACK_SIZE]);
+static VM_WORKER_3_STACK: Mutex<[u8; VM_WORKER_STACK_SIZE]> = Mutex::new([0; VM_WORKER_STACK_SIZE]);
 
 pub static RUNNING_WORKERS: Mutex<[bool; 4]> = Mutex::new([false; 4]);
 
+/// PIDs of the VM worker threads ordered by the worker index, they are
+/// registered once the workers are spawned.
+static WORKER_PIDS: Mutex<Vec<riot_sys::kernel_pid_t>> = Mutex::new(Vec::new());
+
 /// The unique identifier of the request type used to start the execution of the VM.
 pub const VM_EXEC_REQUEST: u16 = 23;
 pub const VM_COMPLETE_NOTIFY: u16 = 24;
@@ -136,6 +150,7 @@ impl VMExecutionManager {
                 worker_2.pid().into(),
                 worker_3.pid().into(),
             ];
+            *WORKER_PIDS.lock() = free_workers.clone();
 
             loop {
                 let message = self.message_semantics.receive();
@@ -198,11 +213,18 @@ pid_to_worker_index: BTreeMap<i16, usize>
     }
 }
 
+/// Returns the index of the VM worker thread with a given PID, `None` if the
+/// thread isn't one of the workers.
+pub fn worker_index(pid: riot_sys::kernel_pid_t) -> Option<usize> {
+    WORKER_PIDS.lock().iter().position(|worker_pid| *worker_pid == pid)
+}
+
 /// Each VM worker thread waits for incoming messages from the `VMExecutionManager`
 /// that represent requests to start executing an instance of the eBPF VM. Once
 /// a message is received, the worker starts executing the program until it
-/// terminates. Current limitation is that the worker has no way of preempting
-/// the executing program unless it crashes or voluntarily terminates.
+/// terminates. The worker has no way of preempting the executing program, a
+/// stop request (see [`job_context::request_stop`]) only takes effect once
+/// the program reaches a blocking helper call and chooses to exit.
 fn vm_main_thread(send_port: &CompletionSendPort) {
     loop {
         // Here we use the msg v1 RIOT API as each VM worker cannot pass the
@@ -220,10 +242,20 @@ fn vm_main_thread(send_port: &CompletionSendPort) {
             request.configuration
         );
 
-        if let Ok(mut vm) = construct_vm(
-            request.configuration,
-            request.allowed_helpers,
-        ) {
+        // The job context needs to be set up before the request is consumed
+        // by the VM, it releases the resources held by the job when dropped.
+        let job = match job_context::enter_job(&request, ExecutionModel::LongRunning) {
+            Ok(job) => job,
+            Err(e) => {
+                error!("Failed to set up the job context: {}", e);
+                notify_job_complete(send_port);
+                continue;
+            }
+        };
+
+        let vm = helper_profiles::request_helpers(&request)
+            .and_then(|allowed_helpers| construct_vm(request.configuration, allowed_helpers));
+        if let Ok(mut vm) = vm {
             // We notify everyone that the slot we are using holds a long running VM.
             suit_storage::suit_mark_slot_running(request.configuration.suit_slot as usize);
 
@@ -240,13 +272,20 @@ fn vm_main_thread(send_port: &CompletionSendPort) {
             error!("Failed to initialize the VM.");
         };
 
-        // Now we notify the VM execution manager that the eBPF program has
-        // terminated and so the manager add us to the pool of free workers
-        // and send new execution requests
-        let completion_notification = VMExecutionCompleteMsg::new(thread::get_pid().into());
-        match send_port.lock().try_send(completion_notification) {
-            Ok(()) => info!("VM execution completion notification sent successfully"),
-            Err(_) => error!("Failed to send notification message."),
-        }
+        // The job resources need to be released before the worker is
+        // made available for new execution requests.
+        drop(job);
+        notify_job_complete(send_port);
+    }
+}
+
+/// Notifies the VM execution manager that the eBPF program has terminated
+/// and so the manager can add us to the pool of free workers and send new
+/// execution requests.
+fn notify_job_complete(send_port: &CompletionSendPort) {
+    let completion_notification = VMExecutionCompleteMsg::new(thread::get_pid().into());
+    match send_port.lock().try_send(completion_notification) {
+        Ok(()) => info!("VM execution completion notification sent successfully"),
+        Err(_) => error!("Failed to send notification message."),
     }
 }